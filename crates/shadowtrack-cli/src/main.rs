@@ -0,0 +1,1230 @@
+use crate::output::{
+    print_bestiary, print_effects, print_event_history, print_journal, print_log, print_monsters,
+    print_new_events, print_party, print_status, print_supplies, print_templates,
+};
+use clap::{Parser, Subcommand, ValueEnum};
+use shadowtrack_core::dice::DiceExpr;
+use shadowtrack_core::event_log::{format_clock, normalize_tag, LogFilter};
+use shadowtrack_core::recap::{Recap, RecapFormat};
+use shadowtrack_core::rng::{verify_journal, RngState};
+use shadowtrack_core::save::autosave::{app_data_dir, Autosaver};
+use shadowtrack_core::save::{
+    load_bestiary, load_light_library, load_save, write_bestiary, write_light_library, write_recap,
+    write_save, SaveError,
+};
+use shadowtrack_core::session::Session;
+use shadowtrack_core::state::{
+    DangerLevel, EffectTarget, EffectUnit, EncounterCheck, InitiativeMode, LightSource,
+    LightSourceType, LightState, MoraleRule, ShadowtrackData, Supply, TimedEffect,
+};
+use std::fmt::{Display, Formatter};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+mod output;
+
+/// Run a Shadowtrack delve from the terminal against a save file.
+#[derive(Parser)]
+#[command(name = "shadowtrack-cli", version)]
+struct Cli {
+    /// Save file to read and update.
+    #[arg(short, long, default_value = "save.json")]
+    save: PathBuf,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Create a fresh save file with the default tables.
+    Init {
+        /// Overwrite an existing save file.
+        #[arg(long)]
+        force: bool,
+        /// Seed for the session's rolls. Random if not given.
+        #[arg(long)]
+        seed: Option<u64>,
+    },
+    /// Advance the game clock, processing turns as they come due.
+    Advance {
+        /// Minutes of game time to pass.
+        #[arg(
+            short,
+            long,
+            conflicts_with = "turns",
+            required_unless_present = "turns"
+        )]
+        minutes: Option<u64>,
+        /// Number of turns to process.
+        #[arg(short, long)]
+        turns: Option<u32>,
+    },
+    /// Roll on the encounter table.
+    RollEncounter {
+        /// Skip the encounter check and always roll on the table.
+        #[arg(long)]
+        force: bool,
+    },
+    /// Set the danger level, which controls how often encounters are checked and their odds.
+    Danger {
+        #[arg(value_enum)]
+        level: DangerArg,
+        /// Custom level: check every N turns.
+        #[arg(long, default_value_t = 1)]
+        every: u32,
+        /// Custom level: encounter on this roll or lower.
+        #[arg(long, default_value_t = 1)]
+        chance: u32,
+        /// Custom level: die to roll.
+        #[arg(long, default_value_t = 6)]
+        die: u32,
+    },
+    /// Roll a dice expression such as 2d6+1, 4d6kh3, d20adv or 3d6!.
+    Roll { expression: String },
+    /// Add a light source to the party.
+    AddLight {
+        /// Name for a light nobody in the party carries, such as "Hireling".
+        #[arg(required_unless_present = "owner")]
+        label: Option<String>,
+        /// Party member carrying the light.
+        #[arg(short, long, conflicts_with = "label")]
+        owner: Option<String>,
+        /// Make the light from a named template instead, as listed by `templates`.
+        #[arg(short, long, conflicts_with_all = ["kind", "spell", "radius", "minutes"])]
+        template: Option<String>,
+        #[arg(short, long, value_enum, default_value_t = LightKind::Torch)]
+        kind: LightKind,
+        /// Spell name, for spell lights.
+        #[arg(long, required_if_eq("kind", "spell"))]
+        spell: Option<String>,
+        #[arg(short, long, default_value_t = 30)]
+        radius: u32,
+        #[arg(short, long, default_value_t = 60)]
+        minutes: u32,
+    },
+    /// Snuff, extinguish or relight a light source.
+    Light {
+        #[arg(value_enum)]
+        action: LightActionArg,
+        /// Number of the light, as listed by `status`.
+        number: usize,
+    },
+    /// Remove every burnt out light source.
+    RemoveSpent,
+    /// Show or set the spare torches, oil flasks and candles each character carries.
+    Supplies {
+        /// Character carrying the supplies, added to the party if new. Lists everyone if not
+        /// given.
+        owner: Option<String>,
+        #[arg(long, requires = "owner")]
+        torches: Option<u32>,
+        #[arg(long, requires = "owner")]
+        oil_flasks: Option<u32>,
+        #[arg(long, requires = "owner")]
+        candles: Option<u32>,
+        /// Light a new torch or candle, or a lantern, from the owner's supplies.
+        #[arg(long, value_enum, requires = "owner")]
+        light: Option<SupplyArg>,
+        /// Light the next torch or candle, or refuel a lantern, as soon as one burns out.
+        #[arg(long)]
+        auto_replace: Option<bool>,
+    },
+    /// List the light templates, optionally importing or exporting a shared library file.
+    Templates {
+        /// Add the templates from a library file, replacing any with the same name.
+        #[arg(long)]
+        import: Option<PathBuf>,
+        /// Write the templates to a library file.
+        #[arg(long)]
+        export: Option<PathBuf>,
+    },
+    /// List the monsters encounter tables can reference as {{Name}}.
+    Bestiary {
+        /// Add the monsters from a bestiary file, replacing any with the same name.
+        #[arg(long)]
+        import: Option<PathBuf>,
+        /// Write the bestiary to a file.
+        #[arg(long)]
+        export: Option<PathBuf>,
+    },
+    /// Add, change or remove a party member.
+    Character {
+        #[command(subcommand)]
+        action: CharacterCommand,
+    },
+    /// List the party.
+    Party,
+    /// Start or end a timed effect such as a spell, poison or blessing.
+    Effect {
+        #[command(subcommand)]
+        action: EffectCommand,
+    },
+    /// List the timed effects that are running.
+    Effects,
+    /// Track the last encounter's monsters: their HP, leader and morale.
+    Monster {
+        #[command(subcommand)]
+        action: MonsterCommand,
+    },
+    /// List the last encounter's monsters.
+    Monsters,
+    /// Fight in rounds while exploration time waits.
+    Combat {
+        #[command(subcommand)]
+        action: CombatCommand,
+    },
+    /// Show the clock, turn and light sources.
+    Status,
+    /// Print the roll journal.
+    Journal {
+        /// Only show rolls made on this turn.
+        #[arg(short, long)]
+        turn: Option<u32>,
+        /// Re-roll the journal from the seed and check every result.
+        #[arg(long)]
+        verify: bool,
+    },
+    /// Print the event log.
+    Log {
+        /// Only show the most recent turns.
+        #[arg(short, long)]
+        last: Option<usize>,
+        /// Only show events with this tag. Repeat for events with any of several.
+        #[arg(long = "tag")]
+        tags: Vec<String>,
+        /// Only show events from this turn on.
+        #[arg(long)]
+        from: Option<u32>,
+        /// Only show events up to this turn.
+        #[arg(long)]
+        to: Option<u32>,
+        /// Only show events containing this text, ignoring case.
+        #[arg(long)]
+        search: Option<String>,
+    },
+    /// Tag a logged event, such as with combat, travel, mystery or loot.
+    Tag {
+        /// Turn the event was logged on.
+        turn: u32,
+        /// Which event in the turn, numbered as `log` lists them.
+        event: usize,
+        #[arg(required = true)]
+        tags: Vec<String>,
+        /// Take the tags off instead.
+        #[arg(long)]
+        remove: bool,
+    },
+    /// Write a GM note into the log.
+    Note {
+        text: String,
+        /// Turn to write it on. The current turn if not given.
+        #[arg(long)]
+        turn: Option<u32>,
+        /// Put it after this event in the turn, numbered as `log` lists them. At the end of
+        /// the turn if not given.
+        #[arg(long)]
+        after: Option<usize>,
+    },
+    /// Edit, strike out, restore or move a logged event.
+    Event {
+        #[command(subcommand)]
+        action: EventCommand,
+    },
+    /// Write a recap of the session for the players: a summary, encounters, lights burnt
+    /// out, GM notes and the log by turn.
+    Recap {
+        #[arg(short, long, value_enum, default_value = "markdown")]
+        format: RecapArg,
+        /// File to write it to. Printed if not given.
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Show or change how the desktop app autosaves. These settings are kept with the
+    /// autosaves, not in the save file.
+    Autosave {
+        #[arg(long)]
+        enabled: Option<bool>,
+        /// Seconds between autosaves.
+        #[arg(long)]
+        interval: Option<u64>,
+        /// Also autosave whenever a turn is processed.
+        #[arg(long)]
+        every_turn: Option<bool>,
+        /// How many older autosaves to keep.
+        #[arg(long)]
+        backups: Option<usize>,
+    },
+    /// Write the desktop app's latest autosave, or one of its backups, to the save file.
+    Restore {
+        /// Backup to restore, 1 for the newest.
+        #[arg(long)]
+        backup: Option<usize>,
+        /// Overwrite the save file if it already exists.
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+/// Logged events are picked by their turn and their number in it, as `log` lists them.
+#[derive(Subcommand)]
+enum EventCommand {
+    /// Rewrite an event's text, keeping what it said before.
+    Edit {
+        turn: u32,
+        event: usize,
+        text: String,
+    },
+    /// Strike an event out of the log without losing it.
+    Strike { turn: u32, event: usize },
+    /// Bring back an event that was struck out.
+    Restore { turn: u32, event: usize },
+    /// Move an event to another place in its turn.
+    Move { turn: u32, event: usize, to: usize },
+    /// Show what an event said before each edit.
+    History { turn: u32, event: usize },
+}
+
+#[derive(Subcommand)]
+enum CharacterCommand {
+    /// Add a character to the party, or change one already in it.
+    Set {
+        name: String,
+        /// Current hit points.
+        #[arg(long, allow_negative_numbers = true)]
+        hp: Option<i32>,
+        /// Maximum hit points. Current hit points start full for a new character.
+        #[arg(long)]
+        max_hp: Option<i32>,
+        #[arg(long)]
+        ac: Option<i32>,
+        #[arg(long)]
+        level: Option<u32>,
+        #[arg(long)]
+        luck: Option<u32>,
+        /// Rename the character, along with the lights they carry.
+        #[arg(long)]
+        rename: Option<String>,
+    },
+    /// Give a character a condition such as "Poisoned".
+    Condition { name: String, condition: String },
+    /// Clear a condition from a character.
+    Cure { name: String, condition: String },
+    /// Take a character out of the party. Their lights stay.
+    Remove { name: String },
+}
+
+#[derive(Subcommand)]
+enum EffectCommand {
+    /// Start an effect on the party, a character or a light.
+    Add {
+        name: String,
+        /// Character the effect is on. On the whole party if neither this nor --light is given.
+        #[arg(long, conflicts_with = "light")]
+        on: Option<String>,
+        /// Number of the light the effect is on, as listed by `status`.
+        #[arg(long)]
+        light: Option<usize>,
+        #[arg(long, group = "length", required = true)]
+        minutes: Option<u32>,
+        #[arg(long, group = "length")]
+        turns: Option<u32>,
+        #[arg(long, group = "length")]
+        rounds: Option<u32>,
+        /// Logged when the effect wears off.
+        #[arg(long)]
+        message: Option<String>,
+    },
+    /// End an effect early.
+    End {
+        /// Number of the effect, as listed by `effects`.
+        number: usize,
+    },
+}
+
+#[derive(Subcommand)]
+enum CombatCommand {
+    /// Start a fight between the party and some monsters, and roll initiative.
+    Start {
+        /// A monster in the fight. Repeat for more. The last encounter if not given.
+        #[arg(short, long)]
+        monster: Vec<String>,
+        #[arg(long, value_enum)]
+        initiative: Option<InitiativeArg>,
+        /// Initiative dice everyone rolls, such as d20.
+        #[arg(long)]
+        dice: Option<String>,
+    },
+    /// Roll initiative again, optionally changing how it works or someone's dice.
+    Initiative {
+        #[arg(long, value_enum)]
+        mode: Option<InitiativeArg>,
+        /// A combatant's initiative dice as NAME=DICE, such as Ginny=d20+2. Repeat for more.
+        #[arg(long)]
+        dice: Vec<String>,
+    },
+    /// Pass the turn to the next combatant, starting a new round after the last.
+    Turn,
+    /// Finish the current round and start the next.
+    Round {
+        /// Number of rounds to finish.
+        #[arg(short = 'n', long, default_value_t = 1)]
+        rounds: u32,
+        /// Game time each round takes from now on.
+        #[arg(long)]
+        seconds: Option<u32>,
+    },
+    /// End the fight, adding the time it took to the game clock.
+    End,
+}
+
+#[derive(Subcommand)]
+enum MonsterCommand {
+    /// Damage a creature, such as "Goblin 2", checking morale if that calls for it.
+    Hit { name: String, amount: i64 },
+    /// Heal a creature, up to the HP it was rolled with.
+    Heal { name: String, amount: i64 },
+    /// Mark the creature leading its group. Morale is checked when it dies.
+    Leader { name: String },
+    /// Roll a morale check for a group, such as "Goblin", or change how morale works.
+    Morale {
+        group: Option<String>,
+        #[arg(long, value_enum)]
+        rule: Option<MoraleArg>,
+        /// Check morale when half a group falls or its leader dies.
+        #[arg(long)]
+        automatic: Option<bool>,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum MoraleArg {
+    /// Hold on 2d6 at or under morale.
+    #[value(name = "2d6")]
+    TwoD6,
+    /// Hold on a d20 at or under morale.
+    D20,
+}
+
+impl From<MoraleArg> for MoraleRule {
+    fn from(arg: MoraleArg) -> Self {
+        match arg {
+            MoraleArg::TwoD6 => MoraleRule::TwoD6,
+            MoraleArg::D20 => MoraleRule::D20,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum InitiativeArg {
+    /// Everyone rolls and acts from highest to lowest.
+    Individual,
+    /// The party and the monsters each roll once.
+    Group,
+    /// Shadowdark: the highest roller goes first, then around the table.
+    Clockwise,
+}
+
+impl From<InitiativeArg> for InitiativeMode {
+    fn from(arg: InitiativeArg) -> Self {
+        match arg {
+            InitiativeArg::Individual => InitiativeMode::Individual,
+            InitiativeArg::Group => InitiativeMode::Group,
+            InitiativeArg::Clockwise => InitiativeMode::Clockwise,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum RecapArg {
+    Markdown,
+    /// A single self-contained page.
+    Html,
+    Text,
+}
+
+impl From<RecapArg> for RecapFormat {
+    fn from(arg: RecapArg) -> Self {
+        match arg {
+            RecapArg::Markdown => RecapFormat::Markdown,
+            RecapArg::Html => RecapFormat::Html,
+            RecapArg::Text => RecapFormat::Text,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum DangerArg {
+    Safe,
+    Unsafe,
+    Risky,
+    Deadly,
+    Custom,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum LightActionArg {
+    /// Put the light out on purpose, keeping its remaining time.
+    Snuff,
+    /// The light was blown out or doused.
+    Extinguish,
+    /// Relight a snuffed or extinguished light.
+    Relight,
+    /// Replace a burnt out torch or candle, or refuel a lantern, from the owner's supplies.
+    Replace,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum SupplyArg {
+    Torch,
+    OilFlask,
+    Candle,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum LightKind {
+    Torch,
+    Lantern,
+    Candle,
+    Spell,
+}
+
+fn main() -> ExitCode {
+    exit_code(run(Cli::parse()))
+}
+
+/// Reports a failed command and exits non-zero for it.
+fn exit_code(result: Result<(), CliError>) -> ExitCode {
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("shadowtrack-cli: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Why a command failed, reported with a non-zero exit.
+#[derive(Debug)]
+enum CliError {
+    Save(SaveError),
+    /// The command asked for something the session doesn't have or can't do, like a light that
+    /// isn't there or an unreadable dice expression.
+    Invalid(String),
+}
+
+impl Display for CliError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CliError::Save(e) => write!(f, "{}", e),
+            CliError::Invalid(reason) => write!(f, "{}", reason),
+        }
+    }
+}
+
+impl From<SaveError> for CliError {
+    fn from(err: SaveError) -> Self {
+        CliError::Save(err)
+    }
+}
+
+fn run(cli: Cli) -> Result<(), CliError> {
+    if let Command::Init { force, seed } = cli.command {
+        if cli.save.exists() && !force {
+            return Err(CliError::Invalid(format!(
+                "{} already exists, use --force to overwrite",
+                cli.save.display()
+            )));
+        }
+        let mut data = ShadowtrackData::default();
+        if let Some(seed) = seed {
+            data.rng = RngState { seed, draws: 0 };
+        }
+        write_save(&cli.save, &data)?;
+        println!("Created {}", cli.save.display());
+        return Ok(());
+    }
+
+    if let Command::Autosave {
+        enabled,
+        interval,
+        every_turn,
+        backups,
+    } = cli.command
+    {
+        let Some(dir) = app_data_dir() else {
+            return Err(CliError::Invalid(
+                "No app data directory to autosave to".to_string(),
+            ));
+        };
+        let mut autosaver = Autosaver::new(dir);
+        let settings = &mut autosaver.settings;
+        if let Some(enabled) = enabled {
+            settings.enabled = enabled;
+        }
+        if let Some(interval) = interval {
+            settings.interval_secs = interval.max(1);
+        }
+        if let Some(every_turn) = every_turn {
+            settings.every_turn = every_turn;
+        }
+        if let Some(backups) = backups {
+            settings.backups = backups;
+        }
+        if settings.enabled {
+            println!(
+                "Autosaving every {} seconds{}, keeping {} backups",
+                settings.interval_secs,
+                if settings.every_turn {
+                    " and every processed turn"
+                } else {
+                    ""
+                },
+                settings.backups
+            );
+        } else {
+            println!("Autosave is off");
+        }
+        println!("Autosaves are kept in {}", autosaver.dir().display());
+        if enabled.is_some() || interval.is_some() || every_turn.is_some() || backups.is_some() {
+            autosaver.save_settings()?;
+        }
+        return Ok(());
+    }
+
+    if let Command::Restore { backup, force } = cli.command {
+        if cli.save.exists() && !force {
+            return Err(CliError::Invalid(format!(
+                "{} already exists, use --force to overwrite",
+                cli.save.display()
+            )));
+        }
+        let Some(dir) = app_data_dir() else {
+            return Err(CliError::Invalid(
+                "No app data directory to restore from".to_string(),
+            ));
+        };
+        let autosaver = Autosaver::new(dir);
+        let from = match backup {
+            Some(number) => autosaver.backup_path(number),
+            None => autosaver.latest_path(),
+        };
+        let data = load_save(&from)?;
+        write_save(&cli.save, &data)?;
+        println!(
+            "Restored {} (turn {}) to {}",
+            from.display(),
+            data.turn,
+            cli.save.display()
+        );
+        return Ok(());
+    }
+
+    let mut session = Session::from_data(load_save(&cli.save)?);
+    let before = session.data.event_log.clone();
+
+    match cli.command {
+        Command::Init { .. } | Command::Autosave { .. } | Command::Restore { .. } => {
+            unreachable!()
+        }
+        Command::Advance { minutes, turns } => {
+            if session.data.combat.is_some() {
+                return Err(CliError::Invalid(
+                    "A fight is going on, end it before advancing time".to_string(),
+                ));
+            }
+            if let Some(turns) = turns {
+                session.pass_turns(turns);
+            } else if let Some(minutes) = minutes {
+                session.pass_minutes(minutes);
+            }
+            print_new_events(&before, &session.data.event_log);
+            println!(
+                "Game time {} (turn {})",
+                format_clock(session.data.clock_elapsed),
+                session.data.turn
+            );
+            return Ok(write_save(&cli.save, &session.data)?);
+        }
+        Command::RollEncounter { force } => session.roll_encounter(force),
+        Command::Danger {
+            level,
+            every,
+            chance,
+            die,
+        } => session.set_danger_level(match level {
+            DangerArg::Safe => DangerLevel::Safe,
+            DangerArg::Unsafe => DangerLevel::Unsafe,
+            DangerArg::Risky => DangerLevel::Risky,
+            DangerArg::Deadly => DangerLevel::Deadly,
+            DangerArg::Custom => DangerLevel::Custom(EncounterCheck {
+                every_turns: every.max(1),
+                chance,
+                die: die.max(1),
+            }),
+        }),
+        Command::Roll { expression } => {
+            if let Err(e) = session.roll_dice(&expression) {
+                return Err(CliError::Invalid(format!(
+                    "Invalid dice expression '{}': {}",
+                    expression, e
+                )));
+            }
+        }
+        Command::AddLight {
+            label,
+            owner,
+            template,
+            kind,
+            spell,
+            radius,
+            minutes,
+        } => {
+            let owner = match owner {
+                Some(name) => match session.data.find_character(&name) {
+                    Some(character) => Some(character.id),
+                    None => {
+                        return Err(CliError::Invalid(format!("No character named '{}'", name)));
+                    }
+                },
+                None => None,
+            };
+            let label = label.unwrap_or_default();
+            let mut light = match template {
+                Some(name) => match session.data.find_light_template(&name) {
+                    Some(template) => template.light(label),
+                    None => {
+                        return Err(CliError::Invalid(format!(
+                            "No light template named '{}'",
+                            name
+                        )));
+                    }
+                },
+                None => LightSource {
+                    label,
+                    light_type: match kind {
+                        LightKind::Torch => LightSourceType::Torch,
+                        LightKind::Lantern => LightSourceType::Lantern,
+                        LightKind::Candle => LightSourceType::Candle,
+                        LightKind::Spell => LightSourceType::Spell(spell.unwrap_or_default()),
+                    },
+                    radius_feet: radius,
+                    minutes_remaining: minutes,
+                    id: 0,
+                    owner: None,
+                    last_roll: None,
+                    state: LightState::Lit,
+                    burn: None,
+                },
+            };
+            light.owner = owner;
+            session.add_light(light);
+            if let Some(light) = session.data.light_sources.last() {
+                println!("Added {} for {}", light.light_type, light.label);
+            }
+        }
+        Command::Templates { import, export } => {
+            if let Some(path) = &import {
+                session
+                    .data
+                    .import_light_templates(load_light_library(path)?);
+            }
+            if let Some(path) = &export {
+                write_light_library(path, &session.data.light_templates)?;
+                println!(
+                    "Wrote {} templates to {}",
+                    session.data.light_templates.len(),
+                    path.display()
+                );
+            }
+            print_templates(&session.data);
+            if import.is_none() {
+                return Ok(());
+            }
+        }
+        Command::Bestiary { import, export } => {
+            if let Some(path) = &import {
+                session.data.import_monsters(load_bestiary(path)?);
+            }
+            if let Some(path) = &export {
+                write_bestiary(path, &session.data.bestiary)?;
+                println!(
+                    "Wrote {} monsters to {}",
+                    session.data.bestiary.len(),
+                    path.display()
+                );
+            }
+            print_bestiary(&session.data);
+            if import.is_none() {
+                return Ok(());
+            }
+        }
+        Command::Light { action, number } => {
+            let index = number.wrapping_sub(1);
+            let changed = match action {
+                LightActionArg::Snuff => session.snuff_light(index),
+                LightActionArg::Extinguish => session.extinguish_light(index),
+                LightActionArg::Relight => session.relight_light(index),
+                LightActionArg::Replace => session.replace_from_supply(index),
+            };
+            if !changed {
+                return Err(CliError::Invalid(format!(
+                    "Light {} can't be changed that way",
+                    number
+                )));
+            }
+        }
+        Command::RemoveSpent => {
+            if session.remove_spent_lights() == 0 {
+                println!("No spent light sources");
+                return Ok(());
+            }
+        }
+        Command::Supplies {
+            owner,
+            torches,
+            oil_flasks,
+            candles,
+            light,
+            auto_replace,
+        } => {
+            if let Some(auto_replace) = auto_replace {
+                session.data.supply_settings.auto_replace = auto_replace;
+            }
+            let character = owner.as_deref().map(|name| session.data.character_id(name));
+            if let Some(character) = character.and_then(|id| session.data.character_mut(id)) {
+                let supplies = &mut character.supplies;
+                for (supply, count) in [
+                    (Supply::Torch, torches),
+                    (Supply::OilFlask, oil_flasks),
+                    (Supply::Candle, candles),
+                ] {
+                    if let Some(count) = count {
+                        *supplies.count_mut(supply) = count;
+                    }
+                }
+            }
+            if let (Some(character), Some(supply)) = (character, light) {
+                let supply = match supply {
+                    SupplyArg::Torch => Supply::Torch,
+                    SupplyArg::OilFlask => Supply::OilFlask,
+                    SupplyArg::Candle => Supply::Candle,
+                };
+                if !session.light_from_supply(character, supply) {
+                    return Err(CliError::Invalid(format!(
+                        "{} has {} to spare",
+                        owner.unwrap_or_default(),
+                        supply.count_label(0)
+                    )));
+                }
+            }
+            print_supplies(&session.data);
+            if character.is_none() && auto_replace.is_none() {
+                return Ok(());
+            }
+        }
+        Command::Character { action } => {
+            if !update_character(&mut session.data, action)? {
+                return Ok(());
+            }
+            print_party(&session.data);
+        }
+        Command::Effect { action } => match action {
+            EffectCommand::Add {
+                name,
+                on,
+                light,
+                minutes,
+                turns,
+                rounds,
+                message,
+            } => {
+                let target = match (on, light) {
+                    (Some(name), _) => match session.data.find_character(&name) {
+                        Some(character) => EffectTarget::Character(character.id),
+                        None => {
+                            return Err(CliError::Invalid(format!(
+                                "No character named '{}'",
+                                name
+                            )));
+                        }
+                    },
+                    (None, Some(number)) => {
+                        match session.data.light_sources.get(number.wrapping_sub(1)) {
+                            Some(light) => EffectTarget::Light(light.id),
+                            None => {
+                                return Err(CliError::Invalid(format!(
+                                    "No light numbered {}",
+                                    number
+                                )));
+                            }
+                        }
+                    }
+                    (None, None) => EffectTarget::Party,
+                };
+                let (unit, length) = match (minutes, turns, rounds) {
+                    (Some(minutes), _, _) => (EffectUnit::Minutes, minutes),
+                    (_, Some(turns), _) => (EffectUnit::Turns, turns),
+                    (_, _, rounds) => (EffectUnit::Rounds, rounds.unwrap_or_default()),
+                };
+                let mut effect = TimedEffect::new(name, target, unit, length.max(1));
+                effect.expiry_message = message.unwrap_or_default();
+                session.add_effect(effect);
+            }
+            EffectCommand::End { number } => {
+                if !session.end_effect(number.wrapping_sub(1)) {
+                    return Err(CliError::Invalid(format!("No effect numbered {}", number)));
+                }
+            }
+        },
+        Command::Combat { action } => {
+            let changed = match action {
+                CombatCommand::Start {
+                    mut monster,
+                    initiative,
+                    dice,
+                } => {
+                    if let Some(mode) = initiative {
+                        session.data.combat_settings.initiative = mode.into();
+                    }
+                    if let Some(dice) = dice {
+                        match DiceExpr::parse(&dice) {
+                            Ok(dice) => session.data.combat_settings.initiative_dice = dice,
+                            Err(e) => {
+                                return Err(CliError::Invalid(format!(
+                                    "Invalid dice expression '{}': {}",
+                                    dice, e
+                                )));
+                            }
+                        }
+                    }
+                    if monster.is_empty() {
+                        monster = session.data.last_encounter_foes();
+                    }
+                    session.start_combat(monster)
+                }
+                CombatCommand::Initiative { mode, dice } => {
+                    if let Some(mode) = mode {
+                        session.data.combat_settings.initiative = mode.into();
+                    }
+                    if let Some(combat) = &mut session.data.combat {
+                        for assignment in &dice {
+                            let parsed = assignment
+                                .split_once('=')
+                                .map(|(name, dice)| (name.trim(), DiceExpr::parse(dice.trim())));
+                            let Some((name, Ok(dice))) = parsed else {
+                                return Err(CliError::Invalid(format!(
+                                    "Expected NAME=DICE, got '{}'",
+                                    assignment
+                                )));
+                            };
+                            match combat
+                                .order
+                                .iter_mut()
+                                .find(|combatant| combatant.name.eq_ignore_ascii_case(name))
+                            {
+                                Some(combatant) => combatant.dice = dice,
+                                None => {
+                                    return Err(CliError::Invalid(format!(
+                                        "Nobody named '{}' is in the fight",
+                                        name
+                                    )));
+                                }
+                            }
+                        }
+                    }
+                    session.roll_initiative()
+                }
+                CombatCommand::Turn => session.next_turn(),
+                CombatCommand::Round { rounds, seconds } => {
+                    if let Some(seconds) = seconds {
+                        session.data.combat_settings.round_seconds = seconds.max(1);
+                    }
+                    (0..rounds).all(|_| session.next_round())
+                }
+                CombatCommand::End => session.end_combat(),
+            };
+            if !changed {
+                let reason = match session.data.combat {
+                    Some(_) => "A fight is already going on",
+                    None => "No fight is going on",
+                };
+                return Err(CliError::Invalid(reason.to_string()));
+            }
+        }
+        Command::Monster { action } => match action {
+            MonsterCommand::Hit { name, amount } => damage_monster(&mut session, &name, amount)?,
+            MonsterCommand::Heal { name, amount } => damage_monster(&mut session, &name, -amount)?,
+            MonsterCommand::Leader { name } => {
+                let Some((group, index)) = session.data.find_creature(&name) else {
+                    return Err(CliError::Invalid(format!(
+                        "No creature named '{}' in the last encounter",
+                        name
+                    )));
+                };
+                session.data.last_monsters[group].leader = Some(index);
+                print_monsters(&session.data);
+            }
+            MonsterCommand::Morale {
+                group,
+                rule,
+                automatic,
+            } => {
+                let settings = &mut session.data.morale_settings;
+                if let Some(rule) = rule {
+                    settings.rule = rule.into();
+                }
+                if let Some(automatic) = automatic {
+                    settings.automatic = automatic;
+                }
+                match group {
+                    Some(name) => {
+                        let Some(group) = session
+                            .data
+                            .last_monsters
+                            .iter()
+                            .position(|group| group.monster.name.eq_ignore_ascii_case(&name))
+                        else {
+                            return Err(CliError::Invalid(format!(
+                                "No {} in the last encounter",
+                                name
+                            )));
+                        };
+                        session.check_morale(group, "called for");
+                    }
+                    None => println!(
+                        "Morale is rolled on {}{}",
+                        settings.rule,
+                        if settings.automatic {
+                            ", checked when half a group falls or its leader dies"
+                        } else {
+                            ""
+                        }
+                    ),
+                }
+            }
+        },
+        Command::Monsters => {
+            print_monsters(&session.data);
+            return Ok(());
+        }
+        Command::Effects => {
+            print_effects(&session.data);
+            return Ok(());
+        }
+        Command::Party => {
+            print_party(&session.data);
+            return Ok(());
+        }
+        Command::Status => {
+            print_status(&session.data);
+            return Ok(());
+        }
+        Command::Journal { turn, verify } => {
+            print_journal(&session.data, turn);
+            if verify {
+                match verify_journal(session.data.rng.seed, &session.data.roll_journal) {
+                    None => println!(
+                        "All {} rolls match seed {}",
+                        session.data.roll_journal.len(),
+                        session.data.rng.seed
+                    ),
+                    Some(record) => println!(
+                        "Roll #{} ({}) does not match seed {}",
+                        record.draw, record.purpose, session.data.rng.seed
+                    ),
+                }
+            }
+            return Ok(());
+        }
+        Command::Log {
+            last,
+            tags,
+            from,
+            to,
+            search,
+        } => {
+            let filter = LogFilter {
+                tags: tags.iter().map(|tag| normalize_tag(tag)).collect(),
+                from_turn: from,
+                to_turn: to,
+                text: search.unwrap_or_default(),
+            };
+            print_log(&session.data.event_log, last, &filter);
+            return Ok(());
+        }
+        Command::Tag {
+            turn,
+            event,
+            tags,
+            remove,
+        } => {
+            let index = event_index(&session.data, turn, event)?;
+            for tag in &tags {
+                if remove {
+                    session.untag_event(turn, index, tag);
+                } else {
+                    session.tag_event(turn, index, tag);
+                }
+            }
+        }
+        Command::Note { text, turn, after } => {
+            let turn = turn.unwrap_or(session.data.turn);
+            session.add_note(turn, after, &text);
+            println!("Noted on turn {}: {}", turn, text);
+            return Ok(write_save(&cli.save, &session.data)?);
+        }
+        Command::Recap { format, output } => {
+            match output {
+                Some(output) => {
+                    write_recap(&output, &session.data, format.into())?;
+                    println!("Wrote recap to {}", output.display());
+                }
+                None => print!("{}", Recap::new(&session.data).render(format.into())),
+            }
+            return Ok(());
+        }
+        Command::Event { action } => {
+            if !update_event(&mut session, action)? {
+                return Ok(());
+            }
+        }
+    }
+
+    print_new_events(&before, &session.data.event_log);
+    Ok(write_save(&cli.save, &session.data)?)
+}
+
+/// Index of a logged event from its number in the turn.
+fn event_index(data: &ShadowtrackData, turn: u32, event: usize) -> Result<usize, CliError> {
+    event
+        .checked_sub(1)
+        .filter(|&index| data.logged_event(turn, index).is_some())
+        .ok_or_else(|| CliError::Invalid(format!("No event {} on turn {}", event, turn)))
+}
+
+/// Applies an event command. Returns `false` if nothing changed.
+fn update_event(session: &mut Session, action: EventCommand) -> Result<bool, CliError> {
+    match action {
+        EventCommand::Edit { turn, event, text } => {
+            let index = event_index(&session.data, turn, event)?;
+            Ok(session.edit_event(turn, index, &text))
+        }
+        EventCommand::Strike { turn, event } => {
+            let index = event_index(&session.data, turn, event)?;
+            Ok(session.strike_event(turn, index, true))
+        }
+        EventCommand::Restore { turn, event } => {
+            let index = event_index(&session.data, turn, event)?;
+            Ok(session.strike_event(turn, index, false))
+        }
+        EventCommand::Move { turn, event, to } => {
+            let index = event_index(&session.data, turn, event)?;
+            let moved = to
+                .checked_sub(1)
+                .is_some_and(|to| session.move_event(turn, index, to));
+            if !moved {
+                return Err(CliError::Invalid(format!(
+                    "Turn {} has no place {} to move to",
+                    turn, to
+                )));
+            }
+            Ok(true)
+        }
+        EventCommand::History { turn, event } => {
+            let index = event_index(&session.data, turn, event)?;
+            if let Some(logged) = session.data.logged_event(turn, index) {
+                print_event_history(logged);
+            }
+            Ok(false)
+        }
+    }
+}
+
+/// Applies a character command. Returns `false` if nothing changed.
+fn update_character(
+    data: &mut ShadowtrackData,
+    action: CharacterCommand,
+) -> Result<bool, CliError> {
+    let find = |data: &ShadowtrackData, name: &str| {
+        data.find_character(name)
+            .map(|character| character.id)
+            .ok_or_else(|| CliError::Invalid(format!("No character named '{}'", name)))
+    };
+    match action {
+        CharacterCommand::Set {
+            name,
+            hp,
+            max_hp,
+            ac,
+            level,
+            luck,
+            rename,
+        } => {
+            let new = data.find_character(&name).is_none();
+            let id = data.character_id(&name);
+            let Some(character) = data.character_mut(id) else {
+                return Ok(false);
+            };
+            if let Some(max_hp) = max_hp {
+                character.set_max_hp(max_hp);
+                if new {
+                    character.hp = character.max_hp;
+                }
+            }
+            if let Some(hp) = hp {
+                character.hp = hp.min(character.max_hp);
+            }
+            if let Some(ac) = ac {
+                character.ac = ac;
+            }
+            if let Some(level) = level {
+                character.level = level;
+            }
+            if let Some(luck) = luck {
+                character.luck_tokens = luck;
+            }
+            if let Some(rename) = rename {
+                data.rename_character(id, rename.trim());
+            }
+            Ok(true)
+        }
+        CharacterCommand::Condition { name, condition } => {
+            let id = find(data, &name)?;
+            Ok(data
+                .character_mut(id)
+                .is_some_and(|character| character.add_condition(&condition)))
+        }
+        CharacterCommand::Cure { name, condition } => {
+            let id = find(data, &name)?;
+            let cured = data
+                .character_mut(id)
+                .is_some_and(|character| character.remove_condition(&condition));
+            if !cured {
+                return Err(CliError::Invalid(format!("{} isn't {}", name, condition)));
+            }
+            Ok(true)
+        }
+        CharacterCommand::Remove { name } => {
+            let id = find(data, &name)?;
+            data.remove_character(id);
+            println!("Removed {} from the party", name);
+            Ok(true)
+        }
+    }
+}
+
+/// Damages, or with a negative `amount` heals, the last encounter's creature with this name.
+fn damage_monster(session: &mut Session, name: &str, amount: i64) -> Result<(), CliError> {
+    let Some((group, index)) = session.data.find_creature(name) else {
+        return Err(CliError::Invalid(format!(
+            "No creature named '{}' in the last encounter",
+            name
+        )));
+    };
+    session.damage_monster(group, index, amount);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,303 @@
+use shadowtrack_core::event_log::{format_clock, format_utc, LogFilter, LoggedEvent, TurnEntry};
+use shadowtrack_core::state::ShadowtrackData;
+use std::collections::HashMap;
+
+/// Collects the events in `after` that were not yet logged in `before`, as each event's turn
+/// entry and its index in that entry. Events are matched wherever they sit in the log, so
+/// notes written between earlier events and events moved within a turn aren't mistaken for
+/// others.
+pub fn new_events<'a>(before: &[TurnEntry], after: &'a [TurnEntry]) -> Vec<(&'a TurnEntry, usize)> {
+    let mut seen: HashMap<_, usize> = HashMap::new();
+    for event in before.iter().flat_map(|entry| &entry.events) {
+        *seen.entry(identity(event)).or_default() += 1;
+    }
+    after
+        .iter()
+        .flat_map(|entry| (0..entry.events.len()).map(move |index| (entry, index)))
+        .filter(
+            |(entry, index)| match seen.get_mut(&identity(&entry.events[*index])) {
+                Some(count) if *count > 0 => {
+                    *count -= 1;
+                    false
+                }
+                _ => true,
+            },
+        )
+        .collect()
+}
+
+/// What tells one logged event from another, unchanged by the GM editing, tagging or
+/// striking it out.
+fn identity(event: &LoggedEvent) -> (&str, Option<u64>, Option<u64>) {
+    let text = event
+        .edits
+        .first()
+        .map_or(&event.text, |edit| &edit.previous);
+    (text, event.game_secs, event.wall_clock)
+}
+
+pub fn print_new_events(before: &[TurnEntry], after: &[TurnEntry]) {
+    let mut current_turn = None;
+    for (entry, index) in new_events(before, after) {
+        if current_turn != Some(entry.turn) {
+            println!("Turn {}:", entry.turn);
+            current_turn = Some(entry.turn);
+        }
+        print_event(&entry.events[index], None);
+    }
+}
+
+/// Prints one logged event with its encounter follow-ups. Given its `number` in the turn, as
+/// in the full log, it also shows its game time, tags and roll tree.
+fn print_event(event: &LoggedEvent, number: Option<usize>) {
+    match number {
+        Some(number) => {
+            let mut notes: String = event.tags.iter().map(|tag| format!(" #{}", tag)).collect();
+            if !event.edits.is_empty() {
+                notes.push_str(" (edited)");
+            }
+            if event.deleted {
+                notes.push_str(" (struck out)");
+            }
+            println!(
+                "{:>4}. [{}] {}{}",
+                number,
+                event.game_time_label(),
+                event,
+                notes
+            );
+        }
+        None => println!("   {}", event),
+    }
+    let Some(detail) = &event.detail else {
+        return;
+    };
+    if let Some(encounter) = &detail.encounter {
+        for line in encounter.to_string().lines() {
+            println!("      {}", line);
+        }
+    }
+    if let Some(tree) = detail.roll_tree.as_ref().filter(|_| number.is_some()) {
+        for line in tree.to_string().lines() {
+            println!("      {}", line);
+        }
+    }
+}
+
+/// Prints what a logged event said before each edit, with when it was changed.
+pub fn print_event_history(event: &LoggedEvent) {
+    if event.edits.is_empty() {
+        println!("Never edited: {}", event);
+        return;
+    }
+    for (number, edit) in event.edits.iter().enumerate() {
+        let when = edit.wall_clock.map(format_utc).unwrap_or_default();
+        println!("{:>4}. {}  {}", number + 1, edit.previous, when);
+    }
+    println!(" Now: {}", event);
+}
+
+pub fn print_status(data: &ShadowtrackData) {
+    println!("Game time: {}", format_clock(data.clock_elapsed));
+    println!("Turn: {}", data.turn);
+    println!("Danger: {}", data.danger_level);
+    println!("Illumination: {}", data.illumination());
+    if let Some(next) = data.next_process_minutes {
+        println!("Next turn at: {:02}:00", next);
+    }
+    if let Some(combat) = &data.combat {
+        println!(
+            "Combat: round {} ({} initiative)",
+            combat.round, data.combat_settings.initiative
+        );
+        for (index, combatant) in combat.order.iter().enumerate() {
+            println!(
+                "   {} {} - {} ({})",
+                if index == combat.current { ">" } else { " " },
+                combatant.name,
+                combatant
+                    .initiative
+                    .map_or("-".to_string(), |roll| roll.to_string()),
+                combatant.dice
+            );
+        }
+    }
+    println!();
+    if data.light_sources.is_empty() {
+        println!("No active light sources");
+    } else {
+        println!("Light sources:");
+        for (index, light) in data.light_sources.iter().enumerate() {
+            let burn = light.burn_model(&data.burn_models);
+            println!(
+                "   {}. {} - {} ({}ft), {} left, {}",
+                index + 1,
+                light.label,
+                light.light_type,
+                light.radius_feet,
+                burn.time_left_label(light.minutes_remaining),
+                light.state
+            );
+        }
+    }
+    if !data.characters.is_empty() {
+        println!();
+        print_party(data);
+    }
+    if !data.effects.is_empty() {
+        println!();
+        print_effects(data);
+    }
+    if !data.last_monsters.is_empty() {
+        println!();
+        print_monsters(data);
+    }
+}
+
+pub fn print_monsters(data: &ShadowtrackData) {
+    let Some(encounter) = &data.last_encounter else {
+        println!("No encounter yet");
+        return;
+    };
+    println!("Last encounter: {}", encounter);
+    for group in &data.last_monsters {
+        println!("   {}", group);
+        if let Some(leader) = group.leader {
+            println!("      Led by {}", group.creature_name(leader));
+        }
+    }
+}
+
+pub fn print_effects(data: &ShadowtrackData) {
+    if data.effects.is_empty() {
+        println!("No effects running");
+        return;
+    }
+    println!("Effects:");
+    for (index, effect) in data.effects.iter().enumerate() {
+        println!(
+            "   {}. {} on {}, {} left",
+            index + 1,
+            effect.name,
+            data.effect_target_name(effect.target),
+            effect.time_left_label()
+        );
+    }
+}
+
+pub fn print_party(data: &ShadowtrackData) {
+    if data.characters.is_empty() {
+        println!("No characters in the party");
+        return;
+    }
+    println!("Party:");
+    for character in &data.characters {
+        println!("   {}", character);
+        let lights: Vec<String> = data
+            .character_lights(character.id)
+            .map(|(index, light)| format!("{}. {}", index + 1, light.light_type))
+            .collect();
+        if !lights.is_empty() {
+            println!("      Carries: {}", lights.join(", "));
+        }
+        if !character.supplies.is_empty() {
+            println!("      Spares: {}", character.supplies);
+        }
+    }
+}
+
+pub fn print_supplies(data: &ShadowtrackData) {
+    let settings = &data.supply_settings;
+    println!(
+        "Spares burn for: torch {} min, oil flask {} min, candle {} min{}",
+        settings.torch_minutes,
+        settings.oil_flask_minutes,
+        settings.candle_minutes,
+        if settings.auto_replace {
+            ", replaced automatically"
+        } else {
+            ""
+        }
+    );
+    let carrying: Vec<_> = data
+        .characters
+        .iter()
+        .filter(|character| !character.supplies.is_empty())
+        .collect();
+    if carrying.is_empty() {
+        println!("No spare supplies");
+        return;
+    }
+    for character in carrying {
+        println!("   {} - {}", character.name, character.supplies);
+    }
+}
+
+pub fn print_templates(data: &ShadowtrackData) {
+    if data.light_templates.is_empty() {
+        println!("No light templates");
+        return;
+    }
+    for template in &data.light_templates {
+        let burn = template
+            .burn
+            .as_ref()
+            .unwrap_or_else(|| data.burn_models.for_type(&template.light_type));
+        println!(
+            "   {} - {} ({}ft), {} min, {}",
+            template.name, template.light_type, template.radius_feet, template.minutes, burn
+        );
+    }
+}
+
+pub fn print_bestiary(data: &ShadowtrackData) {
+    if data.bestiary.is_empty() {
+        println!("No monsters in the bestiary");
+        return;
+    }
+    for monster in &data.bestiary {
+        println!(
+            "   {} ({} appearing) - {}",
+            monster.name, monster.number_appearing, monster
+        );
+    }
+}
+
+/// Prints the events `filter` lets through from the last `last` turns, numbered within each
+/// turn for tagging.
+pub fn print_log(event_log: &[TurnEntry], last: Option<usize>, filter: &LogFilter) {
+    if event_log.is_empty() {
+        println!("No events logged");
+        return;
+    }
+    let skip = last.map_or(0, |last| event_log.len().saturating_sub(last));
+    let shown = filter.apply(&event_log[skip..]);
+    if shown.is_empty() {
+        println!("No events match the filter");
+    }
+    for (entry, indices) in shown {
+        println!("Turn {}:", entry.turn);
+        for index in indices {
+            print_event(&entry.events[index], Some(index + 1));
+        }
+    }
+}
+
+pub fn print_journal(data: &ShadowtrackData, turn: Option<u32>) {
+    println!("Seed: {}  Draws: {}", data.rng.seed, data.rng.draws);
+    let mut current_turn = None;
+    for record in data
+        .roll_journal
+        .iter()
+        .filter(|record| turn.is_none_or(|turn| record.turn == turn))
+    {
+        if current_turn != Some(record.turn) {
+            println!("Turn {}:", record.turn);
+            current_turn = Some(record.turn);
+        }
+        println!(
+            "   #{} {}: {} ({}-{})",
+            record.draw, record.purpose, record.result, record.min, record.max
+        );
+    }
+}
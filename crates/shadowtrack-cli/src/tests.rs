@@ -0,0 +1,139 @@
+#[cfg(test)]
+mod cli {
+    use crate::output::new_events;
+    use crate::{exit_code, run, Cli, CliError};
+    use clap::Parser;
+    use shadowtrack_core::event_log::{LogEvent, LoggedEvent, TurnEntry};
+    use shadowtrack_core::save::load_save;
+    use std::process::ExitCode;
+
+    fn message(text: &str) -> LoggedEvent {
+        LoggedEvent::new(LogEvent::Message, text, 0)
+    }
+
+    #[test]
+    fn new_events_only_returns_unseen_entries() {
+        let before = vec![TurnEntry {
+            turn: 1,
+            events: vec![message("No encounter")],
+        }];
+        let mut after = before.clone();
+        after[0].events.push(message("!ENCOUNTER! - Goblin scouts"));
+        after.push(TurnEntry {
+            turn: 2,
+            events: vec![message("A rat darts between your feet.")],
+        });
+
+        let events: Vec<(u32, &str)> = new_events(&before, &after)
+            .into_iter()
+            .map(|(entry, index)| (entry.turn, entry.events[index].text.as_str()))
+            .collect();
+        assert_eq!(
+            events,
+            vec![
+                (1, "!ENCOUNTER! - Goblin scouts"),
+                (2, "A rat darts between your feet.")
+            ]
+        );
+        assert!(new_events(&after, &after).is_empty());
+    }
+
+    #[test]
+    fn new_events_ignore_notes_and_moves_among_old_events() {
+        let mut before = vec![TurnEntry {
+            turn: 1,
+            events: vec![message("No encounter"), message("Ginny's torch gutters")],
+        }];
+        before[0].events[1].game_secs = Some(600);
+
+        // A note written between two old events is the only new one.
+        let mut after = before.clone();
+        after[0]
+            .events
+            .insert(1, message("The door was already open"));
+        let events: Vec<&str> = new_events(&before, &after)
+            .into_iter()
+            .map(|(entry, index)| entry.events[index].text.as_str())
+            .collect();
+        assert_eq!(events, vec!["The door was already open"]);
+
+        // Moved and edited events aren't new.
+        let mut after = before.clone();
+        after[0].events.swap(0, 1);
+        after[0].events[0].edit("Ginny's torch gutters low");
+        assert!(new_events(&before, &after).is_empty());
+    }
+
+    #[test]
+    fn commands_change_the_save() {
+        let save = std::env::temp_dir().join(format!("cli_commands_{}.json", std::process::id()));
+        let save_arg = save.to_str().unwrap();
+        let command = |args: &[&str]| {
+            let cli = Cli::parse_from(["shadowtrack-cli", "--save", save_arg].iter().chain(args));
+            run(cli).unwrap();
+            load_save(&save).unwrap()
+        };
+        command(&["init", "--force", "--seed", "7"]);
+
+        let data = command(&["character", "set", "Ginny", "--max-hp", "8"]);
+        assert_eq!(data.characters[0].name, "Ginny");
+        assert_eq!(data.characters[0].hp, 8);
+        let data = command(&["add-light", "--owner", "ginny", "--minutes", "30"]);
+        assert_eq!(data.light_sources[0].minutes_remaining, 30);
+        assert_eq!(data.light_sources[0].owner, Some(data.characters[0].id));
+        let data = command(&["add-light", "Hireling", "--kind", "candle"]);
+        assert_eq!(data.light_sources[1].owner, None);
+        assert_eq!(data.characters.len(), 1);
+
+        let data = command(&["advance", "--turns", "2"]);
+        assert_eq!(data.turn, 2);
+        assert_eq!(data.clock_elapsed, 20 * 60);
+
+        let data = command(&["note", "Heard drums", "--after", "1"]);
+        let entry = data.event_log.iter().find(|e| e.turn == 2).unwrap();
+        assert_eq!(entry.events[1].text, "Heard drums");
+        let data = command(&["event", "move", "2", "2", "1"]);
+        let entry = data.event_log.iter().find(|e| e.turn == 2).unwrap();
+        assert_eq!(entry.events[0].text, "Heard drums");
+        let data = command(&["tag", "2", "1", "Mystery"]);
+        let entry = data.event_log.iter().find(|e| e.turn == 2).unwrap();
+        assert!(entry.events[0].tags.contains(&"mystery".to_string()));
+        let data = command(&["event", "strike", "2", "1"]);
+        let entry = data.event_log.iter().find(|e| e.turn == 2).unwrap();
+        assert!(entry.events[0].deleted);
+
+        let data = command(&["character", "remove", "Ginny"]);
+        assert!(data.characters.is_empty());
+
+        std::fs::remove_file(save).unwrap();
+    }
+
+    #[test]
+    fn failures_exit_non_zero() {
+        assert_eq!(exit_code(Ok(())), ExitCode::SUCCESS);
+        let failed = Err(CliError::Invalid("No light 3".to_string()));
+        assert_eq!(exit_code(failed), ExitCode::FAILURE);
+    }
+
+    #[test]
+    fn failed_commands_are_errors() {
+        let save = std::env::temp_dir().join(format!("cli_failures_{}.json", std::process::id()));
+        let save_arg = save.to_str().unwrap();
+        let command = |args: &[&str]| {
+            let cli = Cli::parse_from(["shadowtrack-cli", "--save", save_arg].iter().chain(args));
+            run(cli)
+        };
+        command(&["init", "--force"]).unwrap();
+
+        assert!(command(&["roll", "2d"]).is_err());
+        assert!(command(&["light", "snuff", "3"]).is_err());
+        assert!(command(&["event", "strike", "1", "1"]).is_err());
+        assert!(command(&["monster", "hit", "Goblin", "2"]).is_err());
+        assert!(command(&["add-light", "--owner", "Ginny"]).is_err());
+        assert!(command(&["init"]).is_err());
+        assert!(command(&["restore"]).is_err());
+        assert!(command(&["roll", "2d6"]).is_ok());
+
+        std::fs::remove_file(save).unwrap();
+    }
+}
@@ -0,0 +1,243 @@
+use crate::dice::DiceExpr;
+use crate::rng::RandomSource;
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+
+/// A creature in the bestiary, referenced from encounter tables as `{{Name}}`.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct Monster {
+    pub name: String,
+    /// Rolled for each creature's hit points, such as "2d8".
+    pub hp: DiceExpr,
+    pub ac: i32,
+    /// Attacks as written in a stat block, such as "1 club +0 (1d4)".
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub attacks: String,
+    /// Morale score. The creatures hold if a morale roll comes in at or under it.
+    pub morale: u32,
+    /// Rolled for how many turn up, such as "2d4".
+    pub number_appearing: DiceExpr,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub notes: String,
+}
+
+impl Monster {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            hp: DiceExpr::dice(1, 8),
+            ac: 10,
+            attacks: String::new(),
+            morale: 7,
+            number_appearing: DiceExpr::constant(1),
+            notes: String::new(),
+        }
+    }
+
+    /// Rolls how many turn up and the hit points of each, at least one of each.
+    pub fn roll_group(&self, rng: &mut impl RandomSource) -> MonsterGroup {
+        rng.set_purpose(format!("Number appearing: {}", self.name).as_str());
+        let count = self.number_appearing.roll(rng).total.max(1);
+        rng.set_purpose(format!("Hit points: {}", self.name).as_str());
+        let hp = (0..count).map(|_| self.hp.roll(rng).total.max(1)).collect();
+        MonsterGroup::new(self.clone(), hp)
+    }
+}
+
+impl Display for Monster {
+    /// A compact stat line, such as `AC 11, HP 1d6, club +0 (1d4), MOR 7`.
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "AC {}, HP {}", self.ac, self.hp)?;
+        if !self.attacks.is_empty() {
+            write!(f, ", {}", self.attacks)?;
+        }
+        write!(f, ", MOR {}", self.morale)
+    }
+}
+
+/// The creatures that turned up for an encounter, with the stat block they were rolled from
+/// so the log still reads the same after the bestiary changes.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct MonsterGroup {
+    pub monster: Monster,
+    /// Rolled hit points, one per creature.
+    pub hp: Vec<i64>,
+    /// Damage each creature has taken since the encounter was rolled.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub damage: Vec<i64>,
+    /// Index of the creature leading the group. Its death calls for a morale check.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub leader: Option<usize>,
+    /// Whether morale has been checked for half the group falling.
+    #[serde(default)]
+    pub half_fallen_checked: bool,
+    /// Whether morale has been checked for the leader dying.
+    #[serde(default)]
+    pub leader_checked: bool,
+    /// The group failed a morale check and is running or surrendering.
+    #[serde(default)]
+    pub fled: bool,
+}
+
+impl MonsterGroup {
+    pub fn new(monster: Monster, hp: Vec<i64>) -> Self {
+        Self {
+            monster,
+            hp,
+            damage: vec![],
+            leader: None,
+            half_fallen_checked: false,
+            leader_checked: false,
+            fled: false,
+        }
+    }
+
+    /// A name for each creature, numbered when there's more than one: "Goblin 1", "Goblin 2".
+    pub fn creature_names(&self) -> Vec<String> {
+        (0..self.hp.len())
+            .map(|index| self.creature_name(index))
+            .collect()
+    }
+
+    pub fn creature_name(&self, index: usize) -> String {
+        match self.hp.len() {
+            1 => self.monster.name.clone(),
+            _ => format!("{} {}", self.monster.name, index + 1),
+        }
+    }
+
+    /// Hit points the creature has left. Zero once it has fallen.
+    pub fn hp_left(&self, index: usize) -> i64 {
+        self.hp[index] - self.damage.get(index).copied().unwrap_or(0)
+    }
+
+    pub fn is_dead(&self, index: usize) -> bool {
+        self.hp_left(index) <= 0
+    }
+
+    /// Dead, or gone with the rest of a group that fled.
+    pub fn is_out(&self, index: usize) -> bool {
+        self.fled || self.is_dead(index)
+    }
+
+    /// How many creatures have fallen.
+    pub fn fallen(&self) -> usize {
+        (0..self.hp.len())
+            .filter(|&index| self.is_dead(index))
+            .count()
+    }
+
+    /// Damages a creature, or heals it if `amount` is negative, between zero and its rolled HP.
+    pub fn take_damage(&mut self, index: usize, amount: i64) {
+        self.damage.resize(self.hp.len(), 0);
+        self.damage[index] = (self.damage[index] + amount).clamp(0, self.hp[index].max(0));
+    }
+
+    /// The creature's HP as `4`, `1/4` once hurt, or `dead`.
+    pub fn hp_label(&self, index: usize) -> String {
+        match self.hp_left(index) {
+            left if left <= 0 => "dead".to_string(),
+            left if left == self.hp[index] => left.to_string(),
+            left => format!("{}/{}", left, self.hp[index]),
+        }
+    }
+}
+
+impl Display for MonsterGroup {
+    /// Such as `3 × Goblin (HP 4, 1/2, dead): AC 11, club +0 (1d4), MOR 7`.
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let hp: Vec<String> = (0..self.hp.len())
+            .map(|index| self.hp_label(index))
+            .collect();
+        let monster = &self.monster;
+        write!(
+            f,
+            "{} × {} (HP {}): AC {}",
+            self.hp.len(),
+            monster.name,
+            hp.join(", "),
+            monster.ac
+        )?;
+        if !monster.attacks.is_empty() {
+            write!(f, ", {}", monster.attacks)?;
+        }
+        write!(f, ", MOR {}", monster.morale)?;
+        if self.fled {
+            write!(f, ", fled")?;
+        }
+        Ok(())
+    }
+}
+
+/// Replaces each `{{Name}}` in a table result with the bare name, returning the plain text and
+/// the names referenced in order. An unclosed `{{` is left as written.
+pub fn strip_monster_references(text: &str) -> (String, Vec<String>) {
+    let mut plain = String::with_capacity(text.len());
+    let mut names = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        let name = rest[start + 2..start + 2 + end].trim();
+        plain.push_str(&rest[..start]);
+        plain.push_str(name);
+        if !name.is_empty() {
+            names.push(name.to_string());
+        }
+        rest = &rest[start + 2 + end + 2..];
+    }
+    plain.push_str(rest);
+    (plain, names)
+}
+
+/// Creatures for the default encounter table.
+pub fn default_bestiary() -> Vec<Monster> {
+    vec![
+        Monster {
+            name: "Goblin".to_string(),
+            hp: DiceExpr::dice(1, 6),
+            ac: 11,
+            attacks: "1 club +0 (1d4)".to_string(),
+            morale: 7,
+            number_appearing: DiceExpr::dice(2, 4),
+            notes: String::new(),
+        },
+        Monster {
+            name: "Skeleton".to_string(),
+            hp: DiceExpr::dice(2, 8),
+            ac: 13,
+            attacks: "1 shortsword +1 (1d6)".to_string(),
+            morale: 12,
+            number_appearing: DiceExpr::dice(1, 6),
+            notes: String::new(),
+        },
+        Monster {
+            name: "Gray Ooze".to_string(),
+            hp: DiceExpr::dice(3, 8),
+            ac: 9,
+            attacks: "1 touch +2 (1d8 acid)".to_string(),
+            morale: 12,
+            number_appearing: DiceExpr::constant(1),
+            notes: String::new(),
+        },
+        Monster {
+            name: "Bat Swarm".to_string(),
+            hp: DiceExpr::dice(2, 8),
+            ac: 12,
+            attacks: "1 bite +1 (1d4)".to_string(),
+            morale: 8,
+            number_appearing: DiceExpr::dice(1, 2),
+            notes: String::new(),
+        },
+        Monster {
+            name: "Mimic".to_string(),
+            hp: DiceExpr::dice(4, 8),
+            ac: 12,
+            attacks: "1 bite +3 (2d4)".to_string(),
+            morale: 9,
+            number_appearing: DiceExpr::constant(1),
+            notes: String::new(),
+        },
+    ]
+}
@@ -0,0 +1,510 @@
+use crate::rng::RandomSource;
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+/// Upper bound on dice in one term, and on extra dice from explosions.
+const MAX_DICE: u32 = 1000;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiceError {
+    Empty,
+    UnexpectedChar(char, usize),
+    UnexpectedEnd,
+    InvalidNumber(usize),
+    ZeroSides,
+    TooManyDice(u32),
+    KeepTooMany { keep: u32, count: u32 },
+    ExplodingD1,
+}
+
+impl Display for DiceError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            DiceError::Empty => write!(f, "Empty dice expression"),
+            DiceError::UnexpectedChar(c, pos) => {
+                write!(f, "Unexpected '{}' at position {}", c, pos)
+            }
+            DiceError::UnexpectedEnd => write!(f, "Dice expression ended unexpectedly"),
+            DiceError::InvalidNumber(pos) => write!(f, "Invalid number at position {}", pos),
+            DiceError::ZeroSides => write!(f, "Dice need at least one side"),
+            DiceError::TooManyDice(count) => {
+                write!(f, "Too many dice: {} (max {})", count, MAX_DICE)
+            }
+            DiceError::KeepTooMany { keep, count } => {
+                write!(f, "Can't keep {} of {} dice", keep, count)
+            }
+            DiceError::ExplodingD1 => write!(f, "A one-sided die can't explode"),
+        }
+    }
+}
+
+/// Which dice of a term count towards its total.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Keep {
+    All,
+    Highest(u32),
+    Lowest(u32),
+}
+
+/// Rolls a term twice and keeps the better or worse total.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Advantage {
+    None,
+    Advantage,
+    Disadvantage,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DiceTerm {
+    pub count: u32,
+    pub sides: u32,
+    pub exploding: bool,
+    pub keep: Keep,
+    pub advantage: Advantage,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Term {
+    Dice(DiceTerm),
+    Constant(i64),
+}
+
+/// A parsed dice expression such as `2d6+1`, `4d6kh3`, `d20adv` or `3d6!`.
+///
+/// Supported syntax, terms joined with `+` or `-`:
+/// - `NdS`, where `N` defaults to 1 and `S` may be `%` for d100
+/// - `!` after the sides: dice that roll their maximum are rolled again and added
+/// - `khN` / `klN` (or `kN`): keep the highest / lowest `N` dice
+/// - `adv` / `dis`: roll the term twice and keep the higher / lower total
+/// - plain integers as modifiers
+///
+/// Saved as its notation, e.g. `"2d6+1"`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct DiceExpr {
+    pub terms: Vec<(i64, Term)>,
+}
+
+/// A single die as rolled.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct DieRoll {
+    pub value: u32,
+    pub kept: bool,
+    pub exploded: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum TermRoll {
+    Dice {
+        sign: i64,
+        term: DiceTerm,
+        /// One set of dice, or two when rolled with advantage or disadvantage.
+        attempts: Vec<Vec<DieRoll>>,
+        /// Index into `attempts` of the set that counts.
+        chosen: usize,
+        subtotal: i64,
+    },
+    Constant(i64),
+}
+
+/// The result of rolling a [`DiceExpr`], with every die kept for the log.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DiceRoll {
+    pub expression: String,
+    pub terms: Vec<TermRoll>,
+    pub total: i64,
+}
+
+impl DiceExpr {
+    pub fn parse(input: &str) -> Result<Self, DiceError> {
+        Parser::new(input).parse()
+    }
+
+    /// A single `NdS` term.
+    pub fn dice(count: u32, sides: u32) -> Self {
+        Self {
+            terms: vec![(
+                1,
+                Term::Dice(DiceTerm {
+                    count,
+                    sides,
+                    exploding: false,
+                    keep: Keep::All,
+                    advantage: Advantage::None,
+                }),
+            )],
+        }
+    }
+
+    /// A fixed number, rolled without dice.
+    pub fn constant(value: i64) -> Self {
+        Self {
+            terms: vec![(1, Term::Constant(value))],
+        }
+    }
+
+    pub fn roll(&self, rng: &mut impl RandomSource) -> DiceRoll {
+        let terms: Vec<TermRoll> = self
+            .terms
+            .iter()
+            .map(|(sign, term)| match term {
+                Term::Constant(value) => TermRoll::Constant(sign * value),
+                Term::Dice(dice) => roll_term(*sign, dice, rng),
+            })
+            .collect();
+        let total = terms
+            .iter()
+            .map(|term| match term {
+                TermRoll::Dice { subtotal, .. } => *subtotal,
+                TermRoll::Constant(value) => *value,
+            })
+            .sum();
+
+        DiceRoll {
+            expression: self.to_string(),
+            terms,
+            total,
+        }
+    }
+
+    /// Lowest and highest totals the expression can produce, ignoring explosions.
+    pub fn range(&self) -> (i64, i64) {
+        self.terms.iter().fold((0, 0), |(min, max), (sign, term)| {
+            let (lo, hi) = match term {
+                Term::Constant(value) => (*value, *value),
+                Term::Dice(dice) => {
+                    let kept = match dice.keep {
+                        Keep::All => dice.count,
+                        Keep::Highest(n) | Keep::Lowest(n) => n,
+                    } as i64;
+                    (kept, kept * dice.sides as i64)
+                }
+            };
+            if *sign < 0 {
+                (min - hi, max - lo)
+            } else {
+                (min + lo, max + hi)
+            }
+        })
+    }
+}
+
+impl FromStr for DiceExpr {
+    type Err = DiceError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl TryFrom<String> for DiceExpr {
+    type Error = DiceError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        Self::parse(&s)
+    }
+}
+
+impl From<DiceExpr> for String {
+    fn from(expression: DiceExpr) -> Self {
+        expression.to_string()
+    }
+}
+
+impl Display for DiceExpr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for (i, (sign, term)) in self.terms.iter().enumerate() {
+            if *sign < 0 {
+                write!(f, "-")?;
+            } else if i > 0 {
+                write!(f, "+")?;
+            }
+            match term {
+                Term::Constant(value) => write!(f, "{}", value)?,
+                Term::Dice(dice) => write!(f, "{}", dice)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Display for DiceTerm {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.count != 1 {
+            write!(f, "{}", self.count)?;
+        }
+        write!(f, "d{}", self.sides)?;
+        if self.exploding {
+            write!(f, "!")?;
+        }
+        match self.keep {
+            Keep::All => {}
+            Keep::Highest(n) => write!(f, "kh{}", n)?,
+            Keep::Lowest(n) => write!(f, "kl{}", n)?,
+        }
+        match self.advantage {
+            Advantage::None => Ok(()),
+            Advantage::Advantage => write!(f, "adv"),
+            Advantage::Disadvantage => write!(f, "dis"),
+        }
+    }
+}
+
+impl Display for DiceRoll {
+    /// Formats as e.g. `4d6kh3+1: [5, 4, (1), 3] + 1 = 13`, with dropped dice in parentheses
+    /// and exploded dice marked with `!`.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: ", self.expression)?;
+        for (i, term) in self.terms.iter().enumerate() {
+            let sign = match term {
+                TermRoll::Dice { sign, .. } => *sign,
+                TermRoll::Constant(value) => value.signum(),
+            };
+            if i > 0 {
+                write!(f, " {} ", if sign < 0 { "-" } else { "+" })?;
+            } else if sign < 0 {
+                write!(f, "-")?;
+            }
+            match term {
+                TermRoll::Constant(value) => write!(f, "{}", value.abs())?,
+                TermRoll::Dice {
+                    attempts, chosen, ..
+                } => {
+                    for (n, dice) in attempts.iter().enumerate() {
+                        if n > 0 {
+                            write!(f, " ")?;
+                        }
+                        let faces: Vec<String> = dice
+                            .iter()
+                            .map(|die| {
+                                let face = if die.exploded {
+                                    format!("{}!", die.value)
+                                } else {
+                                    die.value.to_string()
+                                };
+                                if die.kept && n == *chosen {
+                                    face
+                                } else {
+                                    format!("({})", face)
+                                }
+                            })
+                            .collect();
+                        write!(f, "[{}]", faces.join(", "))?;
+                    }
+                }
+            }
+        }
+        write!(f, " = {}", self.total)
+    }
+}
+
+fn roll_term(sign: i64, term: &DiceTerm, rng: &mut impl RandomSource) -> TermRoll {
+    let attempt_count = match term.advantage {
+        Advantage::None => 1,
+        _ => 2,
+    };
+    let attempts: Vec<Vec<DieRoll>> = (0..attempt_count)
+        .map(|_| roll_dice_set(term, rng))
+        .collect();
+    let totals: Vec<i64> = attempts.iter().map(|dice| kept_total(dice)).collect();
+    let chosen = match term.advantage {
+        Advantage::Advantage if totals[1] > totals[0] => 1,
+        Advantage::Disadvantage if totals[1] < totals[0] => 1,
+        _ => 0,
+    };
+
+    TermRoll::Dice {
+        sign,
+        term: term.clone(),
+        subtotal: sign * totals[chosen],
+        attempts,
+        chosen,
+    }
+}
+
+fn roll_dice_set(term: &DiceTerm, rng: &mut impl RandomSource) -> Vec<DieRoll> {
+    let mut dice = Vec::with_capacity(term.count as usize);
+    let mut extra = 0;
+    for _ in 0..term.count {
+        let mut value = rng.roll_range(1, term.sides);
+        dice.push(DieRoll {
+            value,
+            kept: true,
+            exploded: false,
+        });
+        while term.exploding && value == term.sides && extra < MAX_DICE {
+            extra += 1;
+            value = rng.roll_range(1, term.sides);
+            dice.push(DieRoll {
+                value,
+                kept: true,
+                exploded: true,
+            });
+        }
+    }
+
+    let (keep, highest) = match term.keep {
+        Keep::All => return dice,
+        Keep::Highest(n) => (n as usize, true),
+        Keep::Lowest(n) => (n as usize, false),
+    };
+    let mut order: Vec<usize> = (0..dice.len()).collect();
+    // Stable sort so ties keep the earliest dice.
+    order.sort_by(|&a, &b| {
+        if highest {
+            dice[b].value.cmp(&dice[a].value)
+        } else {
+            dice[a].value.cmp(&dice[b].value)
+        }
+    });
+    for &index in order.iter().skip(keep) {
+        dice[index].kept = false;
+    }
+    dice
+}
+
+fn kept_total(dice: &[DieRoll]) -> i64 {
+    dice.iter()
+        .filter(|die| die.kept)
+        .map(|die| die.value as i64)
+        .sum()
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn new(input: &str) -> Self {
+        Self {
+            chars: input
+                .chars()
+                .filter(|c| !c.is_whitespace())
+                .map(|c| c.to_ascii_lowercase())
+                .collect(),
+            pos: 0,
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn eat(&mut self, s: &str) -> bool {
+        let matches = s
+            .chars()
+            .enumerate()
+            .all(|(i, c)| self.chars.get(self.pos + i) == Some(&c));
+        if matches {
+            self.pos += s.len();
+        }
+        matches
+    }
+
+    fn number(&mut self) -> Result<Option<u32>, DiceError> {
+        let start = self.pos;
+        while matches!(self.peek(), Some(c) if c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        if start == self.pos {
+            return Ok(None);
+        }
+        let digits: String = self.chars[start..self.pos].iter().collect();
+        digits
+            .parse()
+            .map(Some)
+            .map_err(|_| DiceError::InvalidNumber(start))
+    }
+
+    fn parse(mut self) -> Result<DiceExpr, DiceError> {
+        if self.chars.is_empty() {
+            return Err(DiceError::Empty);
+        }
+        let mut terms = Vec::new();
+        let mut sign = if self.eat("-") {
+            -1
+        } else {
+            self.eat("+");
+            1
+        };
+        loop {
+            terms.push((sign, self.term()?));
+            sign = match self.peek() {
+                None => break,
+                Some('+') => 1,
+                Some('-') => -1,
+                Some(c) => return Err(DiceError::UnexpectedChar(c, self.pos)),
+            };
+            self.pos += 1;
+        }
+        Ok(DiceExpr { terms })
+    }
+
+    fn term(&mut self) -> Result<Term, DiceError> {
+        let count = self.number()?;
+        if !self.eat("d") {
+            return match (count, self.peek()) {
+                (Some(value), _) => Ok(Term::Constant(value as i64)),
+                (None, Some(c)) => Err(DiceError::UnexpectedChar(c, self.pos)),
+                (None, None) => Err(DiceError::UnexpectedEnd),
+            };
+        }
+
+        let count = count.unwrap_or(1);
+        if count > MAX_DICE {
+            return Err(DiceError::TooManyDice(count));
+        }
+        let sides = if self.eat("%") {
+            100
+        } else {
+            match (self.number()?, self.peek()) {
+                (Some(sides), _) => sides,
+                (None, Some(c)) => return Err(DiceError::UnexpectedChar(c, self.pos)),
+                (None, None) => return Err(DiceError::UnexpectedEnd),
+            }
+        };
+        if sides == 0 {
+            return Err(DiceError::ZeroSides);
+        }
+
+        let exploding = self.eat("!");
+        if exploding && sides == 1 {
+            return Err(DiceError::ExplodingD1);
+        }
+
+        let keep = if self.eat("kl") {
+            Keep::Lowest(self.keep_count(count)?)
+        } else if self.eat("kh") || self.eat("k") {
+            Keep::Highest(self.keep_count(count)?)
+        } else {
+            Keep::All
+        };
+
+        let advantage = if self.eat("adv") {
+            Advantage::Advantage
+        } else if self.eat("dis") {
+            Advantage::Disadvantage
+        } else {
+            Advantage::None
+        };
+
+        Ok(Term::Dice(DiceTerm {
+            count,
+            sides,
+            exploding,
+            keep,
+            advantage,
+        }))
+    }
+
+    fn keep_count(&mut self, count: u32) -> Result<u32, DiceError> {
+        match (self.number()?, self.peek()) {
+            (Some(keep), _) if keep <= count => Ok(keep),
+            (Some(keep), _) => Err(DiceError::KeepTooMany { keep, count }),
+            (None, Some(c)) => Err(DiceError::UnexpectedChar(c, self.pos)),
+            (None, None) => Err(DiceError::UnexpectedEnd),
+        }
+    }
+}
@@ -0,0 +1,414 @@
+use crate::resolve::RollTree;
+use crate::state::{
+    DangerLevel, EffectTarget, EncounterDetail, LightSourceType, LightState, Supply,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fmt::{Display, Formatter};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// What a logged event records, with the structured data behind its text.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug, Default)]
+#[serde(tag = "type")]
+pub enum LogEvent {
+    /// Anything without a type of its own, including events from saves that only kept text.
+    #[default]
+    Message,
+    /// Written by the GM.
+    Note,
+    /// An encounter check that hit, or a forced encounter, with the table result.
+    Encounter {
+        result: String,
+    },
+    NoEncounter,
+    /// A result from the torch event table.
+    LightEvent {
+        result: String,
+    },
+    /// A light burnt out.
+    LightBurnout {
+        light: u32,
+        owner: String,
+        #[serde(default)]
+        kind: LightSourceType,
+    },
+    /// A light was lit, put out, relit, guttered, refuelled or thrown away.
+    LightChange {
+        light: u32,
+        state: LightState,
+    },
+    /// The party lost its last light.
+    Darkness,
+    /// A spare torch, oil flask or candle was used.
+    Supplies {
+        owner: String,
+        supply: Supply,
+        left: u32,
+    },
+    DangerChange {
+        level: DangerLevel,
+    },
+    /// The GM moved the game clock by hand.
+    ClockAdjust {
+        from_secs: u64,
+        to_secs: u64,
+    },
+    EffectStarted {
+        effect: String,
+        target: EffectTarget,
+    },
+    /// An effect wore off or was ended early.
+    EffectExpired {
+        effect: String,
+        target: EffectTarget,
+    },
+    /// Something in a fight: it starting or ending, a new round or initiative.
+    Combat {
+        round: u32,
+    },
+    /// A monster was hit or healed.
+    MonsterHp {
+        creature: String,
+        hp: i64,
+    },
+    Morale {
+        monster: String,
+        roll: i64,
+        morale: u32,
+        held: bool,
+    },
+    DiceRoll {
+        expression: String,
+        total: i64,
+    },
+    Error,
+}
+
+/// Tags offered for any event, alongside those given automatically and any already in use.
+pub const SUGGESTED_TAGS: [&str; 4] = ["combat", "travel", "mystery", "loot"];
+
+impl LogEvent {
+    /// Tags given to this kind of event when it is logged.
+    pub fn auto_tags(&self) -> &'static [&'static str] {
+        match self {
+            LogEvent::Encounter { .. } | LogEvent::NoEncounter => &["encounter"],
+            LogEvent::Combat { .. } | LogEvent::MonsterHp { .. } | LogEvent::Morale { .. } => {
+                &["combat"]
+            }
+            LogEvent::LightEvent { .. }
+            | LogEvent::LightBurnout { .. }
+            | LogEvent::LightChange { .. }
+            | LogEvent::Darkness => &["light"],
+            LogEvent::Supplies { .. } => &["supplies"],
+            LogEvent::DangerChange { .. } => &["danger"],
+            LogEvent::ClockAdjust { .. } => &["time"],
+            LogEvent::EffectStarted { .. } | LogEvent::EffectExpired { .. } => &["effect"],
+            LogEvent::DiceRoll { .. } => &["dice"],
+            LogEvent::Note => &["note"],
+            LogEvent::Message | LogEvent::Error => &[],
+        }
+    }
+
+    /// Works out what an event logged as plain text was, from how its text reads.
+    fn from_legacy_text(text: &str) -> Self {
+        if let Some(result) = text.strip_prefix("!ENCOUNTER! - ") {
+            LogEvent::Encounter {
+                result: result.to_string(),
+            }
+        } else if text == "No encounter" {
+            LogEvent::NoEncounter
+        } else if text.starts_with("[Error]") {
+            LogEvent::Error
+        } else {
+            LogEvent::Message
+        }
+    }
+}
+
+/// One event in the log: its text as shown, what it was, and when it happened.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug, Default)]
+pub struct LoggedEvent {
+    pub text: String,
+    #[serde(default)]
+    pub event: LogEvent,
+    /// Game time, in seconds on the game clock. `None` for events from older saves.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub game_secs: Option<u64>,
+    /// Real time, in seconds since the Unix epoch. `None` for events from older saves.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wall_clock: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<EventDetail>,
+    /// Lowercase tags, such as "combat" or "loot", given automatically or by the GM.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Struck out by the GM. Kept in the log, but left out of recaps.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub deleted: bool,
+    /// The text before each edit, oldest first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub edits: Vec<EventEdit>,
+}
+
+/// What an event said before the GM edited it.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug, Default)]
+pub struct EventEdit {
+    pub previous: String,
+    /// Real time of the edit, in seconds since the Unix epoch.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wall_clock: Option<u64>,
+}
+
+impl LoggedEvent {
+    /// An event happening now, at `game_secs` on the game clock.
+    pub fn new(event: LogEvent, text: impl Into<String>, game_secs: u64) -> Self {
+        Self {
+            text: text.into(),
+            game_secs: Some(game_secs),
+            wall_clock: now(),
+            detail: None,
+            tags: auto_tags(&event),
+            event,
+            deleted: false,
+            edits: vec![],
+        }
+    }
+
+    /// The text as first logged, before any edits.
+    pub fn original_text(&self) -> &str {
+        self.edits.first().map_or(&self.text, |edit| &edit.previous)
+    }
+
+    /// Replaces the text, keeping what it said before. Returns `false` if it's unchanged.
+    pub fn edit(&mut self, text: &str) -> bool {
+        if text == self.text {
+            return false;
+        }
+        let previous = std::mem::replace(&mut self.text, text.to_string());
+        self.edits.push(EventEdit {
+            previous,
+            wall_clock: now(),
+        });
+        true
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| *t == normalize_tag(tag))
+    }
+
+    /// Adds a tag, returning `false` if it was blank or already there.
+    pub fn add_tag(&mut self, tag: &str) -> bool {
+        let tag = normalize_tag(tag);
+        if tag.is_empty() || self.tags.contains(&tag) {
+            return false;
+        }
+        self.tags.push(tag);
+        true
+    }
+
+    /// Removes a tag, returning `false` if the event didn't have it.
+    pub fn remove_tag(&mut self, tag: &str) -> bool {
+        let tag = normalize_tag(tag);
+        let before = self.tags.len();
+        self.tags.retain(|t| *t != tag);
+        self.tags.len() != before
+    }
+
+    /// Game time as `MM:SS` on the game clock, or `--:--` if it wasn't recorded.
+    pub fn game_time_label(&self) -> String {
+        self.game_secs.map_or("--:--".to_string(), format_clock)
+    }
+
+    /// Real time as `YYYY-MM-DD HH:MM UTC`, if it was recorded.
+    pub fn wall_clock_label(&self) -> Option<String> {
+        self.wall_clock.map(format_utc)
+    }
+}
+
+impl Display for LoggedEvent {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+fn now() -> Option<u64> {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .ok()
+        .map(|since| since.as_secs())
+}
+
+/// Tags are compared trimmed and in lowercase, so "Loot " and "loot" are the same tag.
+pub fn normalize_tag(tag: &str) -> String {
+    tag.trim().to_lowercase()
+}
+
+fn auto_tags(event: &LogEvent) -> Vec<String> {
+    event
+        .auto_tags()
+        .iter()
+        .map(|tag| tag.to_string())
+        .collect()
+}
+
+/// Which events to show from the log. The default shows everything.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct LogFilter {
+    /// Only events with at least one of these tags, or any event if there are none.
+    pub tags: BTreeSet<String>,
+    pub from_turn: Option<u32>,
+    pub to_turn: Option<u32>,
+    /// Only events whose text, tags or encounter follow-ups contain this, ignoring case.
+    pub text: String,
+}
+
+impl LogFilter {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Turns a tag on, or off if it was already on.
+    pub fn toggle_tag(&mut self, tag: &str) {
+        let tag = normalize_tag(tag);
+        if !self.tags.remove(&tag) {
+            self.tags.insert(tag);
+        }
+    }
+
+    pub fn matches(&self, turn: u32, event: &LoggedEvent) -> bool {
+        if self.from_turn.is_some_and(|from| turn < from)
+            || self.to_turn.is_some_and(|to| turn > to)
+        {
+            return false;
+        }
+        if !self.tags.is_empty() && !event.tags.iter().any(|tag| self.tags.contains(tag)) {
+            return false;
+        }
+        let search = self.text.trim().to_lowercase();
+        if search.is_empty() {
+            return true;
+        }
+        let encounter = event
+            .detail
+            .as_ref()
+            .and_then(|detail| detail.encounter.as_ref())
+            .map(|encounter| encounter.to_string());
+        std::iter::once(&event.text)
+            .chain(&event.tags)
+            .chain(&encounter)
+            .any(|text| text.to_lowercase().contains(&search))
+    }
+
+    /// The events this filter lets through, as each turn with the indices of its matching
+    /// events. Turns with nothing matching are left out.
+    pub fn apply<'a>(&self, log: &'a [TurnEntry]) -> Vec<(&'a TurnEntry, Vec<usize>)> {
+        log.iter()
+            .filter_map(|entry| {
+                let matching: Vec<usize> = (0..entry.events.len())
+                    .filter(|&index| self.matches(entry.turn, &entry.events[index]))
+                    .collect();
+                (!matching.is_empty()).then_some((entry, matching))
+            })
+            .collect()
+    }
+}
+
+/// Game clock seconds as `MM:SS`, such as `05:00` five minutes in.
+pub fn format_clock(secs: u64) -> String {
+    format!("{:02}:{:02}", secs / 60, secs % 60)
+}
+
+/// Formats seconds since the Unix epoch as a UTC date and time, such as `2025-03-01 19:30 UTC`.
+pub fn format_utc(secs: u64) -> String {
+    let (days, secs) = (secs / 86_400, secs % 86_400);
+    // Civil date from days since 1970-01-01, after Howard Hinnant's `civil_from_days`.
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs % 3600 / 60
+    )
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug, Default)]
+#[serde(from = "TurnEntryRepr")]
+pub struct TurnEntry {
+    pub turn: u32,
+    pub events: Vec<LoggedEvent>,
+}
+
+impl TurnEntry {
+    /// The text of each event, in order.
+    pub fn texts(&self) -> Vec<&str> {
+        self.events
+            .iter()
+            .map(|event| event.text.as_str())
+            .collect()
+    }
+}
+
+/// Extra structured data recorded alongside a logged event.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug, Default)]
+pub struct EventDetail {
+    /// Every roll made to produce a table result that referenced other tables or dice.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub roll_tree: Option<RollTree>,
+    /// Follow-up rolls made when an encounter hits.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encounter: Option<EncounterDetail>,
+}
+
+/// Turn entries as saved, either with typed events or, in older saves, with plain text events.
+#[derive(Deserialize)]
+struct TurnEntryRepr {
+    turn: u32,
+    events: Vec<LoggedEventRepr>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum LoggedEventRepr {
+    Text(String),
+    Event(Box<LoggedEvent>),
+}
+
+impl From<TurnEntryRepr> for TurnEntry {
+    fn from(repr: TurnEntryRepr) -> Self {
+        let events = repr
+            .events
+            .into_iter()
+            .map(|event| match event {
+                LoggedEventRepr::Text(text) => {
+                    let event = LogEvent::from_legacy_text(&text);
+                    LoggedEvent {
+                        tags: auto_tags(&event),
+                        event,
+                        text,
+                        ..Default::default()
+                    }
+                }
+                LoggedEventRepr::Event(event) => *event,
+            })
+            .collect();
+        Self {
+            turn: repr.turn,
+            events,
+        }
+    }
+}
@@ -0,0 +1,19 @@
+//! GUI-free core of Shadowtrack: game state, turn systems, randomness and save files.
+//!
+//! Front-ends drive a [`session::Session`], which owns the state and the game clock and runs
+//! the turn pipeline as time passes.
+
+pub mod bestiary;
+pub mod dice;
+pub mod event_log;
+pub mod recap;
+pub mod resolve;
+pub mod rng;
+pub mod save;
+pub mod session;
+pub mod state;
+pub mod systems;
+pub mod tables;
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,272 @@
+use crate::event_log::{format_clock, LogEvent, LoggedEvent};
+use crate::state::{LightSourceType, ShadowtrackData};
+use std::fmt::{Display, Formatter, Write};
+
+/// How a session recap is written out.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RecapFormat {
+    Markdown,
+    /// A single page with its styles inline, needing nothing else to open.
+    Html,
+    Text,
+}
+
+impl RecapFormat {
+    pub const ALL: [RecapFormat; 3] = [RecapFormat::Markdown, RecapFormat::Html, RecapFormat::Text];
+
+    /// File extension for a recap in this format, without the dot.
+    pub fn extension(self) -> &'static str {
+        match self {
+            RecapFormat::Markdown => "md",
+            RecapFormat::Html => "html",
+            RecapFormat::Text => "txt",
+        }
+    }
+}
+
+impl Display for RecapFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RecapFormat::Markdown => write!(f, "Markdown"),
+            RecapFormat::Html => write!(f, "HTML"),
+            RecapFormat::Text => write!(f, "Plain text"),
+        }
+    }
+}
+
+/// One line of a recap: an event with the turn and game time it happened at.
+#[derive(Clone, PartialEq, Debug)]
+pub struct RecapLine {
+    pub turn: u32,
+    /// Game time as `MM:SS`, or `--:--` if the event didn't record it.
+    pub time: String,
+    pub text: String,
+    pub note: bool,
+}
+
+impl RecapLine {
+    fn new(turn: u32, event: &LoggedEvent) -> Self {
+        Self {
+            turn,
+            time: event.game_time_label(),
+            text: event.text.clone(),
+            note: event.event == LogEvent::Note,
+        }
+    }
+}
+
+/// What happened in a session, gathered from its log for players to read. Struck out events
+/// are left out.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Recap {
+    pub turns_elapsed: u32,
+    pub game_time: String,
+    pub torches_consumed: usize,
+    pub encounters: Vec<RecapLine>,
+    pub burnouts: Vec<RecapLine>,
+    pub notes: Vec<RecapLine>,
+    /// Every event, by turn in the order played.
+    pub turns: Vec<(u32, Vec<RecapLine>)>,
+}
+
+impl Recap {
+    pub fn new(data: &ShadowtrackData) -> Self {
+        let mut recap = Recap {
+            turns_elapsed: data.turn,
+            game_time: format_clock(data.clock_elapsed),
+            ..Default::default()
+        };
+        for entry in &data.event_log {
+            let mut lines = vec![];
+            for event in entry.events.iter().filter(|event| !event.deleted) {
+                let line = RecapLine::new(entry.turn, event);
+                match &event.event {
+                    LogEvent::Encounter { .. } => {
+                        let text = line.text.strip_prefix("!ENCOUNTER! - ");
+                        recap.encounters.push(RecapLine {
+                            text: text.unwrap_or(&line.text).to_string(),
+                            ..line.clone()
+                        });
+                    }
+                    LogEvent::LightBurnout { kind, .. } => {
+                        if *kind == LightSourceType::Torch {
+                            recap.torches_consumed += 1;
+                        }
+                        recap.burnouts.push(line.clone());
+                    }
+                    LogEvent::Note => recap.notes.push(line.clone()),
+                    _ => {}
+                }
+                lines.push(line);
+            }
+            if !lines.is_empty() {
+                recap.turns.push((entry.turn, lines));
+            }
+        }
+        recap
+    }
+
+    fn summary(&self) -> [(&'static str, String); 5] {
+        [
+            ("Turns elapsed", self.turns_elapsed.to_string()),
+            ("Game time", self.game_time.clone()),
+            ("Encounters", self.encounters.len().to_string()),
+            ("Torches consumed", self.torches_consumed.to_string()),
+            ("Lights burnt out", self.burnouts.len().to_string()),
+        ]
+    }
+
+    /// The highlights, each with what to write when there were none.
+    fn sections(&self) -> [(&'static str, &[RecapLine], &'static str); 3] {
+        [
+            ("Encounters", &self.encounters, "No encounters"),
+            ("Light Burn-outs", &self.burnouts, "No lights burnt out"),
+            ("GM Notes", &self.notes, "No notes"),
+        ]
+    }
+
+    pub fn render(&self, format: RecapFormat) -> String {
+        match format {
+            RecapFormat::Markdown => self.markdown(),
+            RecapFormat::Html => self.html(),
+            RecapFormat::Text => self.text(),
+        }
+    }
+
+    fn markdown(&self) -> String {
+        let mut out = String::from("# Session Recap\n\n");
+        for (label, value) in self.summary() {
+            let _ = writeln!(out, "- **{}:** {}", label, value);
+        }
+        for (title, lines, empty) in self.sections() {
+            let _ = writeln!(out, "\n## {}\n", title);
+            if lines.is_empty() {
+                let _ = writeln!(out, "{}", empty);
+            }
+            for line in lines {
+                let _ = writeln!(out, "- Turn {} `{}` {}", line.turn, line.time, line.text);
+            }
+        }
+        out.push_str("\n## Log\n");
+        for (turn, lines) in &self.turns {
+            let _ = writeln!(out, "\n### Turn {}\n", turn);
+            for line in lines {
+                if line.note {
+                    let _ = writeln!(out, "- `{}` *{}*", line.time, line.text);
+                } else {
+                    let _ = writeln!(out, "- `{}` {}", line.time, line.text);
+                }
+            }
+        }
+        out
+    }
+
+    fn html(&self) -> String {
+        let mut out = String::from(concat!(
+            "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n",
+            "<title>Session Recap</title>\n<style>\n",
+            "body { font-family: Georgia, serif; max-width: 48em; margin: 2em auto; ",
+            "padding: 0 1em; background: #1b1a17; color: #e8e2d0; }\n",
+            "h1, h2, h3 { color: #f0c674; }\n",
+            "time { font-family: monospace; color: #a89f86; margin-right: 0.5em; }\n",
+            ".note { font-style: italic; color: #a3d9a5; }\n",
+            "ul { padding-left: 1.2em; }\n",
+            "</style>\n</head>\n<body>\n<h1>Session Recap</h1>\n<ul>\n",
+        ));
+        for (label, value) in self.summary() {
+            let _ = writeln!(
+                out,
+                "<li><strong>{}:</strong> {}</li>",
+                label,
+                escape_html(&value)
+            );
+        }
+        out.push_str("</ul>\n");
+        for (title, lines, empty) in self.sections() {
+            let _ = writeln!(out, "<h2>{}</h2>", title);
+            if lines.is_empty() {
+                let _ = writeln!(out, "<p>{}</p>", empty);
+                continue;
+            }
+            out.push_str("<ul>\n");
+            for line in lines {
+                let _ = writeln!(
+                    out,
+                    "<li>Turn {} <time>{}</time>{}</li>",
+                    line.turn,
+                    line.time,
+                    escape_html(&line.text)
+                );
+            }
+            out.push_str("</ul>\n");
+        }
+        out.push_str("<h2>Log</h2>\n");
+        for (turn, lines) in &self.turns {
+            let _ = writeln!(out, "<h3>Turn {}</h3>\n<ul>", turn);
+            for line in lines {
+                let class = if line.note { " class=\"note\"" } else { "" };
+                let _ = writeln!(
+                    out,
+                    "<li{}><time>{}</time>{}</li>",
+                    class,
+                    line.time,
+                    escape_html(&line.text)
+                );
+            }
+            out.push_str("</ul>\n");
+        }
+        out.push_str("</body>\n</html>\n");
+        out
+    }
+
+    fn text(&self) -> String {
+        let mut out = String::new();
+        heading(&mut out, "SESSION RECAP", '=');
+        for (label, value) in self.summary() {
+            let _ = writeln!(out, "{}: {}", label, value);
+        }
+        for (title, lines, empty) in self.sections() {
+            out.push('\n');
+            heading(&mut out, title, '-');
+            if lines.is_empty() {
+                let _ = writeln!(out, "{}", empty);
+            }
+            for line in lines {
+                let _ = writeln!(out, "Turn {} [{}] {}", line.turn, line.time, line.text);
+            }
+        }
+        out.push('\n');
+        heading(&mut out, "Log", '-');
+        for (turn, lines) in &self.turns {
+            let _ = writeln!(out, "Turn {}", turn);
+            for line in lines {
+                let note = if line.note { "Note: " } else { "" };
+                let _ = writeln!(out, "  [{}] {}{}", line.time, note, line.text);
+            }
+        }
+        out
+    }
+}
+
+fn heading(out: &mut String, title: &str, underline: char) {
+    let _ = writeln!(out, "{}", title);
+    let _ = writeln!(
+        out,
+        "{}",
+        underline.to_string().repeat(title.chars().count())
+    );
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
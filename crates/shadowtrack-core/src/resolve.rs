@@ -0,0 +1,226 @@
+use crate::dice::{DiceExpr, DiceRoll};
+use crate::rng::RandomSource;
+use crate::tables::RollTable;
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display, Formatter};
+
+/// How many tables deep a reference chain may go before it is left unresolved.
+pub const MAX_DEPTH: usize = 8;
+
+/// One roll made while resolving a table result: the table or dice rolled, the result text
+/// and any rolls its text referenced in turn.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct RollTree {
+    /// Table name or dice expression.
+    pub source: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub roll: Option<DiceRoll>,
+    /// Fully expanded result.
+    pub text: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<RollTree>,
+    /// Why a reference was left unresolved.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl RollTree {
+    fn write_indented(&self, f: &mut Formatter<'_>, depth: usize) -> fmt::Result {
+        write!(f, "{:indent$}{}", "", self.source, indent = depth * 2)?;
+        if let Some(roll) = &self.roll {
+            write!(f, " ({})", roll.total)?;
+        }
+        match &self.error {
+            Some(error) => writeln!(f, ": [{}]", error)?,
+            None => writeln!(f, ": {}", self.text)?,
+        }
+        for child in &self.children {
+            child.write_indented(f, depth + 1)?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for RollTree {
+    /// One line per roll, nested rolls indented under the result that referenced them.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.write_indented(f, 0)
+    }
+}
+
+/// Rolls `table` and expands any references in the result.
+///
+/// Inside a result, `[[Name]]` rolls on the named table from `library`, `[[2d6kh1]]` rolls
+/// any dice expression, and bare dice with a count such as `1d4` or `2d6+1` are rolled in
+/// place. Prose like "a d20" is left alone.
+/// References that are unknown, cyclic or nested deeper than [`MAX_DEPTH`] are left as
+/// written and the reason is recorded in the tree.
+pub fn roll_table(
+    name: &str,
+    table: &RollTable,
+    library: &[RollTable],
+    rng: &mut impl RandomSource,
+) -> Option<RollTree> {
+    // The root is often an ad-hoc table like the encounter table, so it isn't on the stack where
+    // a library table sharing its name would look like a cycle.
+    let mut stack = vec![];
+    roll_table_at(name, table, library, rng, &mut stack)
+}
+
+fn roll_table_at(
+    name: &str,
+    table: &RollTable,
+    library: &[RollTable],
+    rng: &mut impl RandomSource,
+    stack: &mut Vec<String>,
+) -> Option<RollTree> {
+    let rolled = table.roll(rng)?;
+    let Some(entry) = rolled.entry else {
+        return Some(RollTree {
+            source: name.to_string(),
+            text: String::new(),
+            error: Some(format!("No entry for a roll of {}", rolled.roll.total)),
+            roll: Some(rolled.roll),
+            children: vec![],
+        });
+    };
+    let entry_text = entry.text.clone();
+    let (text, children) = expand(&entry_text, library, rng, stack);
+
+    Some(RollTree {
+        source: name.to_string(),
+        roll: Some(rolled.roll),
+        text,
+        children,
+        error: None,
+    })
+}
+
+/// Expands every reference in `text`, returning the new text and a tree per reference.
+fn expand(
+    text: &str,
+    library: &[RollTable],
+    rng: &mut impl RandomSource,
+    stack: &mut Vec<String>,
+) -> (String, Vec<RollTree>) {
+    let mut output = String::new();
+    let mut children = Vec::new();
+    let mut rest = text;
+
+    while !rest.is_empty() {
+        if let Some(after_open) = rest.strip_prefix("[[") {
+            if let Some(close) = after_open.find("]]") {
+                let reference = after_open[..close].trim();
+                let node = resolve_reference(reference, library, rng, stack);
+                match &node.error {
+                    Some(_) => output.push_str(&rest[..close + 4]),
+                    None => output.push_str(&node.text),
+                }
+                children.push(node);
+                rest = &after_open[close + 2..];
+                continue;
+            }
+        }
+        let at_word_start = !output.ends_with(|c: char| c.is_alphanumeric());
+        if let Some(len) = bare_dice_len(rest).filter(|_| at_word_start) {
+            let expression = DiceExpr::parse(&rest[..len]).expect("bare dice always parse");
+            rng.set_purpose(format!("Dice: {}", expression).as_str());
+            let roll = expression.roll(rng);
+            output.push_str(&roll.total.to_string());
+            children.push(dice_node(&rest[..len], roll));
+            rest = &rest[len..];
+            continue;
+        }
+        let next = rest.chars().next().map_or(1, char::len_utf8);
+        output.push_str(&rest[..next]);
+        rest = &rest[next..];
+    }
+    (output, children)
+}
+
+fn resolve_reference(
+    reference: &str,
+    library: &[RollTable],
+    rng: &mut impl RandomSource,
+    stack: &mut Vec<String>,
+) -> RollTree {
+    let unresolved = |error: String| RollTree {
+        source: reference.to_string(),
+        roll: None,
+        text: String::new(),
+        children: vec![],
+        error: Some(error),
+    };
+
+    if let Ok(expression) = DiceExpr::parse(reference) {
+        rng.set_purpose(format!("Dice: {}", expression).as_str());
+        return dice_node(reference, expression.roll(rng));
+    }
+    let Some(table) = find_table(library, reference) else {
+        return unresolved(format!("Unknown table \"{}\"", reference));
+    };
+    if stack
+        .iter()
+        .any(|name| name.eq_ignore_ascii_case(&table.name))
+    {
+        return unresolved(format!("Cycle: {} -> {}", stack.join(" -> "), table.name));
+    }
+    if stack.len() >= MAX_DEPTH {
+        return unresolved(format!("Nested deeper than {} tables", MAX_DEPTH));
+    }
+
+    stack.push(table.name.clone());
+    rng.set_purpose(format!("Table: {}", table.name).as_str());
+    let node = roll_table_at(&table.name, table, library, rng, stack)
+        .unwrap_or_else(|| unresolved(format!("Table \"{}\" is empty", table.name)));
+    stack.pop();
+    node
+}
+
+/// Looks a table up by name, ignoring case.
+pub fn find_table<'a>(library: &'a [RollTable], name: &str) -> Option<&'a RollTable> {
+    library
+        .iter()
+        .find(|table| table.name.eq_ignore_ascii_case(name.trim()))
+}
+
+fn dice_node(source: &str, roll: DiceRoll) -> RollTree {
+    RollTree {
+        source: source.to_string(),
+        text: roll.total.to_string(),
+        roll: Some(roll),
+        children: vec![],
+        error: None,
+    }
+}
+
+/// Length of a bare `NdS`, `NdS+M` or `NdS-M` at the start of `text`, if it is a whole word.
+/// The count is required, so a lone `d6` is taken as prose.
+fn bare_dice_len(text: &str) -> Option<usize> {
+    let bytes = text.as_bytes();
+    let digits = |from: usize| {
+        bytes[from..]
+            .iter()
+            .take_while(|b| b.is_ascii_digit())
+            .count()
+    };
+
+    let mut len = digits(0);
+    if len == 0 || !matches!(bytes.get(len), Some(b'd' | b'D')) {
+        return None;
+    }
+    len += 1;
+    let sides = digits(len);
+    if sides == 0 {
+        return None;
+    }
+    len += sides;
+    if let Some(b'+' | b'-') = bytes.get(len) {
+        let modifier = digits(len + 1);
+        if modifier > 0 {
+            len += 1 + modifier;
+        }
+    }
+    let ends_word = !matches!(text[len..].chars().next(), Some(c) if c.is_alphanumeric());
+    (ends_word && DiceExpr::parse(&text[..len]).is_ok()).then_some(len)
+}
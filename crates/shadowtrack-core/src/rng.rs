@@ -0,0 +1,162 @@
+use rand::{Rng, RngCore, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+/// Trait for abstracting over random number generation.
+pub trait RandomSource {
+    fn roll_range(&mut self, min: u32, max: u32) -> u32;
+    fn choose<'a, T>(&mut self, list: &'a [T]) -> Option<&'a T>;
+
+    /// Labels the rolls that follow, for sources that keep a journal.
+    fn set_purpose(&mut self, _purpose: &str) {}
+
+    /// Seed and draw count to persist, for sources that can be resumed.
+    fn state(&self) -> Option<RngState> {
+        None
+    }
+
+    /// Rolls made since the last call, for sources that keep a journal.
+    fn take_journal(&mut self) -> Vec<RollRecord> {
+        Vec::new()
+    }
+}
+
+/// Default implementation using rng()
+pub struct DefaultRandomSource;
+
+impl RandomSource for DefaultRandomSource {
+    fn roll_range(&mut self, min: u32, max: u32) -> u32 {
+        rand::rng().random_range(min..=max)
+    }
+
+    fn choose<'a, T>(&mut self, list: &'a [T]) -> Option<&'a T> {
+        if list.is_empty() {
+            None
+        } else {
+            let index = self.roll_range(0, (list.len() - 1) as u32) as usize;
+            list.get(index)
+        }
+    }
+}
+
+/// Where a [`SeededRandomSource`] is in its stream: enough to resume it exactly.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub struct RngState {
+    pub seed: u64,
+    pub draws: u64,
+}
+
+impl RngState {
+    /// A new stream with a random seed.
+    pub fn from_entropy() -> Self {
+        Self {
+            seed: rand::random(),
+            draws: 0,
+        }
+    }
+}
+
+impl Default for RngState {
+    fn default() -> Self {
+        Self::from_entropy()
+    }
+}
+
+/// One journaled draw: what it was for, its range and the result.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct RollRecord {
+    /// Position of the draw in the seeded stream, starting at 0.
+    pub draw: u64,
+    pub turn: u32,
+    pub purpose: String,
+    pub min: u32,
+    pub max: u32,
+    pub result: u32,
+}
+
+/// Reproducible random source: the same seed always produces the same rolls.
+///
+/// Every draw consumes exactly one `u64` from a ChaCha8 stream, so the draw count alone is
+/// enough to resume the stream after a reload.
+pub struct SeededRandomSource {
+    rng: ChaCha8Rng,
+    state: RngState,
+    purpose: String,
+    journal: Vec<RollRecord>,
+}
+
+impl SeededRandomSource {
+    pub fn new(seed: u64) -> Self {
+        Self::resume(&RngState { seed, draws: 0 })
+    }
+
+    /// Recreates a source from saved state, so the next roll is the one that would have come
+    /// next before saving.
+    pub fn resume(state: &RngState) -> Self {
+        let mut rng = ChaCha8Rng::seed_from_u64(state.seed);
+        // ChaCha positions count 32-bit words, and each draw takes two.
+        rng.set_word_pos(state.draws as u128 * 2);
+        Self {
+            rng,
+            state: *state,
+            purpose: String::new(),
+            journal: Vec::new(),
+        }
+    }
+
+    fn draw(&mut self, min: u32, max: u32) -> u32 {
+        let span = (max as u64).saturating_sub(min as u64) + 1;
+        // Widening multiply maps the draw onto the range with negligible bias and no rejection,
+        // which keeps one draw per roll.
+        let offset = ((self.rng.next_u64() as u128 * span as u128) >> 64) as u32;
+        min + offset
+    }
+}
+
+impl RandomSource for SeededRandomSource {
+    fn roll_range(&mut self, min: u32, max: u32) -> u32 {
+        let result = self.draw(min, max);
+        self.journal.push(RollRecord {
+            draw: self.state.draws,
+            turn: 0,
+            purpose: self.purpose.clone(),
+            min,
+            max,
+            result,
+        });
+        self.state.draws += 1;
+        result
+    }
+
+    fn choose<'a, T>(&mut self, list: &'a [T]) -> Option<&'a T> {
+        if list.is_empty() {
+            None
+        } else {
+            let index = self.roll_range(0, (list.len() - 1) as u32) as usize;
+            list.get(index)
+        }
+    }
+
+    fn set_purpose(&mut self, purpose: &str) {
+        self.purpose = purpose.to_string();
+    }
+
+    fn state(&self) -> Option<RngState> {
+        Some(self.state)
+    }
+
+    fn take_journal(&mut self) -> Vec<RollRecord> {
+        std::mem::take(&mut self.journal)
+    }
+}
+
+/// Re-rolls a journal from its seed and returns the first record that doesn't match, if any.
+pub fn verify_journal(seed: u64, journal: &[RollRecord]) -> Option<&RollRecord> {
+    journal.iter().find(|record| {
+        let mut rng = SeededRandomSource::resume(&RngState {
+            seed,
+            draws: record.draw,
+        });
+        rng.draw(record.min, record.max) != record.result
+    })
+}
@@ -0,0 +1,219 @@
+use super::{load_save, write_atomic, SaveError};
+use crate::state::ShadowtrackData;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs::{copy, create_dir_all, read_to_string, remove_file, rename, write};
+use std::io;
+use std::path::{Path, PathBuf};
+
+const AUTOSAVE_FILE: &str = "autosave.json";
+/// Written when a session starts and removed when it shuts down cleanly.
+const LOCK_FILE: &str = "session.lock";
+const SETTINGS_FILE: &str = "settings.json";
+
+/// Where Shadowtrack keeps its autosaves: under the platform's app data directory, or `None`
+/// if there's no home directory to find it from.
+pub fn app_data_dir() -> Option<PathBuf> {
+    let base = if cfg!(windows) {
+        env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        env::var_os("HOME").map(|home| PathBuf::from(home).join("Library/Application Support"))
+    } else {
+        env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .filter(|dir| dir.is_absolute())
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))
+    };
+    base.map(|base| base.join("shadowtrack"))
+}
+
+/// When the game is autosaved. These are the GM's preferences rather than part of a game, so
+/// they're kept with the autosaves and loading a save leaves them alone.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct AutosaveSettings {
+    pub enabled: bool,
+    /// Real seconds between autosaves.
+    pub interval_secs: u64,
+    /// Also autosave each time a turn is processed.
+    pub every_turn: bool,
+    /// How many earlier autosaves to keep as backups.
+    pub backups: usize,
+}
+
+impl Default for AutosaveSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval_secs: 120,
+            every_turn: true,
+            backups: 5,
+        }
+    }
+}
+
+/// Autosaves a session into a directory as it's played, keeping rotating backups, and
+/// notices when the last session there didn't shut down cleanly.
+#[derive(Debug)]
+pub struct Autosaver {
+    dir: PathBuf,
+    pub settings: AutosaveSettings,
+    last_turn: Option<u32>,
+    secs_since_save: u64,
+    /// The game as last autosaved, so an idle table doesn't rotate out its backups.
+    last_saved: Option<String>,
+}
+
+impl Autosaver {
+    /// An autosaver for `dir`, with the settings saved there or the defaults if there are none
+    /// or they can't be read.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        let dir = dir.into();
+        let settings = read_to_string(dir.join(SETTINGS_FILE))
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        Self {
+            dir,
+            settings,
+            last_turn: None,
+            secs_since_save: 0,
+            last_saved: None,
+        }
+    }
+
+    /// Keeps the current settings for the next autosaver in this directory.
+    pub fn save_settings(&self) -> Result<(), SaveError> {
+        create_dir_all(&self.dir)?;
+        let settings = serde_json::to_string_pretty(&self.settings)?;
+        write_atomic(&self.dir.join(SETTINGS_FILE), &settings)?;
+        Ok(())
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// The latest autosave.
+    pub fn latest_path(&self) -> PathBuf {
+        self.dir.join(AUTOSAVE_FILE)
+    }
+
+    /// The `number`th backup, counting from 1 for the newest.
+    pub fn backup_path(&self, number: usize) -> PathBuf {
+        self.dir.join(format!("autosave.{}.json", number))
+    }
+
+    /// Backups on disk, newest first.
+    pub fn backups(&self) -> Vec<PathBuf> {
+        (1..)
+            .map(|number| self.backup_path(number))
+            .take_while(|path| path.exists())
+            .collect()
+    }
+
+    /// Whether the last session here is still marked as running, because it crashed or was
+    /// killed rather than shutting down.
+    pub fn unclean_shutdown(&self) -> bool {
+        self.dir.join(LOCK_FILE).exists()
+    }
+
+    /// The latest autosave.
+    pub fn load_latest(&self) -> Result<ShadowtrackData, SaveError> {
+        load_save(&self.latest_path())
+    }
+
+    /// The newest autosave that loads, to offer after an unclean shutdown: the latest, or if
+    /// that's missing or damaged, the newest backup that isn't. Fails with the latest's error
+    /// when none of them load.
+    pub fn load_newest(&self) -> Result<ShadowtrackData, SaveError> {
+        self.load_latest().or_else(|e| {
+            self.backups()
+                .iter()
+                .find_map(|path| load_save(path).ok())
+                .ok_or(e)
+        })
+    }
+
+    /// Marks a session playing `data` as running, until [`Autosaver::finish`].
+    pub fn start(&mut self, data: &ShadowtrackData) -> Result<(), SaveError> {
+        create_dir_all(&self.dir)?;
+        write(self.dir.join(LOCK_FILE), std::process::id().to_string())?;
+        self.last_turn = Some(data.turn);
+        self.secs_since_save = 0;
+        Ok(())
+    }
+
+    /// Autosaves if it's due, `elapsed_secs` real seconds after the last call: when a turn has
+    /// been processed or the interval has passed, and the game has changed since the last
+    /// autosave. Returns whether it saved.
+    pub fn tick(&mut self, data: &ShadowtrackData, elapsed_secs: u64) -> Result<bool, SaveError> {
+        let settings = self.settings;
+        if !settings.enabled {
+            return Ok(false);
+        }
+        self.secs_since_save += elapsed_secs;
+        let turn_processed = settings.every_turn && self.last_turn != Some(data.turn);
+        if !turn_processed && self.secs_since_save < settings.interval_secs {
+            return Ok(false);
+        }
+        let save_data = serde_json::to_string_pretty(data)?;
+        if self.last_saved.as_ref() == Some(&save_data) {
+            self.last_turn = Some(data.turn);
+            self.secs_since_save = 0;
+            return Ok(false);
+        }
+        self.write(data.turn, save_data)?;
+        Ok(true)
+    }
+
+    /// Autosaves now, moving the earlier autosaves down the backups.
+    pub fn save(&mut self, data: &ShadowtrackData) -> Result<(), SaveError> {
+        let save_data = serde_json::to_string_pretty(data)?;
+        self.write(data.turn, save_data)
+    }
+
+    fn write(&mut self, turn: u32, save_data: String) -> Result<(), SaveError> {
+        create_dir_all(&self.dir)?;
+        self.rotate(self.settings.backups)?;
+        write_atomic(&self.latest_path(), &save_data)?;
+        self.last_turn = Some(turn);
+        self.secs_since_save = 0;
+        self.last_saved = Some(save_data);
+        Ok(())
+    }
+
+    /// Saves a last time and clears the running mark, for a clean shutdown.
+    pub fn finish(&mut self, data: &ShadowtrackData) -> Result<(), SaveError> {
+        if self.settings.enabled {
+            self.save(data)?;
+        }
+        match remove_file(self.dir.join(LOCK_FILE)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Shifts each backup one older, dropping any past `backups`, and copies the latest
+    /// autosave in as the newest. The latest is copied rather than moved so there's always one
+    /// to restore.
+    fn rotate(&self, backups: usize) -> io::Result<()> {
+        for stale in self.backups().iter().skip(backups) {
+            remove_file(stale)?;
+        }
+        if backups == 0 || !self.latest_path().exists() {
+            return Ok(());
+        }
+        let oldest = self.backup_path(backups);
+        if oldest.exists() {
+            remove_file(oldest)?;
+        }
+        for number in (1..backups).rev() {
+            let from = self.backup_path(number);
+            if from.exists() {
+                rename(&from, self.backup_path(number + 1))?;
+            }
+        }
+        copy(self.latest_path(), self.backup_path(1))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,100 @@
+use crate::bestiary::Monster;
+use crate::recap::{Recap, RecapFormat};
+use crate::state::{LightTemplate, ShadowtrackData};
+use serde_json;
+use std::fs::{read_to_string, rename, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::{fmt, io};
+
+pub mod autosave;
+
+#[derive(Debug)]
+pub enum SaveError {
+    Serialization(serde_json::Error),
+    Io(io::Error),
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SaveError::Serialization(e) => write!(f, "Serialization error: {}", e),
+            SaveError::Io(e) => write!(f, "I/O error: {}", e),
+        }
+    }
+}
+
+impl From<serde_json::Error> for SaveError {
+    fn from(err: serde_json::Error) -> Self {
+        SaveError::Serialization(err)
+    }
+}
+
+impl From<io::Error> for SaveError {
+    fn from(err: io::Error) -> Self {
+        SaveError::Io(err)
+    }
+}
+
+pub fn write_save(save_file: &Path, game_data: &ShadowtrackData) -> Result<(), SaveError> {
+    let save_data = serde_json::to_string_pretty(game_data)?;
+    write_atomic(save_file, &save_data)?;
+    Ok(())
+}
+
+/// Writes to a temporary file next to `file` and renames it into place, so a crash part way
+/// through leaves the old file whole.
+pub(crate) fn write_atomic(file: &Path, contents: &str) -> io::Result<()> {
+    let mut temp_file = file.as_os_str().to_owned();
+    temp_file.push(".tmp");
+    let temp_file = PathBuf::from(temp_file);
+    let mut temp = File::create(&temp_file)?;
+    temp.write_all(contents.as_bytes())?;
+    temp.sync_all()?;
+    rename(&temp_file, file)
+}
+
+pub fn load_save(save_file: &Path) -> Result<ShadowtrackData, SaveError> {
+    let content = read_to_string(save_file)?;
+    let data = serde_json::from_str(&content)?;
+    Ok(data)
+}
+
+/// Writes light templates to a library file that other saves can import.
+pub fn write_light_library(
+    library_file: &Path,
+    templates: &[LightTemplate],
+) -> Result<(), SaveError> {
+    let library = serde_json::to_string_pretty(templates)?;
+    write_atomic(library_file, &library)?;
+    Ok(())
+}
+
+pub fn load_light_library(library_file: &Path) -> Result<Vec<LightTemplate>, SaveError> {
+    let content = read_to_string(library_file)?;
+    let templates = serde_json::from_str(&content)?;
+    Ok(templates)
+}
+
+/// Writes monsters to a bestiary file that other saves can import.
+pub fn write_bestiary(bestiary_file: &Path, monsters: &[Monster]) -> Result<(), SaveError> {
+    let bestiary = serde_json::to_string_pretty(monsters)?;
+    write_atomic(bestiary_file, &bestiary)?;
+    Ok(())
+}
+
+pub fn load_bestiary(bestiary_file: &Path) -> Result<Vec<Monster>, SaveError> {
+    let content = read_to_string(bestiary_file)?;
+    let monsters = serde_json::from_str(&content)?;
+    Ok(monsters)
+}
+
+/// Writes a recap of the session's log for players to read.
+pub fn write_recap(
+    recap_file: &Path,
+    game_data: &ShadowtrackData,
+    format: RecapFormat,
+) -> Result<(), SaveError> {
+    write_atomic(recap_file, &Recap::new(game_data).render(format))?;
+    Ok(())
+}
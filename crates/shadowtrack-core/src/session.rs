@@ -0,0 +1,326 @@
+use crate::dice::{DiceError, DiceExpr, DiceRoll};
+use crate::rng::{RandomSource, SeededRandomSource};
+use crate::state::{DangerLevel, EffectUnit, LightSource, ShadowtrackData, Supply, TimedEffect};
+use crate::systems::{
+    add_effect, add_light, add_note, adjust_clock, burn_real_time, check_morale,
+    count_down_effects, damage_monster, edit_event, encounter_check_due, end_combat, end_effect,
+    extinguish_light, light_from_supply, move_event, next_round, next_turn, process_light_burn,
+    relight_light, remove_spent_lights, replace_from_supply, roll_dice, roll_encounter,
+    roll_initiative, roll_light_event, set_danger_level, snuff_light, start_combat, strike_event,
+    tag_event, untag_event,
+};
+use log::debug;
+use num_integer::Integer;
+
+/// A running exploration session.
+///
+/// Owns the game state, the game clock and the random source, and runs the turn pipeline
+/// whenever enough game time has passed. Front-ends only need to feed it elapsed seconds.
+///
+/// After every roll the session copies the RNG position and any journaled rolls into
+/// [`ShadowtrackData`], so the state is always ready to save.
+pub struct Session<R: RandomSource = SeededRandomSource> {
+    pub data: ShadowtrackData,
+    pub clock_running: bool,
+    pub rng: R,
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Self::from_data(ShadowtrackData::default())
+    }
+}
+
+impl Session {
+    /// Resumes a session from saved state, continuing its seeded RNG where it left off.
+    pub fn from_data(data: ShadowtrackData) -> Self {
+        let rng = SeededRandomSource::resume(&data.rng);
+        Self::new(data, rng)
+    }
+
+    /// Swaps in loaded state, keeping the clock running or stopped as it was.
+    pub fn load(&mut self, data: ShadowtrackData) {
+        self.rng = SeededRandomSource::resume(&data.rng);
+        self.data = data;
+        self.data.number_lights();
+        self.data.link_characters();
+        self.data.sync_darkness();
+    }
+}
+
+impl<R: RandomSource> Session<R> {
+    pub fn new(mut data: ShadowtrackData, rng: R) -> Self {
+        data.number_lights();
+        data.link_characters();
+        data.sync_darkness();
+        Self {
+            data,
+            clock_running: false,
+            rng,
+        }
+    }
+
+    pub fn toggle_clock(&mut self) {
+        self.clock_running = !self.clock_running;
+    }
+
+    /// Stops the clock and rewinds it to zero, clearing the pending turn.
+    pub fn reset_clock(&mut self) {
+        adjust_clock(&mut self.data, 0);
+        self.clock_running = false;
+        self.data.next_process_minutes = None;
+    }
+
+    /// Manually adjusts the game clock without running any turn processing.
+    pub fn advance_clock_secs(&mut self, secs: u64) {
+        let total = self.data.clock_elapsed + secs;
+        adjust_clock(&mut self.data, total);
+        debug!(
+            "Advanced Clock: {secs} Seconds\nNew Value: {}",
+            self.data.clock_elapsed
+        );
+    }
+
+    /// Feeds wall-clock seconds to the session. Does nothing while the clock is stopped.
+    ///
+    /// Returns `true` if a turn was processed.
+    pub fn tick(&mut self, secs: u64) -> bool {
+        // Exploration time waits while a fight is counted in rounds.
+        if self.clock_running && self.data.combat.is_none() {
+            self.pass_time(secs)
+        } else {
+            false
+        }
+    }
+
+    /// Advances the game clock and processes a turn if the interval has been reached,
+    /// regardless of whether the clock is running. Exploration time doesn't pass during a fight,
+    /// so this does nothing while one is going on.
+    ///
+    /// Returns `true` if a turn was processed.
+    pub fn pass_time(&mut self, secs: u64) -> bool {
+        if self.data.combat.is_some() {
+            return false;
+        }
+        let minutes_before = self.data.clock_elapsed / 60;
+        self.data.clock_elapsed += secs;
+        let (elapsed_minutes, _seconds) = self.data.clock_elapsed.div_rem(&60);
+        if elapsed_minutes > minutes_before {
+            let minutes = u32::try_from(elapsed_minutes - minutes_before).unwrap_or(u32::MAX);
+            burn_real_time(&mut self.data, minutes);
+            count_down_effects(&mut self.data, EffectUnit::Minutes, minutes);
+        }
+        self.process_due_turn()
+    }
+
+    /// Processes a turn if the clock has reached the next one.
+    fn process_due_turn(&mut self) -> bool {
+        let elapsed_minutes = self.data.clock_elapsed / 60;
+        match self.data.next_process_minutes {
+            Some(next_minutes) if elapsed_minutes >= next_minutes => {
+                self.process_turn();
+                self.data.next_process_minutes =
+                    Some(elapsed_minutes + self.data.process_interval_minutes);
+                true
+            }
+            None => {
+                // First activation of process timer, we need to set a value to check against.
+                self.data.next_process_minutes = Some(self.data.process_interval_minutes);
+                false
+            }
+            _ => false,
+        }
+    }
+
+    /// Passes game time a minute at a time, exactly as a running clock would.
+    ///
+    /// Returns the number of turns processed, which is none during a fight.
+    pub fn pass_minutes(&mut self, minutes: u64) -> u32 {
+        (0..minutes).filter(|_| self.pass_time(60)).count() as u32
+    }
+
+    /// Passes game time a minute at a time until `turns` turns have been processed.
+    ///
+    /// Returns `false`, passing no time, during a fight.
+    pub fn pass_turns(&mut self, turns: u32) -> bool {
+        if self.data.combat.is_some() {
+            return false;
+        }
+        let mut processed = 0;
+        while processed < turns {
+            if self.pass_time(60) {
+                processed += 1;
+            }
+        }
+        true
+    }
+
+    /// Runs the per-turn systems: light burn, timed effects, light events and, when the danger level calls
+    /// for one, the encounter check.
+    pub fn process_turn(&mut self) {
+        self.data.turn += 1;
+
+        process_light_burn(&mut self.data, &mut self.rng);
+        count_down_effects(&mut self.data, EffectUnit::Turns, 1);
+        roll_light_event(&mut self.data, &mut self.rng);
+        if encounter_check_due(&self.data) {
+            roll_encounter(&mut self.data, &mut self.rng, false);
+        }
+        self.sync_rng();
+    }
+
+    pub fn roll_encounter(&mut self, forced_encounter: bool) {
+        roll_encounter(&mut self.data, &mut self.rng, forced_encounter);
+        self.sync_rng();
+    }
+
+    pub fn set_danger_level(&mut self, level: DangerLevel) {
+        set_danger_level(&mut self.data, level);
+    }
+
+    /// Tags the event at `index` in the given turn's log entry.
+    pub fn tag_event(&mut self, turn: u32, index: usize, tag: &str) -> bool {
+        tag_event(&mut self.data, turn, index, tag)
+    }
+
+    pub fn untag_event(&mut self, turn: u32, index: usize, tag: &str) -> bool {
+        untag_event(&mut self.data, turn, index, tag)
+    }
+
+    /// Rewrites a logged event's text, keeping what it said before.
+    pub fn edit_event(&mut self, turn: u32, index: usize, text: &str) -> bool {
+        edit_event(&mut self.data, turn, index, text)
+    }
+
+    /// Strikes out a logged event, or restores it if `deleted` is `false`.
+    pub fn strike_event(&mut self, turn: u32, index: usize, deleted: bool) -> bool {
+        strike_event(&mut self.data, turn, index, deleted)
+    }
+
+    pub fn move_event(&mut self, turn: u32, from: usize, to: usize) -> bool {
+        move_event(&mut self.data, turn, from, to)
+    }
+
+    /// Writes a GM note into a turn of the log, at `index` or at the end of the turn.
+    pub fn add_note(&mut self, turn: u32, index: Option<usize>, text: &str) {
+        add_note(&mut self.data, turn, index, text);
+    }
+
+    /// Starts a fight against the given monsters and rolls initiative, pausing exploration
+    /// time. Returns `false` if a fight is already going on.
+    pub fn start_combat(&mut self, monsters: Vec<String>) -> bool {
+        if !start_combat(&mut self.data, monsters) {
+            return false;
+        }
+        self.roll_initiative();
+        true
+    }
+
+    pub fn roll_initiative(&mut self) -> bool {
+        let rolled = roll_initiative(&mut self.data, &mut self.rng);
+        self.sync_rng();
+        rolled
+    }
+
+    pub fn damage_monster(&mut self, group: usize, index: usize, amount: i64) -> bool {
+        let damaged = damage_monster(&mut self.data, &mut self.rng, group, index, amount);
+        self.sync_rng();
+        damaged
+    }
+
+    pub fn check_morale(&mut self, group: usize, reason: &str) -> Option<bool> {
+        let held = check_morale(&mut self.data, &mut self.rng, group, reason);
+        self.sync_rng();
+        held
+    }
+
+    pub fn next_turn(&mut self) -> bool {
+        next_turn(&mut self.data)
+    }
+
+    pub fn next_round(&mut self) -> bool {
+        next_round(&mut self.data)
+    }
+
+    /// Ends the fight, adding the time its rounds took to the clock and processing every turn
+    /// that came due. Returns `false` if there's no fight going on.
+    pub fn end_combat(&mut self) -> bool {
+        let Some(secs) = end_combat(&mut self.data) else {
+            return false;
+        };
+        self.data.clock_elapsed += secs;
+        // A long fight can cross several turns, each of which is processed in order.
+        let elapsed_minutes = self.data.clock_elapsed / 60;
+        while let Some(next_minutes) = self
+            .data
+            .next_process_minutes
+            .filter(|&next_minutes| elapsed_minutes >= next_minutes)
+        {
+            self.process_turn();
+            self.data.next_process_minutes =
+                Some(next_minutes + self.data.process_interval_minutes);
+        }
+        self.process_due_turn();
+        true
+    }
+
+    pub fn add_effect(&mut self, effect: TimedEffect) {
+        add_effect(&mut self.data, effect);
+    }
+
+    pub fn end_effect(&mut self, index: usize) -> bool {
+        end_effect(&mut self.data, index)
+    }
+
+    pub fn add_light(&mut self, light: LightSource) {
+        add_light(&mut self.data, light);
+    }
+
+    pub fn replace_from_supply(&mut self, index: usize) -> bool {
+        replace_from_supply(&mut self.data, index)
+    }
+
+    pub fn light_from_supply(&mut self, character: u32, supply: Supply) -> bool {
+        light_from_supply(&mut self.data, character, supply)
+    }
+
+    pub fn snuff_light(&mut self, index: usize) -> bool {
+        snuff_light(&mut self.data, index)
+    }
+
+    pub fn extinguish_light(&mut self, index: usize) -> bool {
+        extinguish_light(&mut self.data, index)
+    }
+
+    pub fn relight_light(&mut self, index: usize) -> bool {
+        relight_light(&mut self.data, index)
+    }
+
+    pub fn remove_spent_lights(&mut self) -> usize {
+        remove_spent_lights(&mut self.data)
+    }
+
+    pub fn roll_light_event(&mut self) {
+        roll_light_event(&mut self.data, &mut self.rng);
+        self.sync_rng();
+    }
+
+    /// Parses and rolls a dice expression, logging the breakdown.
+    pub fn roll_dice(&mut self, expression: &str) -> Result<DiceRoll, DiceError> {
+        let expression = DiceExpr::parse(expression)?;
+        let roll = roll_dice(&mut self.data, &mut self.rng, &expression);
+        self.sync_rng();
+        Ok(roll)
+    }
+
+    /// Copies the RNG position and new journal entries into the game state.
+    pub fn sync_rng(&mut self) {
+        if let Some(state) = self.rng.state() {
+            self.data.rng = state;
+        }
+        for mut record in self.rng.take_journal() {
+            record.turn = self.data.turn;
+            self.data.roll_journal.push(record);
+        }
+    }
+}
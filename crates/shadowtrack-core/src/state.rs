@@ -0,0 +1,1400 @@
+use crate::bestiary::{default_bestiary, Monster, MonsterGroup};
+use crate::dice::DiceExpr;
+use crate::event_log::{LoggedEvent, TurnEntry, SUGGESTED_TAGS};
+use crate::resolve::RollTree;
+use crate::rng::{RngState, RollRecord};
+use crate::tables::RollTable;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fmt::{Display, Formatter};
+
+const DEFAULT_ENCOUNTER_TABLE: &[&str] = &[
+    "{{Goblin}} scouts",
+    "{{Skeleton}} patrol",
+    "{{Gray Ooze}} dripping from the ceiling",
+    "Lost adventurer",
+    "{{Bat Swarm}}",
+    "{{Mimic}} chest",
+];
+
+const DEFAULT_TORCH_EVENTS_TABLE: &[&str] = &[
+    "You hear a distant moan in the dark...",
+    "A gust of wind threatens to blow out a torch.",
+    "You stumble over loose stones, nearly falling.",
+    "The smell of sulfur fills the air.",
+    "Whispers echo from nowhere.",
+    "A rat darts between your feet.",
+];
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug, Default)]
+pub enum LightSourceType {
+    #[default]
+    Torch,
+    Lantern,
+    Candle,
+    Spell(String),
+}
+
+impl Display for LightSourceType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LightSourceType::Torch => {
+                write!(f, "Torch")
+            }
+            LightSourceType::Lantern => {
+                write!(f, "Lantern")
+            }
+            LightSourceType::Candle => {
+                write!(f, "Candle")
+            }
+            LightSourceType::Spell(name) => {
+                write!(f, "Spell( {} )", name)
+            }
+        }
+    }
+}
+
+/// How a light source uses up its remaining minutes.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub enum BurnModel {
+    /// Burns a minute for every minute on the game clock, between turns as well.
+    RealTime,
+    /// Burns a fixed number of minutes each processed turn.
+    PerTurn { minutes: u32 },
+    /// Burns `minutes` each turn, and `extra_minutes` more when `dice` rolls `chance` or lower.
+    Chance {
+        minutes: u32,
+        dice: DiceExpr,
+        chance: i64,
+        extra_minutes: u32,
+    },
+    /// Never runs out.
+    Never,
+    /// Lasts as long as the caster keeps concentrating, however long that is.
+    Concentration,
+}
+
+impl BurnModel {
+    /// Whether the light counts down its remaining minutes at all.
+    pub fn expires(&self) -> bool {
+        !matches!(self, BurnModel::Never | BurnModel::Concentration)
+    }
+
+    /// Time left on a light burning this way, such as `40 min`, or the model if it doesn't
+    /// count down.
+    pub fn time_left_label(&self, minutes_remaining: u32) -> String {
+        if self.expires() {
+            format!("{} min", minutes_remaining)
+        } else {
+            self.to_string()
+        }
+    }
+}
+
+impl Display for BurnModel {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BurnModel::RealTime => write!(f, "Real time"),
+            BurnModel::PerTurn { minutes } => write!(f, "{} min per turn", minutes),
+            BurnModel::Chance {
+                minutes,
+                dice,
+                chance,
+                extra_minutes,
+            } => write!(
+                f,
+                "{} min per turn, {} more on {} or less on {}",
+                minutes, extra_minutes, chance, dice
+            ),
+            BurnModel::Never => write!(f, "Never expires"),
+            BurnModel::Concentration => write!(f, "Concentration"),
+        }
+    }
+}
+
+/// The burn model used for each type of light source.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct BurnModels {
+    pub torch: BurnModel,
+    pub lantern: BurnModel,
+    #[serde(default = "default_candle_burn")]
+    pub candle: BurnModel,
+    pub spell: BurnModel,
+}
+
+fn default_candle_burn() -> BurnModel {
+    BurnModel::PerTurn { minutes: 10 }
+}
+
+impl BurnModels {
+    pub fn for_type(&self, light_type: &LightSourceType) -> &BurnModel {
+        match light_type {
+            LightSourceType::Torch => &self.torch,
+            LightSourceType::Lantern => &self.lantern,
+            LightSourceType::Candle => &self.candle,
+            LightSourceType::Spell(_) => &self.spell,
+        }
+    }
+}
+
+impl Default for BurnModels {
+    fn default() -> Self {
+        Self {
+            torch: BurnModel::Chance {
+                minutes: 10,
+                dice: DiceExpr::dice(1, 6),
+                chance: 2,
+                extra_minutes: 10,
+            },
+            lantern: BurnModel::PerTurn { minutes: 10 },
+            candle: default_candle_burn(),
+            spell: BurnModel::PerTurn { minutes: 10 },
+        }
+    }
+}
+
+/// How well the party can see, from the lights they have lit.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Illumination {
+    Bright,
+    Dim,
+    Darkness,
+}
+
+impl Display for Illumination {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Illumination::Bright => write!(f, "Bright"),
+            Illumination::Dim => write!(f, "Dim"),
+            Illumination::Darkness => write!(f, "Total darkness"),
+        }
+    }
+}
+
+/// How the party's illumination is worked out, and what changes in total darkness.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct IlluminationSettings {
+    /// A steadily lit light with at least this radius gives bright light. Anything less,
+    /// or a guttering light, gives dim light.
+    pub bright_radius_feet: u32,
+    /// Danger level to switch to while the party is in total darkness, if any.
+    pub darkness_danger_level: Option<DangerLevel>,
+}
+
+impl Default for IlluminationSettings {
+    fn default() -> Self {
+        Self {
+            bright_radius_feet: 30,
+            darkness_danger_level: None,
+        }
+    }
+}
+
+/// How often the party checks for wandering monsters, and the odds of each check.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub struct EncounterCheck {
+    /// Check once every this many processed turns.
+    pub every_turns: u32,
+    /// An encounter happens on a roll of `chance` or lower on a `die`, e.g. 2 in 6.
+    pub chance: u32,
+    pub die: u32,
+}
+
+impl Display for EncounterCheck {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} in {}", self.chance, self.die)?;
+        match self.every_turns {
+            1 => write!(f, " every turn"),
+            n => write!(f, " every {} turns", n),
+        }
+    }
+}
+
+/// Danger level of the current area, controlling the wandering monster check.
+///
+/// The presets follow Shadowdark's 1-in-6 check every 3 (Unsafe), 2 (Risky) or every (Deadly)
+/// turn. `Deadly` is the classic check on every turn.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug, Default)]
+pub enum DangerLevel {
+    Safe,
+    Unsafe,
+    Risky,
+    #[default]
+    Deadly,
+    Custom(EncounterCheck),
+}
+
+impl DangerLevel {
+    pub const PRESETS: [DangerLevel; 4] = [
+        DangerLevel::Safe,
+        DangerLevel::Unsafe,
+        DangerLevel::Risky,
+        DangerLevel::Deadly,
+    ];
+
+    /// The check this level makes, or `None` if there are no wandering monsters.
+    pub fn check(&self) -> Option<EncounterCheck> {
+        let every_turns = match self {
+            DangerLevel::Safe => return None,
+            DangerLevel::Unsafe => 3,
+            DangerLevel::Risky => 2,
+            DangerLevel::Deadly => 1,
+            DangerLevel::Custom(check) => return Some(*check),
+        };
+        Some(EncounterCheck {
+            every_turns,
+            chance: 1,
+            die: 6,
+        })
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            DangerLevel::Safe => "Safe",
+            DangerLevel::Unsafe => "Unsafe",
+            DangerLevel::Risky => "Risky",
+            DangerLevel::Deadly => "Deadly",
+            DangerLevel::Custom(_) => "Custom",
+        }
+    }
+}
+
+impl Display for DangerLevel {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.check() {
+            Some(check) => write!(f, "{} ({})", self.name(), check),
+            None => write!(f, "{} (no encounter checks)", self.name()),
+        }
+    }
+}
+
+/// Tables rolled right after an encounter hits, to set up how the meeting starts.
+///
+/// A follow-up whose table is empty is skipped.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct EncounterFollowUps {
+    pub enabled: bool,
+    /// How the creatures react to the party, usually on 2d6.
+    pub reaction: RollTable,
+    /// How far away the creatures are when first noticed.
+    pub distance: RollTable,
+    /// Rolled once for the party and once for the creatures.
+    pub surprise: RollTable,
+    /// What the creatures are doing when found.
+    pub activity: RollTable,
+}
+
+impl Default for EncounterFollowUps {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            reaction: RollTable::ranged(
+                DiceExpr::dice(2, 6),
+                [
+                    (2, 6, "Hostile"),
+                    (7, 8, "Suspicious"),
+                    (9, 9, "Neutral"),
+                    (10, 11, "Curious"),
+                    (12, 12, "Friendly"),
+                ],
+            ),
+            distance: RollTable::ranged(
+                DiceExpr::dice(1, 6),
+                [(1, 1, "Close"), (2, 4, "Near"), (5, 6, "Far")],
+            ),
+            surprise: RollTable::ranged(
+                DiceExpr::dice(1, 6),
+                [(1, 2, "Surprised"), (3, 6, "Not surprised")],
+            ),
+            activity: RollTable::ranged(
+                DiceExpr::dice(2, 6),
+                [
+                    (2, 4, "Hunting"),
+                    (5, 6, "Eating"),
+                    (7, 8, "Building or nesting"),
+                    (9, 10, "Socializing or playing"),
+                    (11, 11, "Guarding"),
+                    (12, 12, "Sleeping"),
+                ],
+            ),
+        }
+    }
+}
+
+/// Follow-up results recorded with an encounter. Skipped follow-ups are `None`.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug, Default)]
+pub struct EncounterDetail {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reaction: Option<RollTree>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub distance: Option<RollTree>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub party_surprise: Option<RollTree>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub monster_surprise: Option<RollTree>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub activity: Option<RollTree>,
+    /// Creatures from the bestiary the encounter referenced, with their rolled numbers and HP.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub monsters: Vec<MonsterGroup>,
+}
+
+impl EncounterDetail {
+    /// Each follow-up that was rolled, with a label for display.
+    pub fn fields(&self) -> Vec<(&'static str, &RollTree)> {
+        [
+            ("Reaction", &self.reaction),
+            ("Distance", &self.distance),
+            ("Party surprise", &self.party_surprise),
+            ("Monster surprise", &self.monster_surprise),
+            ("Activity", &self.activity),
+        ]
+        .into_iter()
+        .filter_map(|(label, tree)| tree.as_ref().map(|tree| (label, tree)))
+        .collect()
+    }
+}
+
+impl Display for EncounterDetail {
+    /// One line per follow-up, such as `Reaction: Suspicious (7)`, then one per monster group.
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (label, tree) in self.fields() {
+            match (&tree.error, &tree.roll) {
+                (Some(error), _) => writeln!(f, "{}: [{}]", label, error)?,
+                (None, Some(roll)) => writeln!(f, "{}: {} ({})", label, tree.text, roll.total)?,
+                (None, None) => writeln!(f, "{}: {}", label, tree.text)?,
+            }
+        }
+        for group in &self.monsters {
+            writeln!(f, "{}", group)?;
+        }
+        Ok(())
+    }
+}
+
+/// Where a light source is in its life, from lit to burnt out.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum LightState {
+    #[default]
+    Lit,
+    /// Still lit, but at or below the warning threshold.
+    Guttering,
+    /// Put out by something in the dungeon, such as wind or water. Can be relit.
+    Extinguished,
+    /// Ran out of time. Can't be relit.
+    BurntOut,
+    /// Put out on purpose to save what's left. Can be relit.
+    Snuffed,
+}
+
+impl LightState {
+    /// Whether the light is giving off light and burning down.
+    pub fn is_lit(&self) -> bool {
+        matches!(self, LightState::Lit | LightState::Guttering)
+    }
+
+    /// Whether the light is out but has time left to be relit.
+    pub fn can_relight(&self) -> bool {
+        matches!(self, LightState::Extinguished | LightState::Snuffed)
+    }
+}
+
+impl Display for LightState {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LightState::Lit => write!(f, "Lit"),
+            LightState::Guttering => write!(f, "Guttering"),
+            LightState::Extinguished => write!(f, "Extinguished"),
+            LightState::BurntOut => write!(f, "Burnt out"),
+            LightState::Snuffed => write!(f, "Snuffed"),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug, Default)]
+pub struct LightSource {
+    /// Stable id effects refer to the light by, given when it's added. 0 until then.
+    #[serde(default)]
+    pub id: u32,
+    /// Name shown with the light: its owner's, kept in step with the character, or a label
+    /// such as "Hireling" for a light nobody in the party carries.
+    pub label: String,
+    /// Id of the character carrying the light.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<u32>,
+    pub light_type: LightSourceType,
+    pub radius_feet: u32,
+    pub minutes_remaining: u32,
+    pub last_roll: Option<u8>,
+    #[serde(default)]
+    pub state: LightState,
+    /// Burn model for this light alone. Uses its type's model if `None`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub burn: Option<BurnModel>,
+}
+
+impl LightSource {
+    /// The burn model this light uses, from its own or its type's.
+    pub fn burn_model<'a>(&'a self, models: &'a BurnModels) -> &'a BurnModel {
+        self.burn
+            .as_ref()
+            .unwrap_or_else(|| models.for_type(&self.light_type))
+    }
+
+    /// The light as named in the log, such as "Ginny's torch" or "Aloona's Light spell".
+    pub fn name(&self) -> String {
+        match &self.light_type {
+            LightSourceType::Torch => format!("{}'s torch", self.label),
+            LightSourceType::Lantern => format!("{}'s lantern", self.label),
+            LightSourceType::Candle => format!("{}'s candle", self.label),
+            LightSourceType::Spell(spell) if spell.is_empty() => format!("{}'s spell", self.label),
+            LightSourceType::Spell(spell) => format!("{}'s {} spell", self.label, spell),
+        }
+    }
+}
+
+/// A consumable that keeps a light going.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Supply {
+    Torch,
+    OilFlask,
+    Candle,
+}
+
+impl Supply {
+    pub const ALL: [Supply; 3] = [Supply::Torch, Supply::OilFlask, Supply::Candle];
+
+    /// The supply a light of this type burns, if any.
+    pub fn for_light(light_type: &LightSourceType) -> Option<Supply> {
+        match light_type {
+            LightSourceType::Torch => Some(Supply::Torch),
+            LightSourceType::Lantern => Some(Supply::OilFlask),
+            LightSourceType::Candle => Some(Supply::Candle),
+            LightSourceType::Spell(_) => None,
+        }
+    }
+
+    /// The light this supply is lit as. Oil flasks fill a lantern.
+    pub fn light_type(&self) -> LightSourceType {
+        match self {
+            Supply::Torch => LightSourceType::Torch,
+            Supply::OilFlask => LightSourceType::Lantern,
+            Supply::Candle => LightSourceType::Candle,
+        }
+    }
+
+    /// Name for a count of this supply, such as "1 torch" or "2 oil flasks".
+    pub fn count_label(&self, count: u32) -> String {
+        let (one, many) = match self {
+            Supply::Torch => ("torch", "torches"),
+            Supply::OilFlask => ("oil flask", "oil flasks"),
+            Supply::Candle => ("candle", "candles"),
+        };
+        format!("{} {}", count, if count == 1 { one } else { many })
+    }
+}
+
+/// Spare light supplies one character carries.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Supplies {
+    pub torches: u32,
+    pub oil_flasks: u32,
+    pub candles: u32,
+}
+
+impl Supplies {
+    pub fn count(&self, supply: Supply) -> u32 {
+        match supply {
+            Supply::Torch => self.torches,
+            Supply::OilFlask => self.oil_flasks,
+            Supply::Candle => self.candles,
+        }
+    }
+
+    pub fn count_mut(&mut self, supply: Supply) -> &mut u32 {
+        match supply {
+            Supply::Torch => &mut self.torches,
+            Supply::OilFlask => &mut self.oil_flasks,
+            Supply::Candle => &mut self.candles,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        Supply::ALL.iter().all(|supply| self.count(*supply) == 0)
+    }
+}
+
+impl Display for Supplies {
+    /// Non-zero supplies, such as "2 torches, 1 oil flask", or "nothing".
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let carried: Vec<String> = Supply::ALL
+            .iter()
+            .filter(|supply| self.count(**supply) > 0)
+            .map(|supply| supply.count_label(self.count(*supply)))
+            .collect();
+        if carried.is_empty() {
+            write!(f, "nothing")
+        } else {
+            write!(f, "{}", carried.join(", "))
+        }
+    }
+}
+
+/// How spare supplies are used.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct SupplySettings {
+    /// Light the next torch or candle, or refuel a lantern, as soon as one burns out.
+    pub auto_replace: bool,
+    pub torch_minutes: u32,
+    pub oil_flask_minutes: u32,
+    pub candle_minutes: u32,
+}
+
+impl SupplySettings {
+    /// How long one of this supply burns.
+    pub fn minutes(&self, supply: Supply) -> u32 {
+        match supply {
+            Supply::Torch => self.torch_minutes,
+            Supply::OilFlask => self.oil_flask_minutes,
+            Supply::Candle => self.candle_minutes,
+        }
+    }
+}
+
+impl Default for SupplySettings {
+    fn default() -> Self {
+        Self {
+            auto_replace: false,
+            torch_minutes: 60,
+            oil_flask_minutes: 60,
+            candle_minutes: 60,
+        }
+    }
+}
+
+/// A named starting point for new lights, such as "Torch 30ft/60m".
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct LightTemplate {
+    pub name: String,
+    pub light_type: LightSourceType,
+    pub radius_feet: u32,
+    pub minutes: u32,
+    /// Burn model for lights made from this template. Uses the type's model if `None`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub burn: Option<BurnModel>,
+}
+
+impl LightTemplate {
+    /// A freshly lit light made from this template.
+    pub fn light(&self, label: impl Into<String>) -> LightSource {
+        LightSource {
+            label: label.into(),
+            id: 0,
+            owner: None,
+            light_type: self.light_type.clone(),
+            radius_feet: self.radius_feet,
+            minutes_remaining: self.minutes,
+            last_roll: None,
+            state: LightState::Lit,
+            burn: self.burn.clone(),
+        }
+    }
+}
+
+pub fn default_light_templates() -> Vec<LightTemplate> {
+    vec![
+        LightTemplate {
+            name: "Torch 30ft/60m".to_string(),
+            light_type: LightSourceType::Torch,
+            radius_feet: 30,
+            minutes: 60,
+            burn: None,
+        },
+        LightTemplate {
+            name: "Lantern 30ft/1h per oil flask".to_string(),
+            light_type: LightSourceType::Lantern,
+            radius_feet: 30,
+            minutes: 60,
+            burn: None,
+        },
+        LightTemplate {
+            name: "Candle 5ft/1h".to_string(),
+            light_type: LightSourceType::Candle,
+            radius_feet: 5,
+            minutes: 60,
+            burn: None,
+        },
+        LightTemplate {
+            name: "Light spell 30ft/1h".to_string(),
+            light_type: LightSourceType::Spell("Light".to_string()),
+            radius_feet: 30,
+            minutes: 60,
+            burn: Some(BurnModel::RealTime),
+        },
+    ]
+}
+
+/// A member of the party.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct Character {
+    /// Stable id lights and effects refer to the character by.
+    pub id: u32,
+    pub name: String,
+    pub hp: i32,
+    pub max_hp: i32,
+    pub ac: i32,
+    pub level: u32,
+    pub luck_tokens: u32,
+    /// Free-form conditions such as "Poisoned" or "Exhausted".
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub conditions: Vec<String>,
+    /// Spare torches, oil flasks and candles carried.
+    #[serde(default)]
+    pub supplies: Supplies,
+}
+
+impl Character {
+    pub fn new(id: u32, name: impl Into<String>) -> Self {
+        Self {
+            id,
+            name: name.into(),
+            hp: 1,
+            max_hp: 1,
+            ac: 10,
+            level: 1,
+            luck_tokens: 0,
+            conditions: vec![],
+            supplies: Supplies::default(),
+        }
+    }
+
+    pub fn is_down(&self) -> bool {
+        self.hp <= 0
+    }
+
+    /// Sets the maximum HP, at least 1, bringing current HP down to it if it was above.
+    pub fn set_max_hp(&mut self, max_hp: i32) {
+        self.max_hp = max_hp.max(1);
+        self.hp = self.hp.min(self.max_hp);
+    }
+
+    /// Adds a condition unless the character already has it. Returns `false` if it did.
+    pub fn add_condition(&mut self, condition: &str) -> bool {
+        let condition = condition.trim();
+        if condition.is_empty() || self.has_condition(condition) {
+            return false;
+        }
+        self.conditions.push(condition.to_string());
+        true
+    }
+
+    /// Removes a condition, ignoring case. Returns `false` if the character didn't have it.
+    pub fn remove_condition(&mut self, condition: &str) -> bool {
+        let before = self.conditions.len();
+        self.conditions
+            .retain(|existing| !existing.eq_ignore_ascii_case(condition.trim()));
+        self.conditions.len() != before
+    }
+
+    pub fn has_condition(&self, condition: &str) -> bool {
+        self.conditions
+            .iter()
+            .any(|existing| existing.eq_ignore_ascii_case(condition.trim()))
+    }
+}
+
+impl Display for Character {
+    /// Such as "Ginny (L2) HP 5/8 AC 14, 1 luck, Poisoned".
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} (L{}) HP {}/{} AC {}",
+            self.name, self.level, self.hp, self.max_hp, self.ac
+        )?;
+        if self.luck_tokens > 0 {
+            write!(f, ", {} luck", self.luck_tokens)?;
+        }
+        for condition in &self.conditions {
+            write!(f, ", {}", condition)?;
+        }
+        Ok(())
+    }
+}
+
+/// What a timed effect is on.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum EffectTarget {
+    Party,
+    /// A character, by id.
+    Character(u32),
+    /// A light source, by id.
+    Light(u32),
+}
+
+/// The unit a timed effect counts down in.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum EffectUnit {
+    /// Minutes of game time.
+    Minutes,
+    /// Exploration turns, counted down as each turn is processed.
+    Turns,
+    /// Combat rounds.
+    Rounds,
+}
+
+impl EffectUnit {
+    pub const ALL: [EffectUnit; 3] = [EffectUnit::Minutes, EffectUnit::Turns, EffectUnit::Rounds];
+
+    /// Such as "1 turn" or "3 rounds".
+    pub fn count_label(&self, count: u32) -> String {
+        let (one, many) = match self {
+            EffectUnit::Minutes => ("min", "min"),
+            EffectUnit::Turns => ("turn", "turns"),
+            EffectUnit::Rounds => ("round", "rounds"),
+        };
+        format!("{} {}", count, if count == 1 { one } else { many })
+    }
+}
+
+impl Display for EffectUnit {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EffectUnit::Minutes => write!(f, "Minutes"),
+            EffectUnit::Turns => write!(f, "Turns"),
+            EffectUnit::Rounds => write!(f, "Rounds"),
+        }
+    }
+}
+
+/// A spell, poison, blessing or the like that wears off after a while.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct TimedEffect {
+    pub name: String,
+    pub target: EffectTarget,
+    pub unit: EffectUnit,
+    /// Minutes, turns or rounds left before it wears off.
+    pub remaining: u32,
+    /// Logged when it wears off. A default message naming the effect and target if empty.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub expiry_message: String,
+}
+
+impl TimedEffect {
+    pub fn new(
+        name: impl Into<String>,
+        target: EffectTarget,
+        unit: EffectUnit,
+        length: u32,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            target,
+            unit,
+            remaining: length,
+            expiry_message: String::new(),
+        }
+    }
+
+    pub fn time_left_label(&self) -> String {
+        self.unit.count_label(self.remaining)
+    }
+}
+
+/// Someone taking part in a fight.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct Combatant {
+    pub name: String,
+    /// The roster character this is, or `None` for a monster.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub character: Option<u32>,
+    /// Rolled for initiative, such as "d20+2".
+    #[serde(default = "default_initiative_dice")]
+    pub dice: DiceExpr,
+    /// The last initiative roll, once rolled.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub initiative: Option<i64>,
+    /// Group and creature index in the last encounter's monsters, for a monster rolled from
+    /// the bestiary.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub creature: Option<(usize, usize)>,
+}
+
+impl Combatant {
+    pub fn monster(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            character: None,
+            dice: default_initiative_dice(),
+            initiative: None,
+            creature: None,
+        }
+    }
+
+    pub fn is_monster(&self) -> bool {
+        self.character.is_none()
+    }
+}
+
+fn default_initiative_dice() -> DiceExpr {
+    DiceExpr::dice(1, 20)
+}
+
+/// How the order combatants act in is worked out.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum InitiativeMode {
+    /// Everyone rolls and acts from highest to lowest.
+    #[default]
+    Individual,
+    /// The party and the monsters each roll once, and the higher side acts first.
+    Group,
+    /// Everyone rolls, the highest acts first, and play goes on around the table from them
+    /// with the monsters acting at the GM's seat, as in Shadowdark.
+    Clockwise,
+}
+
+impl InitiativeMode {
+    pub const ALL: [InitiativeMode; 3] = [
+        InitiativeMode::Individual,
+        InitiativeMode::Group,
+        InitiativeMode::Clockwise,
+    ];
+}
+
+impl Display for InitiativeMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InitiativeMode::Individual => write!(f, "Individual"),
+            InitiativeMode::Group => write!(f, "Group"),
+            InitiativeMode::Clockwise => write!(f, "Clockwise from highest"),
+        }
+    }
+}
+
+/// A fight in progress, counted in rounds while the exploration clock waits.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct Combat {
+    /// The round being fought, from 1.
+    pub round: u32,
+    /// Combatants in the order they act.
+    pub order: Vec<Combatant>,
+    /// Index into `order` of whoever's turn it is.
+    #[serde(default)]
+    pub current: usize,
+    /// Game time the finished rounds took, added to the clock when combat ends.
+    pub elapsed_secs: u64,
+}
+
+impl Combat {
+    pub fn new(order: Vec<Combatant>) -> Self {
+        Self {
+            round: 1,
+            order,
+            current: 0,
+            elapsed_secs: 0,
+        }
+    }
+
+    /// Whoever's turn it is.
+    pub fn current(&self) -> Option<&Combatant> {
+        self.order.get(self.current)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct CombatSettings {
+    /// Game time one combat round takes.
+    pub round_seconds: u32,
+    #[serde(default)]
+    pub initiative: InitiativeMode,
+    /// Initiative dice new combatants roll.
+    #[serde(default = "default_initiative_dice")]
+    pub initiative_dice: DiceExpr,
+}
+
+impl Default for CombatSettings {
+    fn default() -> Self {
+        Self {
+            round_seconds: 6,
+            initiative: InitiativeMode::default(),
+            initiative_dice: default_initiative_dice(),
+        }
+    }
+}
+
+/// The dice a morale check is rolled with. Monsters hold on a roll at or under their morale.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum MoraleRule {
+    /// Classic 2d6, with morale from 2 (always breaks) to 12 (never does).
+    #[default]
+    TwoD6,
+    /// A d20, with morale up to 20.
+    D20,
+}
+
+impl MoraleRule {
+    pub const ALL: [MoraleRule; 2] = [MoraleRule::TwoD6, MoraleRule::D20];
+
+    pub fn dice(&self) -> DiceExpr {
+        match self {
+            MoraleRule::TwoD6 => DiceExpr::dice(2, 6),
+            MoraleRule::D20 => DiceExpr::dice(1, 20),
+        }
+    }
+}
+
+impl Display for MoraleRule {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MoraleRule::TwoD6 => write!(f, "2d6"),
+            MoraleRule::D20 => write!(f, "d20"),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct MoraleSettings {
+    /// Check morale when half a group falls or its leader dies.
+    pub automatic: bool,
+    #[serde(default)]
+    pub rule: MoraleRule,
+}
+
+impl Default for MoraleSettings {
+    fn default() -> Self {
+        Self {
+            automatic: true,
+            rule: MoraleRule::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ShadowtrackData {
+    pub turn: u32,
+    pub light_sources: Vec<LightSource>,
+    #[serde(default)]
+    pub burn_models: BurnModels,
+    /// Lit lights with this many minutes or fewer left are guttering.
+    #[serde(default = "default_light_warning_minutes")]
+    pub light_warning_minutes: u32,
+    #[serde(default)]
+    pub illumination: IlluminationSettings,
+    /// Whether the party was last seen in total darkness.
+    #[serde(default)]
+    pub in_darkness: bool,
+    /// Danger level to go back to when the party has light again.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub danger_before_darkness: Option<DangerLevel>,
+    pub encounter_table: RollTable,
+    pub torch_event_table: RollTable,
+    /// Named tables that other table results can reference as `[[Name]]`.
+    #[serde(default)]
+    pub tables: Vec<RollTable>,
+    pub encounter_roll: Option<u8>,
+    #[serde(default)]
+    pub danger_level: DangerLevel,
+    #[serde(default)]
+    pub encounter_follow_ups: EncounterFollowUps,
+    pub event_log: Vec<TurnEntry>,
+    pub clock_elapsed: u64,
+    pub next_process_minutes: Option<u64>,
+    pub process_interval_minutes: u64,
+    /// Seeded RNG position, so a reloaded save rolls the same future results.
+    #[serde(default)]
+    pub rng: RngState,
+    /// Every roll made this session, for replaying disputed results.
+    #[serde(default)]
+    pub roll_journal: Vec<RollRecord>,
+
+    /// The party, in marching order.
+    #[serde(default)]
+    pub characters: Vec<Character>,
+    /// Whether the lights have been linked to the roster. Saves made before the roster are
+    /// linked by name once, when they're loaded.
+    #[serde(default)]
+    pub roster_linked: bool,
+    /// The fight in progress, if any. Exploration time waits until it ends.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub combat: Option<Combat>,
+    #[serde(default)]
+    pub combat_settings: CombatSettings,
+    /// What the last encounter that hit was, such as "Goblins", for starting a fight.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_encounter: Option<String>,
+    /// Creatures the last encounter turned up, if it referenced the bestiary, tracking their
+    /// HP and morale while the encounter plays out.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub last_monsters: Vec<MonsterGroup>,
+    #[serde(default)]
+    pub morale_settings: MoraleSettings,
+    /// Stat blocks encounter table entries can reference as `{{Name}}`.
+    #[serde(default = "default_bestiary")]
+    pub bestiary: Vec<Monster>,
+    /// Effects counting down towards wearing off.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub effects: Vec<TimedEffect>,
+    #[serde(default)]
+    pub supply_settings: SupplySettings,
+    /// Templates offered when adding a light.
+    #[serde(default = "default_light_templates")]
+    pub light_templates: Vec<LightTemplate>,
+
+    pub new_light_type: LightSourceType,
+    pub new_light_label: String,
+    /// Party member picked to carry the new light. The label is used if there's none.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub new_light_owner: Option<u32>,
+    pub new_light_minutes: u32,
+    pub new_light_range: u32,
+    /// Burn model from the template the new light was picked from, if it has its own.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub new_light_burn: Option<BurnModel>,
+}
+
+impl ShadowtrackData {
+    /// The spare supplies carried by a light's owner.
+    pub fn supplies_of(&self, light: &LightSource) -> Supplies {
+        light
+            .owner
+            .and_then(|id| self.character(id))
+            .map_or_else(Supplies::default, |character| character.supplies)
+    }
+
+    pub fn character(&self, id: u32) -> Option<&Character> {
+        self.characters.iter().find(|character| character.id == id)
+    }
+
+    pub fn character_mut(&mut self, id: u32) -> Option<&mut Character> {
+        self.characters
+            .iter_mut()
+            .find(|character| character.id == id)
+    }
+
+    /// Looks a character up by name, ignoring case.
+    pub fn find_character(&self, name: &str) -> Option<&Character> {
+        self.characters
+            .iter()
+            .find(|character| character.name.eq_ignore_ascii_case(name.trim()))
+    }
+
+    /// Adds a character to the end of the roster and returns its id.
+    pub fn add_character(&mut self, name: &str) -> u32 {
+        let id = self
+            .characters
+            .iter()
+            .map(|character| character.id + 1)
+            .max()
+            .unwrap_or(1);
+        self.characters.push(Character::new(id, name.trim()));
+        id
+    }
+
+    /// The id of the character with this name, adding them to the roster if there's none.
+    pub fn character_id(&mut self, name: &str) -> u32 {
+        match self.find_character(name) {
+            Some(character) => character.id,
+            None => self.add_character(name),
+        }
+    }
+
+    /// Renames a character along with every light they carry.
+    pub fn rename_character(&mut self, id: u32, name: &str) {
+        let Some(character) = self.character_mut(id) else {
+            return;
+        };
+        character.name = name.to_string();
+        for light in &mut self.light_sources {
+            if light.owner == Some(id) {
+                light.label = name.to_string();
+            }
+        }
+    }
+
+    /// Takes a character off the roster, with the effects on them. Their lights stay, no
+    /// longer linked to anyone.
+    pub fn remove_character(&mut self, id: u32) {
+        self.characters.retain(|character| character.id != id);
+        self.effects
+            .retain(|effect| effect.target != EffectTarget::Character(id));
+        for light in &mut self.light_sources {
+            if light.owner == Some(id) {
+                light.owner = None;
+            }
+        }
+    }
+
+    /// The lights a character carries, with their indexes.
+    pub fn character_lights(&self, id: u32) -> impl Iterator<Item = (usize, &LightSource)> {
+        self.light_sources
+            .iter()
+            .enumerate()
+            .filter(move |(_, light)| light.owner == Some(id))
+    }
+
+    pub fn light(&self, id: u32) -> Option<&LightSource> {
+        self.light_sources.iter().find(|light| light.id == id)
+    }
+
+    /// Gives every light without one an id, for lights from saves made before lights had ids.
+    pub fn number_lights(&mut self) {
+        for index in 0..self.light_sources.len() {
+            if self.light_sources[index].id == 0 {
+                self.light_sources[index].id = self.next_light_id();
+            }
+        }
+    }
+
+    /// Works out whether the party is in total darkness from its lights, without logging it,
+    /// for saves made before darkness was tracked.
+    pub fn sync_darkness(&mut self) {
+        self.in_darkness = self.illumination() == Illumination::Darkness;
+    }
+
+    pub fn next_light_id(&self) -> u32 {
+        self.light_sources
+            .iter()
+            .map(|light| light.id + 1)
+            .max()
+            .unwrap_or(1)
+    }
+
+    /// Who or what an effect is on, such as "the party", "Ginny" or "Ginny's torch".
+    pub fn effect_target_name(&self, target: EffectTarget) -> String {
+        match target {
+            EffectTarget::Party => "the party".to_string(),
+            EffectTarget::Character(id) => self
+                .character(id)
+                .map_or_else(|| "someone".to_string(), |character| character.name.clone()),
+            EffectTarget::Light(id) => self
+                .light(id)
+                .map_or_else(|| "a light".to_string(), |light| light.name()),
+        }
+    }
+
+    /// Links every light without an owner to the character named by its label, adding
+    /// characters as needed, for saves made before the roster. Only done once, so characters
+    /// taken off the roster later don't come back.
+    pub fn link_characters(&mut self) {
+        if self.roster_linked {
+            return;
+        }
+        self.roster_linked = true;
+        for index in 0..self.light_sources.len() {
+            let light = &self.light_sources[index];
+            if light.owner.is_some() || light.label.trim().is_empty() {
+                continue;
+            }
+            let id = self.character_id(&light.label.clone());
+            self.light_sources[index].owner = Some(id);
+        }
+    }
+
+    /// Fills in the new light fields from a template.
+    pub fn apply_light_template(&mut self, index: usize) {
+        if let Some(template) = self.light_templates.get(index) {
+            self.new_light_type = template.light_type.clone();
+            self.new_light_range = template.radius_feet;
+            self.new_light_minutes = template.minutes;
+            self.new_light_burn = template.burn.clone();
+        }
+    }
+
+    /// Looks a light template up by name, ignoring case.
+    pub fn find_light_template(&self, name: &str) -> Option<&LightTemplate> {
+        self.light_templates
+            .iter()
+            .find(|template| template.name.eq_ignore_ascii_case(name.trim()))
+    }
+
+    /// Adds templates from a shared library, replacing any with the same name.
+    pub fn import_light_templates(&mut self, templates: Vec<LightTemplate>) {
+        for template in templates {
+            match self
+                .light_templates
+                .iter_mut()
+                .find(|existing| existing.name.eq_ignore_ascii_case(&template.name))
+            {
+                Some(existing) => *existing = template,
+                None => self.light_templates.push(template),
+            }
+        }
+    }
+
+    /// The bestiary entry with this name, ignoring case.
+    pub fn monster(&self, name: &str) -> Option<&Monster> {
+        self.bestiary
+            .iter()
+            .find(|monster| monster.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Adds monsters from a shared bestiary, replacing any with the same name.
+    pub fn import_monsters(&mut self, monsters: Vec<Monster>) {
+        for monster in monsters {
+            match self
+                .bestiary
+                .iter_mut()
+                .find(|existing| existing.name.eq_ignore_ascii_case(&monster.name))
+            {
+                Some(existing) => *existing = monster,
+                None => self.bestiary.push(monster),
+            }
+        }
+    }
+
+    /// The event at `index` in the given turn's log entry.
+    pub fn logged_event(&self, turn: u32, index: usize) -> Option<&LoggedEvent> {
+        self.event_log
+            .iter()
+            .find(|entry| entry.turn == turn)
+            .and_then(|entry| entry.events.get(index))
+    }
+
+    pub fn logged_event_mut(&mut self, turn: u32, index: usize) -> Option<&mut LoggedEvent> {
+        self.event_log
+            .iter_mut()
+            .find(|entry| entry.turn == turn)
+            .and_then(|entry| entry.events.get_mut(index))
+    }
+
+    /// Every tag in the log, with the suggested ones, in alphabetical order.
+    pub fn log_tags(&self) -> Vec<String> {
+        let used = self
+            .event_log
+            .iter()
+            .flat_map(|entry| &entry.events)
+            .flat_map(|event| event.tags.iter().cloned());
+        let suggested = SUGGESTED_TAGS.iter().map(|tag| tag.to_string());
+        let tags: BTreeSet<String> = used.chain(suggested).collect();
+        tags.into_iter().collect()
+    }
+
+    /// Who to fight from the last encounter: each creature it turned up, or the encounter
+    /// itself if it didn't reference the bestiary.
+    pub fn last_encounter_foes(&self) -> Vec<String> {
+        if self.last_monsters.is_empty() {
+            return self.last_encounter.iter().cloned().collect();
+        }
+        self.last_monsters
+            .iter()
+            .flat_map(MonsterGroup::creature_names)
+            .collect()
+    }
+
+    /// Group and index of the last encounter's creature with this name, ignoring case.
+    pub fn find_creature(&self, name: &str) -> Option<(usize, usize)> {
+        self.last_monsters
+            .iter()
+            .enumerate()
+            .find_map(|(group_index, group)| {
+                (0..group.hp.len())
+                    .find(|&index| group.creature_name(index).eq_ignore_ascii_case(name))
+                    .map(|index| (group_index, index))
+            })
+    }
+
+    /// Whether a combatant can't act: a character who is down, or a creature that is dead or
+    /// has fled.
+    pub fn is_out_of_fight(&self, combatant: &Combatant) -> bool {
+        match (combatant.character, combatant.creature) {
+            (Some(id), _) => self.character(id).is_some_and(Character::is_down),
+            (None, Some((group, index))) => self
+                .last_monsters
+                .get(group)
+                .is_some_and(|group| index < group.hp.len() && group.is_out(index)),
+            (None, None) => false,
+        }
+    }
+
+    /// Builds the light described by the new light fields and clears its owner and label,
+    /// leaving the rest ready for the next light. `None` if neither has been given.
+    pub fn take_new_light(&mut self) -> Option<LightSource> {
+        let owner = self.new_light_owner.and_then(|id| self.character(id));
+        let label = match owner {
+            Some(character) => character.name.clone(),
+            None => self.new_light_label.clone(),
+        };
+        if label.is_empty() {
+            return None;
+        }
+        let owner = owner.map(|character| character.id);
+        self.new_light_owner = None;
+        self.new_light_label.clear();
+        Some(LightSource {
+            label,
+            id: 0,
+            owner,
+            light_type: self.new_light_type.clone(),
+            radius_feet: self.new_light_range,
+            minutes_remaining: self.new_light_minutes,
+            last_roll: None,
+            state: LightState::Lit,
+            burn: self.new_light_burn.clone(),
+        })
+    }
+
+    /// The party's illumination from its lit lights.
+    pub fn illumination(&self) -> Illumination {
+        let lit = self
+            .light_sources
+            .iter()
+            .filter(|light| light.state.is_lit());
+        let mut illumination = Illumination::Darkness;
+        for light in lit {
+            if light.state == LightState::Lit
+                && light.radius_feet >= self.illumination.bright_radius_feet
+            {
+                return Illumination::Bright;
+            }
+            illumination = Illumination::Dim;
+        }
+        illumination
+    }
+}
+
+fn default_light_warning_minutes() -> u32 {
+    10
+}
+
+impl Default for ShadowtrackData {
+    fn default() -> Self {
+        Self {
+            new_light_type: LightSourceType::default(),
+            turn: 0,
+            encounter_roll: None,
+            danger_level: DangerLevel::default(),
+            encounter_follow_ups: EncounterFollowUps::default(),
+            event_log: vec![],
+            light_sources: vec![],
+            burn_models: BurnModels::default(),
+            light_warning_minutes: default_light_warning_minutes(),
+            illumination: IlluminationSettings::default(),
+            // A new party hasn't lit anything yet.
+            in_darkness: true,
+            danger_before_darkness: None,
+            new_light_label: String::new(),
+            new_light_owner: None,
+            new_light_minutes: 60,
+            new_light_range: 30,
+            new_light_burn: None,
+            light_templates: default_light_templates(),
+            characters: vec![],
+            roster_linked: true,
+            effects: vec![],
+            combat: None,
+            combat_settings: CombatSettings::default(),
+            last_encounter: None,
+            last_monsters: vec![],
+            morale_settings: MoraleSettings::default(),
+            bestiary: default_bestiary(),
+            supply_settings: SupplySettings::default(),
+            encounter_table: RollTable::uniform(DEFAULT_ENCOUNTER_TABLE.iter().copied()),
+            torch_event_table: RollTable::uniform(DEFAULT_TORCH_EVENTS_TABLE.iter().copied()),
+            tables: vec![],
+            clock_elapsed: 0_u64,
+            next_process_minutes: None,
+            process_interval_minutes: 10,
+            rng: RngState::from_entropy(),
+            roll_journal: vec![],
+        }
+    }
+}
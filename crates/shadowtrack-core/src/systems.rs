@@ -0,0 +1,1061 @@
+use crate::bestiary::{strip_monster_references, MonsterGroup};
+use crate::dice::{DiceExpr, DiceRoll};
+use crate::event_log::{format_clock, EventDetail, LogEvent, LoggedEvent, TurnEntry};
+use crate::resolve::{roll_table, RollTree};
+use crate::rng::RandomSource;
+use crate::state::{
+    BurnModel, Combat, Combatant, DangerLevel, EffectTarget, EffectUnit, EncounterDetail,
+    Illumination, InitiativeMode, LightSource, LightState, ShadowtrackData, Supply, TimedEffect,
+};
+
+pub fn log_event(data: &mut ShadowtrackData, event: LogEvent, text: &str) {
+    log_event_with(data, event, text, None);
+}
+
+/// Logs an event along with its structured detail, stamped with the game time and the real
+/// time it happened at.
+pub fn log_event_with(
+    data: &mut ShadowtrackData,
+    event: LogEvent,
+    text: &str,
+    detail: Option<EventDetail>,
+) {
+    // Rounds fought so far count towards game time before the clock catches up.
+    let game_secs = data.clock_elapsed + data.combat.as_ref().map_or(0, |c| c.elapsed_secs);
+    let entry = match data.event_log.iter_mut().position(|e| e.turn == data.turn) {
+        // Update existing TurnEntry in log.
+        Some(index) => &mut data.event_log[index],
+        // Need a new TurnEntry for the log.
+        None => {
+            data.event_log.push(TurnEntry {
+                turn: data.turn,
+                ..Default::default()
+            });
+            data.event_log.last_mut().unwrap()
+        }
+    };
+    entry.events.push(LoggedEvent {
+        detail,
+        ..LoggedEvent::new(event, text, game_secs)
+    });
+}
+
+/// Keeps the roll tree for results that referenced other tables or dice.
+fn table_result_detail(tree: RollTree) -> Option<EventDetail> {
+    (!tree.children.is_empty()).then_some(EventDetail {
+        roll_tree: Some(tree),
+        ..Default::default()
+    })
+}
+
+/// Burns each lit light for a processed turn according to its type's burn model.
+pub fn process_light_burn(data: &mut ShadowtrackData, rng: &mut impl RandomSource) {
+    for light in &mut data.light_sources {
+        light.last_roll = None;
+        if !light.state.is_lit() {
+            continue;
+        }
+        match light.burn_model(&data.burn_models).clone() {
+            BurnModel::PerTurn { minutes } => {
+                light.minutes_remaining = light.minutes_remaining.saturating_sub(minutes);
+            }
+            BurnModel::Chance {
+                minutes,
+                dice,
+                chance,
+                extra_minutes,
+            } => {
+                rng.set_purpose(format!("Light burn: {}", light.label).as_str());
+                light.minutes_remaining = light.minutes_remaining.saturating_sub(minutes);
+                let roll = dice.roll(rng).total;
+                light.last_roll = u8::try_from(roll).ok();
+                if roll <= chance {
+                    light.minutes_remaining = light.minutes_remaining.saturating_sub(extra_minutes);
+                }
+            }
+            BurnModel::RealTime | BurnModel::Never | BurnModel::Concentration => {}
+        }
+    }
+    update_light_states(data);
+}
+
+/// Burns lit real-time lights for minutes passed on the game clock.
+pub fn burn_real_time(data: &mut ShadowtrackData, minutes: u32) {
+    for light in &mut data.light_sources {
+        let burn = light.burn_model(&data.burn_models);
+        if light.state.is_lit() && *burn == BurnModel::RealTime {
+            light.minutes_remaining = light.minutes_remaining.saturating_sub(minutes);
+        }
+    }
+    update_light_states(data);
+}
+
+/// Moves lit lights to guttering or burnt out as their time runs low, logging each change.
+pub fn update_light_states(data: &mut ShadowtrackData) {
+    let mut messages = vec![];
+    let mut burnt_out = vec![];
+    for (index, light) in data.light_sources.iter_mut().enumerate() {
+        if !light.state.is_lit() || !light.burn_model(&data.burn_models).expires() {
+            continue;
+        }
+        let state = burning_state(light.minutes_remaining, data.light_warning_minutes);
+        if state != light.state {
+            light.state = state;
+            if state == LightState::BurntOut {
+                burnt_out.push(index);
+            }
+            let event = match state {
+                LightState::BurntOut => LogEvent::LightBurnout {
+                    light: light.id,
+                    owner: light.label.clone(),
+                    kind: light.light_type.clone(),
+                },
+                _ => LogEvent::LightChange {
+                    light: light.id,
+                    state,
+                },
+            };
+            messages.push((
+                event,
+                match state {
+                    LightState::BurntOut => format!("{} gutters out", light.name()),
+                    LightState::Guttering => format!(
+                        "{} is guttering, {} min left",
+                        light.name(),
+                        light.minutes_remaining
+                    ),
+                    _ => format!("{} burns steadily again", light.name()),
+                },
+            ));
+        }
+    }
+    for (event, message) in messages {
+        log_event(data, event, &message);
+    }
+    for index in burnt_out {
+        offer_replacement(data, index);
+    }
+    update_illumination(data);
+}
+
+/// How a lit light burns with this many minutes left: guttering at or below the warning, burnt
+/// out at none.
+fn burning_state(minutes_remaining: u32, warning_minutes: u32) -> LightState {
+    if minutes_remaining == 0 {
+        LightState::BurntOut
+    } else if minutes_remaining <= warning_minutes {
+        LightState::Guttering
+    } else {
+        LightState::Lit
+    }
+}
+
+/// Replaces a light that just burnt out from its owner's supplies when that happens
+/// automatically, otherwise logs what the owner has left to replace it with.
+fn offer_replacement(data: &mut ShadowtrackData, index: usize) {
+    let light = &data.light_sources[index];
+    let Some(supply) = Supply::for_light(&light.light_type) else {
+        return;
+    };
+    let spare = data.supplies_of(light).count(supply);
+    if spare == 0 {
+        return;
+    }
+    if !data.supply_settings.auto_replace {
+        let message = format!(
+            "{} has {} to replace it",
+            light.label,
+            supply.count_label(spare)
+        );
+        log_event(data, LogEvent::Message, &message);
+    } else {
+        refill_from_supply(data, index);
+    }
+}
+
+/// Takes one of the supply a light burns from its owner, lighting a fresh torch or candle
+/// in place of a burnt out one, or adding a flask of oil to a lantern.
+fn refill_from_supply(data: &mut ShadowtrackData, index: usize) -> bool {
+    let Some(light) = data.light_sources.get(index) else {
+        return false;
+    };
+    let Some(supply) = Supply::for_light(&light.light_type) else {
+        return false;
+    };
+    let refuel = supply == Supply::OilFlask;
+    if !refuel && light.state != LightState::BurntOut {
+        return false;
+    }
+    let owner = light.label.clone();
+    let Some(left) = light.owner.and_then(|id| take_supply(data, id, supply)) else {
+        return false;
+    };
+
+    let minutes = data.supply_settings.minutes(supply);
+    let warning_minutes = data.light_warning_minutes;
+    let light = &mut data.light_sources[index];
+    let message = if refuel {
+        light.minutes_remaining += minutes;
+        format!(
+            "{} is refuelled (+{} min), {} left",
+            light.name(),
+            minutes,
+            supply.count_label(left)
+        )
+    } else {
+        light.minutes_remaining = minutes;
+        format!(
+            "{} lights a fresh {}, {} left",
+            owner,
+            light.light_type.to_string().to_lowercase(),
+            supply.count_label(left)
+        )
+    };
+    if light.state == LightState::BurntOut || light.state.is_lit() {
+        light.state = burning_state(light.minutes_remaining, warning_minutes);
+    }
+    let event = LogEvent::Supplies {
+        owner,
+        supply,
+        left,
+    };
+    log_event(data, event, &message);
+    true
+}
+
+/// Lights the next torch or candle in place of a burnt out one, or refuels a lantern, from
+/// the owner's supplies. Returns `false` if the owner has none left.
+pub fn replace_from_supply(data: &mut ShadowtrackData, index: usize) -> bool {
+    let replaced = refill_from_supply(data, index);
+    if replaced {
+        update_light_states(data);
+    }
+    replaced
+}
+
+/// Takes one of a supply from a character, returning what they have left, such as
+/// "2 torches". `None` if they have none.
+fn take_supply(data: &mut ShadowtrackData, character: u32, supply: Supply) -> Option<u32> {
+    let spare = data.character_mut(character)?.supplies.count_mut(supply);
+    if *spare == 0 {
+        return None;
+    }
+    *spare -= 1;
+    Some(*spare)
+}
+
+/// Lights a new light from a character's supplies, sized like the first template of its
+/// type. Returns `false` if they have none left.
+pub fn light_from_supply(data: &mut ShadowtrackData, character: u32, supply: Supply) -> bool {
+    let Some(left) = take_supply(data, character, supply) else {
+        return false;
+    };
+    let owner = data
+        .character(character)
+        .map_or_else(String::new, |c| c.name.clone());
+
+    let light_type = supply.light_type();
+    let mut light = match data
+        .light_templates
+        .iter()
+        .find(|template| template.light_type == light_type)
+    {
+        Some(template) => template.light(&owner),
+        None => LightSource {
+            label: owner.clone(),
+            light_type,
+            radius_feet: 30,
+            ..Default::default()
+        },
+    };
+    light.owner = Some(character);
+    light.minutes_remaining = data.supply_settings.minutes(supply);
+    let message = format!(
+        "{} lights a {}, {} left",
+        owner,
+        light.light_type.to_string().to_lowercase(),
+        supply.count_label(left)
+    );
+    let event = LogEvent::Supplies {
+        owner,
+        supply,
+        left,
+    };
+    log_event(data, event, &message);
+    add_light(data, light);
+    update_light_states(data);
+    true
+}
+
+/// Adds a light source to the party. A light carried by a character takes their name; one
+/// whose owner isn't on the roster is kept unowned under its own label.
+pub fn add_light(data: &mut ShadowtrackData, mut light: LightSource) {
+    match light.owner.and_then(|id| data.character(id)) {
+        Some(character) => light.label = character.name.clone(),
+        None => light.owner = None,
+    }
+    if light.id == 0 {
+        light.id = data.next_light_id();
+    }
+    data.light_sources.push(light);
+    update_illumination(data);
+}
+
+/// Logs the party entering total darkness, switching to the darkness danger level until it
+/// has light again.
+pub fn update_illumination(data: &mut ShadowtrackData) {
+    let dark = data.illumination() == Illumination::Darkness;
+    if dark == data.in_darkness {
+        return;
+    }
+    data.in_darkness = dark;
+    if dark {
+        log_event(
+            data,
+            LogEvent::Darkness,
+            "The last light goes out. The party is in total darkness!",
+        );
+        if let Some(level) = data.illumination.darkness_danger_level {
+            if level != data.danger_level {
+                data.danger_before_darkness = Some(data.danger_level);
+                set_danger_level(data, level);
+            }
+        }
+    } else {
+        // Only go back if the GM hasn't picked another level in the meantime.
+        if let Some(level) = data.danger_before_darkness.take() {
+            if Some(data.danger_level) == data.illumination.darkness_danger_level {
+                set_danger_level(data, level);
+            }
+        }
+    }
+}
+
+/// Puts a lit light out on purpose, keeping its remaining time. Returns `false` if the light
+/// wasn't lit.
+pub fn snuff_light(data: &mut ShadowtrackData, index: usize) -> bool {
+    put_out_light(data, index, LightState::Snuffed, "is snuffed out")
+}
+
+/// Puts a lit light out by accident, such as a gust of wind. Returns `false` if the light
+/// wasn't lit.
+pub fn extinguish_light(data: &mut ShadowtrackData, index: usize) -> bool {
+    put_out_light(data, index, LightState::Extinguished, "is extinguished")
+}
+
+fn put_out_light(data: &mut ShadowtrackData, index: usize, state: LightState, verb: &str) -> bool {
+    let Some(light) = data
+        .light_sources
+        .get_mut(index)
+        .filter(|light| light.state.is_lit())
+    else {
+        return false;
+    };
+    light.state = state;
+    let message = format!(
+        "{} {}, {} min left",
+        light.name(),
+        verb,
+        light.minutes_remaining
+    );
+    let event = LogEvent::LightChange {
+        light: light.id,
+        state,
+    };
+    log_event(data, event, &message);
+    update_illumination(data);
+    true
+}
+
+/// Relights a snuffed or extinguished light. Returns `false` if it can't be relit.
+pub fn relight_light(data: &mut ShadowtrackData, index: usize) -> bool {
+    let Some(light) = data
+        .light_sources
+        .get_mut(index)
+        .filter(|light| light.state.can_relight())
+    else {
+        return false;
+    };
+    light.state = LightState::Lit;
+    let message = format!("{} is relit", light.name());
+    let event = LogEvent::LightChange {
+        light: light.id,
+        state: light.state,
+    };
+    log_event(data, event, &message);
+    update_light_states(data);
+    true
+}
+
+/// Removes every burnt out light, logging each one. Returns how many were removed.
+pub fn remove_spent_lights(data: &mut ShadowtrackData) -> usize {
+    let (spent, kept) = std::mem::take(&mut data.light_sources)
+        .into_iter()
+        .partition::<Vec<_>, _>(|light| light.state == LightState::BurntOut);
+    data.light_sources = kept;
+    for light in &spent {
+        let event = LogEvent::LightChange {
+            light: light.id,
+            state: light.state,
+        };
+        log_event(
+            data,
+            event,
+            format!("{} is discarded", light.name()).as_str(),
+        );
+    }
+    data.effects.retain(|effect| match effect.target {
+        EffectTarget::Light(id) => spent.iter().all(|light| light.id != id),
+        _ => true,
+    });
+    update_illumination(data);
+    spent.len()
+}
+
+/// Starts a timed effect. An effect on a character also gives them its name as a condition
+/// until it wears off.
+pub fn add_effect(data: &mut ShadowtrackData, effect: TimedEffect) {
+    if let EffectTarget::Character(id) = effect.target {
+        if let Some(character) = data.character_mut(id) {
+            character.add_condition(&effect.name);
+        }
+    }
+    let message = format!(
+        "{} on {} for {}",
+        effect.name,
+        data.effect_target_name(effect.target),
+        effect.time_left_label()
+    );
+    let event = LogEvent::EffectStarted {
+        effect: effect.name.clone(),
+        target: effect.target,
+    };
+    data.effects.push(effect);
+    log_event(data, event, &message);
+}
+
+/// Counts effects measured in `unit` down by `amount`, logging and ending those that run out.
+pub fn count_down_effects(data: &mut ShadowtrackData, unit: EffectUnit, amount: u32) {
+    let mut expired = vec![];
+    data.effects.retain_mut(|effect| {
+        if effect.unit != unit {
+            return true;
+        }
+        effect.remaining = effect.remaining.saturating_sub(amount);
+        if effect.remaining > 0 {
+            return true;
+        }
+        expired.push(effect.clone());
+        false
+    });
+    for effect in expired {
+        let message = if effect.expiry_message.is_empty() {
+            format!(
+                "{} on {} wears off",
+                effect.name,
+                data.effect_target_name(effect.target)
+            )
+        } else {
+            effect.expiry_message.clone()
+        };
+        clear_effect_condition(data, &effect);
+        let event = LogEvent::EffectExpired {
+            effect: effect.name,
+            target: effect.target,
+        };
+        log_event(data, event, &message);
+    }
+}
+
+/// Ends an effect before it runs out. Returns `false` if there's no such effect.
+pub fn end_effect(data: &mut ShadowtrackData, index: usize) -> bool {
+    if index >= data.effects.len() {
+        return false;
+    }
+    let effect = data.effects.remove(index);
+    clear_effect_condition(data, &effect);
+    let message = format!(
+        "{} on {} ends early",
+        effect.name,
+        data.effect_target_name(effect.target)
+    );
+    let event = LogEvent::EffectExpired {
+        effect: effect.name,
+        target: effect.target,
+    };
+    log_event(data, event, &message);
+    true
+}
+
+/// Takes an ended effect's condition off its character, unless another effect of the same
+/// name is still on them.
+fn clear_effect_condition(data: &mut ShadowtrackData, effect: &TimedEffect) {
+    let EffectTarget::Character(id) = effect.target else {
+        return;
+    };
+    let still_on = data.effects.iter().any(|other| {
+        other.target == effect.target && other.name.eq_ignore_ascii_case(&effect.name)
+    });
+    if let Some(character) = data.character_mut(id).filter(|_| !still_on) {
+        character.remove_condition(&effect.name);
+    }
+}
+
+/// Starts a fight between the party's characters that are still standing and the given
+/// monsters. Returns `false` if a fight is already going on.
+pub fn start_combat(data: &mut ShadowtrackData, monsters: Vec<String>) -> bool {
+    if data.combat.is_some() {
+        return false;
+    }
+    let mut order: Vec<Combatant> = data
+        .characters
+        .iter()
+        .filter(|character| !character.is_down())
+        .map(|character| Combatant {
+            character: Some(character.id),
+            ..Combatant::monster(character.name.clone())
+        })
+        .collect();
+    order.extend(
+        monsters
+            .into_iter()
+            .map(|name| name.trim().to_string())
+            .filter(|name| !name.is_empty())
+            .map(|name| Combatant {
+                creature: data.find_creature(&name),
+                ..Combatant::monster(name)
+            }),
+    );
+    for combatant in &mut order {
+        combatant.dice = data.combat_settings.initiative_dice.clone();
+    }
+    let monsters: Vec<&str> = order
+        .iter()
+        .filter(|combatant| combatant.is_monster())
+        .map(|combatant| combatant.name.as_str())
+        .collect();
+    let message = if monsters.is_empty() {
+        "Combat begins".to_string()
+    } else {
+        format!("Combat begins against {}", monsters.join(", "))
+    };
+    data.combat = Some(Combat::new(order));
+    log_event(data, LogEvent::Combat { round: 1 }, &message);
+    true
+}
+
+/// Finishes the current round, passing its game time for lights and effects that burn by
+/// the minute and counting down effects measured in rounds. Returns `false` if there's no
+/// fight going on.
+pub fn next_round(data: &mut ShadowtrackData) -> bool {
+    if !finish_round(data) {
+        return false;
+    }
+    if let Some(combat) = &mut data.combat {
+        combat.round += 1;
+        combat.current = 0;
+        let message = format!("Round {}", combat.round);
+        let event = LogEvent::Combat {
+            round: combat.round,
+        };
+        log_event(data, event, &message);
+    }
+    true
+}
+
+/// Rolls initiative for everyone in the fight and puts them in order, the way the combat
+/// settings say. Returns `false` if there's no fight going on.
+pub fn roll_initiative(data: &mut ShadowtrackData, rng: &mut impl RandomSource) -> bool {
+    let mode = data.combat_settings.initiative;
+    // Where each character sits at the table, for going around it clockwise.
+    let seats: Vec<u32> = data
+        .characters
+        .iter()
+        .map(|character| character.id)
+        .collect();
+    let Some(combat) = &mut data.combat else {
+        return false;
+    };
+    let mut roll = |name: &str, dice: &DiceExpr| {
+        rng.set_purpose(format!("Initiative: {}", name).as_str());
+        dice.roll(rng).total
+    };
+    match mode {
+        InitiativeMode::Individual | InitiativeMode::Clockwise => {
+            for combatant in &mut combat.order {
+                combatant.initiative = Some(roll(&combatant.name, &combatant.dice));
+            }
+        }
+        InitiativeMode::Group => {
+            for (side, monsters) in [("Party", false), ("Monsters", true)] {
+                let Some(first) = combat.order.iter().find(|c| c.is_monster() == monsters) else {
+                    continue;
+                };
+                let initiative = roll(side, &first.dice.clone());
+                for combatant in &mut combat.order {
+                    if combatant.is_monster() == monsters {
+                        combatant.initiative = Some(initiative);
+                    }
+                }
+            }
+        }
+    }
+
+    // The party wins ties.
+    let by_initiative = |c: &Combatant| (std::cmp::Reverse(c.initiative), c.is_monster());
+    match mode {
+        InitiativeMode::Individual | InitiativeMode::Group => {
+            combat.order.sort_by_key(by_initiative)
+        }
+        InitiativeMode::Clockwise => {
+            combat.order.sort_by_key(|c| {
+                c.character
+                    .and_then(|id| seats.iter().position(|seat| *seat == id))
+                    .unwrap_or(seats.len())
+            });
+            let highest = combat
+                .order
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, c)| by_initiative(c))
+                .map_or(0, |(index, _)| index);
+            combat.order.rotate_left(highest);
+        }
+    }
+    combat.current = 0;
+
+    let rolls: Vec<String> = combat
+        .order
+        .iter()
+        .map(|c| format!("{} {}", c.name, c.initiative.unwrap_or_default()))
+        .collect();
+    let message = format!("Initiative ({}): {}", mode, rolls.join(", "));
+    let event = LogEvent::Combat {
+        round: combat.round,
+    };
+    log_event(data, event, &message);
+    true
+}
+
+/// Passes the turn to the next combatant still standing, starting a new round after the
+/// last. Returns `false` if there's no fight going on.
+pub fn next_turn(data: &mut ShadowtrackData) -> bool {
+    let Some(count) = data.combat.as_ref().map(|combat| combat.order.len()) else {
+        return false;
+    };
+    // Stops after going all the way around, in case nobody is standing.
+    for _ in 0..count.max(1) {
+        let Some(combat) = &mut data.combat else {
+            return false;
+        };
+        if combat.current + 1 >= count {
+            next_round(data);
+        } else {
+            combat.current += 1;
+        }
+        let out = data
+            .combat
+            .as_ref()
+            .and_then(|combat| combat.current())
+            .is_some_and(|combatant| data.is_out_of_fight(combatant));
+        if !out {
+            break;
+        }
+    }
+    true
+}
+
+fn finish_round(data: &mut ShadowtrackData) -> bool {
+    let Some(combat) = &mut data.combat else {
+        return false;
+    };
+    let before = data.clock_elapsed + combat.elapsed_secs;
+    combat.elapsed_secs += u64::from(data.combat_settings.round_seconds);
+    let minutes = (before + u64::from(data.combat_settings.round_seconds)) / 60 - before / 60;
+    count_down_effects(data, EffectUnit::Rounds, 1);
+    if minutes > 0 {
+        let minutes = u32::try_from(minutes).unwrap_or(u32::MAX);
+        burn_real_time(data, minutes);
+        count_down_effects(data, EffectUnit::Minutes, minutes);
+    }
+    true
+}
+
+/// Finishes the current round and ends the fight, returning the game time it took for the
+/// clock to catch up on. `None` if there's no fight going on.
+///
+/// Lights and effects that burn by the minute have already burnt round by round.
+pub fn end_combat(data: &mut ShadowtrackData) -> Option<u64> {
+    if !finish_round(data) {
+        return None;
+    }
+    let combat = data.combat.as_ref()?;
+    let message = format!(
+        "Combat ends after {} {}, {}",
+        combat.round,
+        if combat.round == 1 { "round" } else { "rounds" },
+        format_duration(combat.elapsed_secs)
+    );
+    let event = LogEvent::Combat {
+        round: combat.round,
+    };
+    // Logged before the fight is over, so it is stamped with the time the rounds took.
+    log_event(data, event, &message);
+    let combat = data.combat.take()?;
+    Some(combat.elapsed_secs)
+}
+
+/// Such as "1 min 12 sec" or "48 sec".
+fn format_duration(secs: u64) -> String {
+    match (secs / 60, secs % 60) {
+        (0, secs) => format!("{} sec", secs),
+        (minutes, 0) => format!("{} min", minutes),
+        (minutes, secs) => format!("{} min {} sec", minutes, secs),
+    }
+}
+
+/// Damages one of the last encounter's creatures, or heals it if `amount` is negative, then
+/// checks the group's morale if that killed its leader or brought half of it down. Returns
+/// `false` if there's no such creature.
+pub fn damage_monster(
+    data: &mut ShadowtrackData,
+    rng: &mut impl RandomSource,
+    group_index: usize,
+    index: usize,
+    amount: i64,
+) -> bool {
+    let automatic = data.morale_settings.automatic;
+    let Some(group) = data
+        .last_monsters
+        .get_mut(group_index)
+        .filter(|group| index < group.hp.len())
+    else {
+        return false;
+    };
+    let was_dead = group.is_dead(index);
+    group.take_damage(index, amount);
+    let name = group.creature_name(index);
+    let hp = format!("{}/{} HP", group.hp_left(index), group.hp[index]);
+    let message = if amount < 0 {
+        format!("{} heals {}, {}", name, -amount, hp)
+    } else if group.is_dead(index) && !was_dead {
+        format!("{} takes {} damage and falls", name, amount)
+    } else {
+        format!("{} takes {} damage, {}", name, amount, hp)
+    };
+
+    // Each reason for a check only comes up once per encounter.
+    let mut reason = None;
+    if automatic && !group.fled && group.fallen() < group.hp.len() {
+        if group.leader == Some(index) && group.is_dead(index) && !group.leader_checked {
+            group.leader_checked = true;
+            reason = Some("their leader has fallen");
+        }
+        if group.hp.len() > 1 && group.fallen() * 2 >= group.hp.len() && !group.half_fallen_checked
+        {
+            group.half_fallen_checked = true;
+            reason = reason.or(Some("half have fallen"));
+        }
+    }
+    let event = LogEvent::MonsterHp {
+        creature: name,
+        hp: group.hp_left(index),
+    };
+    log_event(data, event, &message);
+    if let Some(reason) = reason {
+        check_morale(data, rng, group_index, reason);
+    }
+    true
+}
+
+/// Rolls a morale check for one of the last encounter's groups and logs whether it holds or
+/// flees. Returns whether it held, or `None` if there's no such group.
+pub fn check_morale(
+    data: &mut ShadowtrackData,
+    rng: &mut impl RandomSource,
+    group_index: usize,
+    reason: &str,
+) -> Option<bool> {
+    let rule = data.morale_settings.rule;
+    let group = data.last_monsters.get_mut(group_index)?;
+    rng.set_purpose(format!("Morale: {}", group.monster.name).as_str());
+    let roll = rule.dice().roll(rng).total;
+    let holds = roll <= i64::from(group.monster.morale);
+    group.fled = !holds;
+    let message = format!(
+        "Morale check for {} ({}): {} on {} against {}, they {}",
+        group.monster.name,
+        reason,
+        roll,
+        rule,
+        group.monster.morale,
+        if holds { "hold" } else { "flee" }
+    );
+    let event = LogEvent::Morale {
+        monster: group.monster.name.clone(),
+        roll,
+        morale: group.monster.morale,
+        held: holds,
+    };
+    log_event(data, event, &message);
+    Some(holds)
+}
+
+/// Rolls a torch event from the event table
+pub fn roll_light_event(data: &mut ShadowtrackData, rng: &mut impl RandomSource) {
+    rng.set_purpose("Light event");
+    let Some(tree) = roll_table("Torch events", &data.torch_event_table, &data.tables, rng) else {
+        return;
+    };
+    match &tree.error {
+        Some(error) => log_event(
+            data,
+            LogEvent::Error,
+            format!("[Error] Torch event table: {}", error).as_str(),
+        ),
+        None => {
+            let text = tree.text.clone();
+            let event = LogEvent::LightEvent {
+                result: text.clone(),
+            };
+            log_event_with(data, event, &text, table_result_detail(tree))
+        }
+    }
+}
+
+/// Whether the danger level calls for an encounter check on the current turn.
+pub fn encounter_check_due(data: &ShadowtrackData) -> bool {
+    data.danger_level
+        .check()
+        .is_some_and(|check| data.turn.is_multiple_of(check.every_turns.max(1)))
+}
+
+/// Rolls for a random encounter using the danger level's odds, then rolls on the encounter
+/// table
+pub fn roll_encounter(
+    data: &mut ShadowtrackData,
+    rng: &mut impl RandomSource,
+    forced_encounter: bool,
+) {
+    rng.set_purpose("Encounter check");
+    let hit = forced_encounter
+        || data
+            .danger_level
+            .check()
+            .is_some_and(|check| rng.roll_range(1, check.die) <= check.chance);
+    if hit {
+        rng.set_purpose("Encounter table");
+        match roll_table("Encounters", &data.encounter_table, &data.tables, rng) {
+            Some(tree) => match &tree.error {
+                Some(error) => {
+                    let message = format!("[Error] Encounter table: {}", error);
+                    log_event(data, LogEvent::Error, &message)
+                }
+                None => {
+                    let (text, names) = strip_monster_references(&tree.text);
+                    let log_entry = format!("!ENCOUNTER! - {}", text);
+                    let event = LogEvent::Encounter {
+                        result: text.clone(),
+                    };
+                    data.last_encounter = Some(text);
+                    let mut detail = table_result_detail(tree);
+                    let mut encounter = if data.encounter_follow_ups.enabled {
+                        Some(roll_encounter_follow_ups(data, rng))
+                    } else {
+                        None
+                    };
+                    let monsters: Vec<MonsterGroup> = names
+                        .iter()
+                        .filter_map(|name| data.monster(name))
+                        .map(|monster| monster.roll_group(rng))
+                        .collect();
+                    if !monsters.is_empty() {
+                        encounter.get_or_insert_with(Default::default).monsters = monsters.clone();
+                    }
+                    // Fighters refer to creatures by index, so newcomers join mid-fight.
+                    if data.combat.is_some() {
+                        data.last_monsters.extend(monsters);
+                    } else {
+                        data.last_monsters = monsters;
+                    }
+                    if encounter.is_some() {
+                        detail.get_or_insert_with(Default::default).encounter = encounter;
+                    }
+                    log_event_with(data, event, log_entry.as_str(), detail);
+                }
+            },
+            None => log_event(data, LogEvent::Error, "[Error] Encounter table empty!"),
+        }
+    } else {
+        log_event(data, LogEvent::NoEncounter, "No encounter");
+    }
+}
+
+/// Rolls the reaction, distance, surprise and activity tables for an encounter that hit.
+fn roll_encounter_follow_ups(
+    data: &ShadowtrackData,
+    rng: &mut impl RandomSource,
+) -> EncounterDetail {
+    let follow_ups = &data.encounter_follow_ups;
+    let mut roll = |name: &str, table| {
+        rng.set_purpose(name);
+        roll_table(name, table, &data.tables, rng)
+    };
+    EncounterDetail {
+        reaction: roll("Reaction", &follow_ups.reaction),
+        distance: roll("Distance", &follow_ups.distance),
+        party_surprise: roll("Party surprise", &follow_ups.surprise),
+        monster_surprise: roll("Monster surprise", &follow_ups.surprise),
+        activity: roll("Activity", &follow_ups.activity),
+        monsters: vec![],
+    }
+}
+
+/// Rolls a dice expression and logs the result with every die shown.
+pub fn roll_dice(
+    data: &mut ShadowtrackData,
+    rng: &mut impl RandomSource,
+    expression: &DiceExpr,
+) -> DiceRoll {
+    rng.set_purpose(format!("Dice: {}", expression).as_str());
+    let roll = expression.roll(rng);
+    let event = LogEvent::DiceRoll {
+        expression: expression.to_string(),
+        total: roll.total,
+    };
+    log_event(data, event, format!("Rolled {}", roll).as_str());
+    roll
+}
+
+/// Moves the game clock by hand to `secs`, logging the change. No turn is processed.
+pub fn adjust_clock(data: &mut ShadowtrackData, secs: u64) {
+    let from_secs = data.clock_elapsed;
+    data.clock_elapsed = secs;
+    // Adjustments made one after another, like a few clicks of +1 min, are logged as one move.
+    let turn = data.turn;
+    let last = data
+        .event_log
+        .iter_mut()
+        .find(|entry| entry.turn == turn)
+        .and_then(|entry| entry.events.last_mut())
+        .filter(|last| last.edits.is_empty() && !last.deleted);
+    if let Some(last) = last {
+        if let LogEvent::ClockAdjust {
+            from_secs: first,
+            to_secs,
+        } = last.event
+        {
+            if to_secs == from_secs {
+                last.event = LogEvent::ClockAdjust {
+                    from_secs: first,
+                    to_secs: secs,
+                };
+                last.text = clock_adjust_text(first, secs);
+                return;
+            }
+        }
+    }
+    let event = LogEvent::ClockAdjust {
+        from_secs,
+        to_secs: secs,
+    };
+    log_event(data, event, &clock_adjust_text(from_secs, secs));
+}
+
+fn clock_adjust_text(from_secs: u64, to_secs: u64) -> String {
+    format!(
+        "Clock moved from {} to {}",
+        format_clock(from_secs),
+        format_clock(to_secs)
+    )
+}
+
+/// Tags the event at `index` in the given turn. Returns `false` if there's no such event or it
+/// already had the tag.
+pub fn tag_event(data: &mut ShadowtrackData, turn: u32, index: usize, tag: &str) -> bool {
+    data.logged_event_mut(turn, index)
+        .is_some_and(|event| event.add_tag(tag))
+}
+
+/// Takes a tag off the event at `index` in the given turn. Returns `false` if there's no such
+/// event or it didn't have the tag.
+pub fn untag_event(data: &mut ShadowtrackData, turn: u32, index: usize, tag: &str) -> bool {
+    data.logged_event_mut(turn, index)
+        .is_some_and(|event| event.remove_tag(tag))
+}
+
+/// Rewrites the text of the event at `index` in the given turn, keeping the old text with it.
+/// Returns `false` if there's no such event or the text is unchanged.
+pub fn edit_event(data: &mut ShadowtrackData, turn: u32, index: usize, text: &str) -> bool {
+    data.logged_event_mut(turn, index)
+        .is_some_and(|event| event.edit(text))
+}
+
+/// Strikes out the event at `index` in the given turn, or restores it if `deleted` is `false`.
+/// Returns `false` if there's no such event or it was already that way.
+pub fn strike_event(data: &mut ShadowtrackData, turn: u32, index: usize, deleted: bool) -> bool {
+    match data.logged_event_mut(turn, index) {
+        Some(event) if event.deleted != deleted => {
+            event.deleted = deleted;
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Moves the event at `from` in the given turn to `to`, shifting those in between. Returns
+/// `false` if either is out of range.
+pub fn move_event(data: &mut ShadowtrackData, turn: u32, from: usize, to: usize) -> bool {
+    let Some(entry) = data.event_log.iter_mut().find(|entry| entry.turn == turn) else {
+        return false;
+    };
+    if from >= entry.events.len() || to >= entry.events.len() {
+        return false;
+    }
+    let event = entry.events.remove(from);
+    entry.events.insert(to, event);
+    true
+}
+
+/// Writes a GM note into the given turn, at `index` or after its last event. Notes on the
+/// current turn are stamped with the game time now; notes on earlier turns with the time of
+/// the event before them.
+pub fn add_note(data: &mut ShadowtrackData, turn: u32, index: Option<usize>, text: &str) {
+    let now = data.clock_elapsed + data.combat.as_ref().map_or(0, |c| c.elapsed_secs);
+    let position = data.event_log.iter().position(|entry| entry.turn >= turn);
+    let entry = match position {
+        Some(position) if data.event_log[position].turn == turn => &mut data.event_log[position],
+        // Keep the log in turn order when noting a turn nothing was logged on.
+        _ => {
+            let position = position.unwrap_or(data.event_log.len());
+            data.event_log.insert(
+                position,
+                TurnEntry {
+                    turn,
+                    ..Default::default()
+                },
+            );
+            &mut data.event_log[position]
+        }
+    };
+    let index = index.map_or(entry.events.len(), |index| index.min(entry.events.len()));
+    let mut note = LoggedEvent::new(LogEvent::Note, text, now);
+    if turn != data.turn {
+        note.game_secs = index
+            .checked_sub(1)
+            .and_then(|before| entry.events[before].game_secs);
+    }
+    entry.events.insert(index, note);
+}
+
+/// Changes the danger level, logging the new encounter odds.
+pub fn set_danger_level(data: &mut ShadowtrackData, level: DangerLevel) {
+    if data.danger_level != level {
+        data.danger_level = level;
+        log_event(
+            data,
+            LogEvent::DangerChange { level },
+            format!("Danger level: {}", level).as_str(),
+        );
+    }
+}
@@ -0,0 +1,243 @@
+use crate::dice::{DiceExpr, DiceRoll};
+use crate::rng::RandomSource;
+use serde::{Deserialize, Serialize};
+
+/// Largest dice range that [`RollTable::problems`] checks total by total.
+const MAX_CHECKED_TOTALS: i64 = 10_000;
+
+/// One line of a roll table, selected when the table's dice total falls in `low..=high`.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct TableEntry {
+    pub text: String,
+    pub low: i64,
+    pub high: i64,
+}
+
+impl TableEntry {
+    /// How many totals select this entry. Zero if the range is empty.
+    pub fn weight(&self) -> u32 {
+        (self.high - self.low + 1).max(0) as u32
+    }
+
+    /// The range as written in a published table: `3` or `1-2`.
+    pub fn range_label(&self) -> String {
+        if self.low == self.high {
+            self.low.to_string()
+        } else {
+            format!("{}-{}", self.low, self.high)
+        }
+    }
+}
+
+/// A table rolled with its own dice, such as "1-2 Goblins, 3 Bats" on a d6 or a 2d6 reaction
+/// table.
+///
+/// Weighted tables keep their entries' ranges packed from 1 and roll `d(total weight)`, so
+/// entries can be given relative odds without working out ranges by hand.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(from = "RollTableRepr")]
+pub struct RollTable {
+    /// Name other tables use to reference this one, as `[[Name]]`.
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub name: String,
+    pub dice: DiceExpr,
+    pub weighted: bool,
+    pub entries: Vec<TableEntry>,
+}
+
+/// Result of rolling a [`RollTable`]. `entry` is `None` if no entry covers the total.
+#[derive(Clone, PartialEq, Debug)]
+pub struct TableRoll<'a> {
+    pub roll: DiceRoll,
+    pub entry: Option<&'a TableEntry>,
+}
+
+impl RollTable {
+    /// A weighted table where every entry is equally likely.
+    pub fn uniform<S: Into<String>>(entries: impl IntoIterator<Item = S>) -> Self {
+        Self::weighted(entries.into_iter().map(|text| (text, 1)))
+    }
+
+    pub fn weighted<S: Into<String>>(entries: impl IntoIterator<Item = (S, u32)>) -> Self {
+        let (texts, weights): (Vec<String>, Vec<u32>) = entries
+            .into_iter()
+            .map(|(text, weight)| (text.into(), weight))
+            .unzip();
+        let mut table = Self {
+            name: String::new(),
+            dice: DiceExpr::dice(1, 1),
+            weighted: true,
+            entries: texts
+                .into_iter()
+                .map(|text| TableEntry {
+                    text,
+                    low: 0,
+                    high: 0,
+                })
+                .collect(),
+        };
+        table.set_weights(&weights);
+        table
+    }
+
+    /// A table rolled with `dice`, entries matched by range.
+    pub fn ranged<S: Into<String>>(
+        dice: DiceExpr,
+        entries: impl IntoIterator<Item = (i64, i64, S)>,
+    ) -> Self {
+        Self {
+            name: String::new(),
+            dice,
+            weighted: false,
+            entries: entries
+                .into_iter()
+                .map(|(low, high, text)| TableEntry {
+                    text: text.into(),
+                    low,
+                    high,
+                })
+                .collect(),
+        }
+    }
+
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn contains(&self, text: &str) -> bool {
+        self.entries.iter().any(|entry| entry.text == text)
+    }
+
+    /// Adds an entry. Weighted tables give it a weight of one; ranged tables place it just
+    /// after the current highest entry.
+    pub fn push(&mut self, text: impl Into<String>) {
+        let next = self.entries.iter().map(|e| e.high).max().unwrap_or(0) + 1;
+        self.entries.push(TableEntry {
+            text: text.into(),
+            low: next,
+            high: next,
+        });
+        if self.weighted {
+            self.repack();
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        if self.weighted {
+            self.repack();
+        }
+    }
+
+    pub fn remove(&mut self, index: usize) {
+        self.entries.remove(index);
+        if self.weighted {
+            self.repack();
+        }
+    }
+
+    /// Reassigns weights in entry order and repacks the ranges on `d(total weight)`.
+    pub fn set_weights(&mut self, weights: &[u32]) {
+        let mut next = 1;
+        for (entry, weight) in self.entries.iter_mut().zip(weights) {
+            entry.low = next;
+            entry.high = next + *weight as i64 - 1;
+            next += *weight as i64;
+        }
+        self.dice = DiceExpr::dice(1, (next - 1).max(1) as u32);
+    }
+
+    /// Packs the ranges again after entries were added, removed or reweighted.
+    pub fn repack(&mut self) {
+        let weights: Vec<u32> = self.entries.iter().map(TableEntry::weight).collect();
+        self.set_weights(&weights);
+    }
+
+    pub fn lookup(&self, total: i64) -> Option<&TableEntry> {
+        self.entries
+            .iter()
+            .find(|entry| (entry.low..=entry.high).contains(&total))
+    }
+
+    /// Rolls the table's dice and looks up the entry. `None` if the table is empty.
+    pub fn roll(&self, rng: &mut impl RandomSource) -> Option<TableRoll<'_>> {
+        if self.entries.is_empty() {
+            return None;
+        }
+        let roll = self.dice.roll(rng);
+        Some(TableRoll {
+            entry: self.lookup(roll.total),
+            roll,
+        })
+    }
+
+    /// Describes totals the dice can roll that no entry covers, totals covered twice, and
+    /// entries the dice can never reach.
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        let (min, max) = self.dice.range();
+        if max - min < MAX_CHECKED_TOTALS {
+            let mut uncovered = Vec::new();
+            for total in min..=max {
+                match self
+                    .entries
+                    .iter()
+                    .filter(|entry| (entry.low..=entry.high).contains(&total))
+                    .count()
+                {
+                    0 => uncovered.push(total.to_string()),
+                    1 => {}
+                    _ => problems.push(format!("{} is covered by more than one entry", total)),
+                }
+            }
+            if !uncovered.is_empty() {
+                problems.push(format!("No entry for {}", uncovered.join(", ")));
+            }
+        }
+        for entry in &self.entries {
+            if entry.high < min || entry.low > max || entry.low > entry.high {
+                problems.push(format!("\"{}\" can never be rolled", entry.text));
+            }
+        }
+        problems
+    }
+}
+
+/// Saves from before roll tables stored plain string lists, rolled uniformly.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RollTableRepr {
+    Table {
+        #[serde(default)]
+        name: String,
+        dice: DiceExpr,
+        #[serde(default)]
+        weighted: bool,
+        entries: Vec<TableEntry>,
+    },
+    List(Vec<String>),
+}
+
+impl From<RollTableRepr> for RollTable {
+    fn from(repr: RollTableRepr) -> Self {
+        match repr {
+            RollTableRepr::Table {
+                name,
+                dice,
+                weighted,
+                entries,
+            } => Self {
+                name,
+                dice,
+                weighted,
+                entries,
+            },
+            RollTableRepr::List(entries) => Self::uniform(entries),
+        }
+    }
+}
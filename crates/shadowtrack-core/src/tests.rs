@@ -0,0 +1,1992 @@
+#[cfg(test)]
+mod systems {
+    use crate::dice::{DiceError, DiceExpr};
+    use crate::event_log::{format_clock, format_utc, LogEvent, LogFilter, LoggedEvent, TurnEntry};
+    use crate::recap::{Recap, RecapFormat};
+    use crate::resolve::{roll_table, MAX_DEPTH};
+    use crate::rng::{
+        verify_journal, DefaultRandomSource, RandomSource, RngState, SeededRandomSource,
+    };
+    use crate::save::autosave::Autosaver;
+    use crate::session::Session;
+    use crate::state::{
+        BurnModel, Character, DangerLevel, EffectTarget, EffectUnit, EncounterCheck, Illumination,
+        InitiativeMode, LightSource, LightSourceType, LightState, ShadowtrackData, Supplies,
+        Supply, TimedEffect,
+    };
+    use crate::systems::{
+        add_effect, add_light, count_down_effects, encounter_check_due, end_effect,
+        light_from_supply, process_light_burn, relight_light, remove_spent_lights,
+        replace_from_supply, roll_encounter, roll_light_event, snuff_light,
+    };
+    use crate::tables::RollTable;
+
+    #[test]
+    fn add_new_encounter_table_entry() {
+        let mut data = ShadowtrackData::default();
+        let new_entry = "Spectral hound".to_string();
+        data.encounter_table.push(new_entry.clone());
+        assert!(data.encounter_table.contains(&new_entry));
+    }
+
+    fn message(text: &str) -> LoggedEvent {
+        LoggedEvent::new(LogEvent::Message, text, 0)
+    }
+
+    #[test]
+    fn log_ordering() {
+        // TODO: This really just checks that Vec maintains insertion order, and it does.
+        // Currently I dont know how to properly setup a testing harness to test the egui widget
+        // displaying the log in reverse order. So for now this is just a sanity check.
+        let mut data = ShadowtrackData::default();
+        let turn_entry_0 = TurnEntry {
+            turn: 0,
+            events: vec![message("First"), message("Second"), message("Third")],
+        };
+        let turn_entry_1 = TurnEntry {
+            turn: 1,
+            events: vec![message("Forth"), message("Fifth"), message("Sixth")],
+        };
+        let turn_entry_2 = TurnEntry {
+            turn: 2,
+            events: vec![message("Seventh"), message("Eighth"), message("Ninth")],
+        };
+
+        data.event_log.push(turn_entry_0.clone());
+        data.event_log.push(turn_entry_1.clone());
+        data.event_log.push(turn_entry_2.clone());
+
+        assert!(data.event_log.contains(&turn_entry_0));
+        assert!(data.event_log.contains(&turn_entry_1));
+        assert!(data.event_log.contains(&turn_entry_2));
+
+        assert_eq!(data.event_log.len(), 3);
+
+        assert_eq!(data.event_log[0], turn_entry_0);
+        assert_eq!(data.event_log[1], turn_entry_1);
+        assert_eq!(data.event_log[2], turn_entry_2);
+
+        // "Third"
+        assert_eq!(data.event_log[0].events[2], turn_entry_0.events[2]);
+        // "Forth"
+        assert_eq!(data.event_log[1].events[0], turn_entry_1.events[0]);
+        // "Eighth"
+        assert_eq!(data.event_log[2].events[1], turn_entry_2.events[1]);
+    }
+
+    #[test]
+    fn light_source_adds_correctly() {
+        use crate::state::{LightSource, LightSourceType};
+        let source = LightSource {
+            label: "Lantern".into(),
+            light_type: LightSourceType::Lantern,
+            radius_feet: 30,
+            minutes_remaining: 60,
+            id: 0,
+            owner: None,
+            last_roll: None,
+            state: Default::default(),
+            burn: None,
+        };
+        assert_eq!(source.label, "Lantern");
+        assert_eq!(source.radius_feet, 30);
+    }
+
+    #[test]
+    fn add_new_torch_event_table_entry() {
+        let mut data = ShadowtrackData::default();
+        let new_entry = "Shadows attack!".to_string();
+        data.torch_event_table.push(new_entry.clone());
+        assert!(data.torch_event_table.contains(&new_entry));
+    }
+
+    #[test]
+    fn save_and_load_game() {
+        use crate::state::{LightSource, LightSourceType, ShadowtrackData};
+        use std::{env, fs};
+
+        let mock_light_source = LightSource {
+            label: "Mock Light Label".to_string(),
+            radius_feet: 10,
+            light_type: LightSourceType::Spell("Mock Testing Spell".to_string()),
+            minutes_remaining: 420,
+            id: 0,
+            owner: None,
+            last_roll: Some(69),
+            state: Default::default(),
+            burn: None,
+        };
+
+        let test_save_file = std::path::PathBuf::from(format!(
+            "{}/test_save.json",
+            env::temp_dir().to_str().unwrap()
+        ));
+        let data = ShadowtrackData {
+            clock_elapsed: 69420,
+            light_sources: vec![mock_light_source],
+            ..Default::default()
+        };
+
+        let _test_save = crate::save::write_save(&test_save_file, &data);
+
+        assert_eq!(crate::save::load_save(&test_save_file).unwrap(), data);
+
+        // clean up.
+        fs::remove_file(test_save_file).unwrap();
+    }
+
+    #[test]
+    fn rng_range_inclusive() {
+        let mut rng = DefaultRandomSource;
+        let mut rolls: Vec<u32> = Vec::new();
+        let min = 0;
+        let max = 5;
+
+        // Let's hope this is enough.
+        for _ in 1..100 {
+            rolls.push(rng.roll_range(min, max));
+        }
+
+        assert!(rolls.contains(&min));
+        assert!(rolls.contains(&max));
+    }
+
+    #[test]
+    fn rng_choose_empty() {
+        let mut rng = DefaultRandomSource;
+        let empty_set: Vec<String> = Vec::new();
+        let choice: Option<&String> = rng.choose(&empty_set);
+
+        assert!(choice.is_none());
+    }
+
+    #[test]
+    fn rng_choose_inclusive() {
+        let mut rng = DefaultRandomSource;
+        let mut choices: Vec<String> = Vec::new();
+        let choice_set: Vec<String> = vec!["first".into(), "second".into(), "third".into()];
+
+        for _ in 0..100 {
+            choices.push(
+                rng.choose(&choice_set)
+                    .expect("Something Broke Here")
+                    .to_string(),
+            );
+        }
+
+        assert!(!choices.is_empty());
+        assert!(choices.contains(&choice_set[0]));
+        assert!(choices.contains(&choice_set[1]));
+        assert!(choices.contains(&choice_set[2]));
+        assert!(!choices.contains(&"fifth".to_string()));
+    }
+
+    // A mock RNG source to control test results
+    struct MockRng {
+        roll_values: Vec<u32>,
+        choose_indices: Vec<usize>,
+        roll_index: usize,
+        choose_index: usize,
+    }
+
+    impl MockRng {
+        fn new(roll_values: Vec<u32>, choose_indices: Vec<usize>) -> Self {
+            Self {
+                roll_values,
+                choose_indices,
+                roll_index: 0,
+                choose_index: 0,
+            }
+        }
+    }
+
+    impl RandomSource for MockRng {
+        fn roll_range(&mut self, _min: u32, _max: u32) -> u32 {
+            let val = self.roll_values[self.roll_index];
+            self.roll_index = (self.roll_index + 1) % self.roll_values.len();
+            val
+        }
+
+        fn choose<'a, T>(&mut self, list: &'a [T]) -> Option<&'a T> {
+            if list.is_empty() {
+                return None;
+            }
+            let idx = self.choose_indices[self.choose_index] % list.len();
+            self.choose_index = (self.choose_index + 1) % self.choose_indices.len();
+            list.get(idx)
+        }
+    }
+
+    #[test]
+    fn test_roll_encounter_with_miss() {
+        let mut data = ShadowtrackData::default();
+        data.encounter_table.push("Goblin Screamer".to_string());
+
+        let mut rng = MockRng::new(vec![4], vec![0]); // force roll != 1
+        roll_encounter(&mut data, &mut rng, false);
+
+        assert_eq!(data.event_log.len(), 1);
+        assert_eq!(data.event_log[0].events.len(), 1);
+        assert!(data.event_log[0].texts().contains(&"No encounter"));
+    }
+
+    #[test]
+    fn test_roll_encounter_with_hit() {
+        let mut data = ShadowtrackData::default();
+        data.encounter_table.clear();
+        data.encounter_table.push("Skeleton Ambush".to_string());
+
+        let mut rng = MockRng::new(vec![1], vec![0]); // force 1 on d6, select 0th entry
+        roll_encounter(&mut data, &mut rng, false);
+
+        assert_eq!(data.event_log.len(), 1);
+        assert_eq!(data.event_log[0].events.len(), 1);
+        assert!(data.event_log[0]
+            .texts()
+            .contains(&"!ENCOUNTER! - Skeleton Ambush"));
+    }
+
+    #[test]
+    fn test_process_torch_burn() {
+        let mut data = ShadowtrackData::default();
+        data.light_sources.push(crate::state::LightSource {
+            label: "".to_string(),
+            light_type: Default::default(),
+            radius_feet: 0,
+            minutes_remaining: 30,
+            id: 0,
+            owner: None,
+            last_roll: None,
+            state: Default::default(),
+            burn: None,
+        });
+
+        let mut rng = MockRng::new(vec![2], vec![]);
+        process_light_burn(&mut data, &mut rng);
+        let torch = &data.light_sources[0];
+
+        assert_eq!(torch.minutes_remaining, 10);
+        assert_eq!(torch.last_roll, Some(2));
+    }
+
+    #[test]
+    fn test_roll_torch_event() {
+        let mut data = ShadowtrackData::default();
+        data.torch_event_table.clear();
+        data.torch_event_table
+            .push("Torch sputters ominously.".to_string());
+
+        let mut rng = MockRng::new(vec![1], vec![]);
+        roll_light_event(&mut data, &mut rng);
+
+        assert_eq!(data.event_log.len(), 1);
+        assert_eq!(data.event_log[0].events.len(), 1);
+        assert!(data.event_log[0]
+            .texts()
+            .contains(&"Torch sputters ominously."));
+    }
+
+    #[test]
+    fn session_tick_ignored_while_clock_stopped() {
+        let mut session = Session::new(ShadowtrackData::default(), MockRng::new(vec![4], vec![0]));
+
+        assert!(!session.tick(600));
+        assert_eq!(session.data.clock_elapsed, 0);
+        assert!(session.data.next_process_minutes.is_none());
+    }
+
+    #[test]
+    fn session_processes_turn_at_interval() {
+        let mut session = Session::new(ShadowtrackData::default(), MockRng::new(vec![4], vec![0]));
+        session.toggle_clock();
+
+        // First tick arms the process timer.
+        assert!(!session.tick(60));
+        assert_eq!(session.data.next_process_minutes, Some(10));
+
+        assert!(session.tick(540));
+        assert_eq!(session.data.turn, 1);
+        assert_eq!(session.data.next_process_minutes, Some(20));
+        assert!(session.data.event_log[0].texts().contains(&"No encounter"));
+    }
+
+    #[test]
+    fn session_pass_minutes_and_turns() {
+        let mut session = Session::new(ShadowtrackData::default(), MockRng::new(vec![4], vec![0]));
+
+        // The first minute arms the timer, so turn one lands on minute ten.
+        assert_eq!(session.pass_minutes(25), 2);
+        assert_eq!(session.data.turn, 2);
+        assert_eq!(session.data.clock_elapsed, 25 * 60);
+
+        session.pass_turns(3);
+        assert_eq!(session.data.turn, 5);
+        assert_eq!(session.data.clock_elapsed, 50 * 60);
+    }
+
+    #[test]
+    fn dice_parse_round_trips() {
+        for expression in [
+            "2d6+1", "d20", "4d6kh3", "2d20kl1", "d20adv", "d20dis", "3d6!", "d8-2",
+        ] {
+            assert_eq!(DiceExpr::parse(expression).unwrap().to_string(), expression);
+        }
+        assert_eq!(DiceExpr::parse(" 1D% ").unwrap().to_string(), "d100");
+        assert_eq!(DiceExpr::parse("4d6k3").unwrap().to_string(), "4d6kh3");
+        assert_eq!(DiceExpr::parse("2d6+1").unwrap().range(), (3, 13));
+        assert_eq!(DiceExpr::parse("d8-2").unwrap().range(), (-1, 6));
+    }
+
+    #[test]
+    fn dice_parse_errors() {
+        assert_eq!(DiceExpr::parse(""), Err(DiceError::Empty));
+        assert_eq!(DiceExpr::parse("2d"), Err(DiceError::UnexpectedEnd));
+        assert_eq!(DiceExpr::parse("d0"), Err(DiceError::ZeroSides));
+        assert_eq!(DiceExpr::parse("d1!"), Err(DiceError::ExplodingD1));
+        assert_eq!(
+            DiceExpr::parse("2x6"),
+            Err(DiceError::UnexpectedChar('x', 1))
+        );
+        assert_eq!(
+            DiceExpr::parse("4d6kh5"),
+            Err(DiceError::KeepTooMany { keep: 5, count: 4 })
+        );
+        assert_eq!(DiceExpr::parse("5000d6"), Err(DiceError::TooManyDice(5000)));
+    }
+
+    #[test]
+    fn dice_roll_with_modifier() {
+        let mut rng = MockRng::new(vec![3, 5], vec![]);
+        let roll = DiceExpr::parse("2d6+1").unwrap().roll(&mut rng);
+
+        assert_eq!(roll.total, 9);
+        assert_eq!(roll.to_string(), "2d6+1: [3, 5] + 1 = 9");
+    }
+
+    #[test]
+    fn dice_roll_keep_highest_and_lowest() {
+        let mut rng = MockRng::new(vec![5, 4, 1, 3], vec![]);
+        let roll = DiceExpr::parse("4d6kh3").unwrap().roll(&mut rng);
+        assert_eq!(roll.total, 12);
+        assert_eq!(roll.to_string(), "4d6kh3: [5, 4, (1), 3] = 12");
+
+        let mut rng = MockRng::new(vec![5, 4, 1, 3], vec![]);
+        let roll = DiceExpr::parse("4d6kl2").unwrap().roll(&mut rng);
+        assert_eq!(roll.total, 4);
+    }
+
+    #[test]
+    fn dice_roll_advantage_and_disadvantage() {
+        let mut rng = MockRng::new(vec![7, 15], vec![]);
+        let roll = DiceExpr::parse("d20adv").unwrap().roll(&mut rng);
+        assert_eq!(roll.total, 15);
+        assert_eq!(roll.to_string(), "d20adv: [(7)] [15] = 15");
+
+        let mut rng = MockRng::new(vec![7, 15], vec![]);
+        let roll = DiceExpr::parse("d20dis").unwrap().roll(&mut rng);
+        assert_eq!(roll.total, 7);
+    }
+
+    #[test]
+    fn dice_roll_exploding() {
+        let mut rng = MockRng::new(vec![6, 6, 2, 3], vec![]);
+        let roll = DiceExpr::parse("2d6!").unwrap().roll(&mut rng);
+
+        assert_eq!(roll.total, 17);
+        assert_eq!(roll.to_string(), "2d6!: [6, 6!, 2!, 3] = 17");
+    }
+
+    #[test]
+    fn dice_roll_stays_in_range() {
+        let expression = DiceExpr::parse("3d6+2").unwrap();
+        let mut rng = DefaultRandomSource;
+        for _ in 0..100 {
+            let total = expression.roll(&mut rng).total;
+            assert!((5..=20).contains(&total));
+        }
+    }
+
+    #[test]
+    fn session_logs_dice_rolls() {
+        let mut session = Session::new(ShadowtrackData::default(), MockRng::new(vec![4], vec![]));
+
+        assert_eq!(session.roll_dice("d6+1").unwrap().total, 5);
+        assert!(session.roll_dice("d").is_err());
+        assert_eq!(
+            session.data.event_log[0].texts(),
+            vec!["Rolled d6+1: [4] + 1 = 5".to_string()]
+        );
+    }
+
+    #[test]
+    fn seeded_rng_is_reproducible() {
+        let mut first = SeededRandomSource::new(42);
+        let mut second = SeededRandomSource::new(42);
+        let rolls: Vec<u32> = (0..50).map(|_| first.roll_range(1, 6)).collect();
+
+        assert!(rolls.iter().all(|roll| (1..=6).contains(roll)));
+        assert_eq!(
+            rolls,
+            (0..50).map(|_| second.roll_range(1, 6)).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            first.state(),
+            Some(RngState {
+                seed: 42,
+                draws: 50
+            })
+        );
+    }
+
+    #[test]
+    fn seeded_rng_resumes_mid_stream() {
+        let mut original = SeededRandomSource::new(7);
+        for _ in 0..13 {
+            original.roll_range(1, 20);
+        }
+        let mut resumed = SeededRandomSource::resume(&original.state().unwrap());
+
+        for _ in 0..20 {
+            assert_eq!(original.roll_range(1, 100), resumed.roll_range(1, 100));
+        }
+    }
+
+    #[test]
+    fn reloaded_session_rolls_the_same_future() {
+        let mut session = Session::default();
+        session.pass_turns(3);
+
+        let saved = session.data.clone();
+        session.pass_turns(3);
+        let mut reloaded = Session::from_data(saved);
+        reloaded.pass_turns(3);
+
+        assert_eq!(reloaded.data.event_log, session.data.event_log);
+        assert_eq!(reloaded.data.roll_journal, session.data.roll_journal);
+    }
+
+    #[test]
+    fn session_journals_rolls_by_turn_and_purpose() {
+        let mut session = Session::default();
+        session.data.light_sources.push(LightSource {
+            label: "Ginny".to_string(),
+            minutes_remaining: 60,
+            ..Default::default()
+        });
+        session.pass_turns(1);
+        session.roll_dice("2d6").unwrap();
+
+        let purposes: Vec<&str> = session
+            .data
+            .roll_journal
+            .iter()
+            .map(|record| record.purpose.as_str())
+            .collect();
+        assert_eq!(
+            &purposes[..3],
+            ["Light burn: Ginny", "Light event", "Encounter check"]
+        );
+        assert_eq!(purposes.last(), Some(&"Dice: 2d6"));
+        assert!(session
+            .data
+            .roll_journal
+            .iter()
+            .all(|record| record.turn == 1));
+        assert_eq!(
+            session.data.rng.draws,
+            session.data.roll_journal.len() as u64
+        );
+        assert!(verify_journal(session.data.rng.seed, &session.data.roll_journal).is_none());
+
+        let mut tampered = session.data.roll_journal.clone();
+        tampered[0].result = if tampered[0].result == 6 { 5 } else { 6 };
+        assert_eq!(
+            verify_journal(session.data.rng.seed, &tampered).map(|record| record.draw),
+            Some(0)
+        );
+    }
+
+    #[test]
+    fn old_saves_without_rng_state_load() {
+        let data: ShadowtrackData =
+            serde_json::from_str(include_str!("../../../example_save.json")).unwrap();
+
+        assert_eq!(data.turn, 3);
+        assert!(data.roll_journal.is_empty());
+        assert_eq!(data.rng.draws, 0);
+    }
+
+    #[test]
+    fn ranged_table_rolls_by_range() {
+        let table = RollTable::ranged(
+            DiceExpr::parse("d6").unwrap(),
+            [(1, 2, "Goblins"), (3, 5, "Bats"), (6, 6, "Mimic")],
+        );
+        assert!(table.problems().is_empty());
+        assert_eq!(table.entries[0].range_label(), "1-2");
+        assert_eq!(table.entries[2].range_label(), "6");
+
+        let mut rng = MockRng::new(vec![2, 3, 6], vec![]);
+        let rolled: Vec<&str> = (0..3)
+            .map(|_| table.roll(&mut rng).unwrap().entry.unwrap().text.as_str())
+            .collect();
+        assert_eq!(rolled, ["Goblins", "Bats", "Mimic"]);
+    }
+
+    #[test]
+    fn ranged_table_reports_problems() {
+        let table = RollTable::ranged(
+            DiceExpr::parse("2d6").unwrap(),
+            [(2, 6, "Hostile"), (6, 8, "Uncertain"), (13, 14, "Helpful")],
+        );
+
+        assert_eq!(
+            table.problems(),
+            vec![
+                "6 is covered by more than one entry".to_string(),
+                "No entry for 9, 10, 11, 12".to_string(),
+                "\"Helpful\" can never be rolled".to_string(),
+            ]
+        );
+
+        let mut rng = MockRng::new(vec![5, 5], vec![]);
+        let rolled = table.roll(&mut rng).unwrap();
+        assert_eq!(rolled.roll.total, 10);
+        assert!(rolled.entry.is_none());
+    }
+
+    #[test]
+    fn weighted_table_packs_ranges() {
+        let mut table = RollTable::weighted([("Rats", 3), ("Ghoul", 1)]);
+        assert_eq!(table.dice.to_string(), "d4");
+        assert_eq!(table.lookup(3).unwrap().text, "Rats");
+        assert_eq!(table.lookup(4).unwrap().text, "Ghoul");
+
+        table.push("Wight");
+        assert_eq!(table.dice.to_string(), "d5");
+        assert_eq!(table.entries[2].range_label(), "5");
+
+        table.remove(0);
+        assert_eq!(table.dice.to_string(), "d2");
+        assert_eq!(table.entries[0].range_label(), "1");
+        assert!(table.problems().is_empty());
+    }
+
+    #[test]
+    fn roll_tables_load_from_string_lists() {
+        let data: ShadowtrackData =
+            serde_json::from_str(include_str!("../../../example_save.json")).unwrap();
+        assert_eq!(data.encounter_table.dice.to_string(), "d7");
+        assert_eq!(data.encounter_table.lookup(7).unwrap().text, "Sinkhole");
+
+        let table = RollTable::ranged(DiceExpr::parse("2d6+1").unwrap(), [(3, 13, "Anything")]);
+        let json = serde_json::to_string(&table).unwrap();
+        assert!(json.contains("\"dice\":\"2d6+1\""));
+        assert_eq!(serde_json::from_str::<RollTable>(&json).unwrap(), table);
+    }
+
+    #[test]
+    fn nested_tables_resolve_recursively() {
+        let library = vec![
+            RollTable::uniform(["[[Goblin Leader]] and 2d4 goblins"]).with_name("Goblin Warband"),
+            RollTable::uniform(["a hobgoblin", "a goblin boss"]).with_name("Goblin Leader"),
+        ];
+        let table = RollTable::uniform(["[[Goblin Warband]] led by 1d4 [[goblin leader]]"]);
+
+        // Encounter d1, warband d1, leader d2 -> 2, 2d4 -> 3 + 4, 1d4 -> 2, leader d2 -> 1
+        let mut rng = MockRng::new(vec![1, 1, 2, 3, 4, 2, 1], vec![]);
+        let tree = roll_table("Encounters", &table, &library, &mut rng).unwrap();
+
+        assert_eq!(
+            tree.text,
+            "a goblin boss and 7 goblins led by 2 a hobgoblin"
+        );
+        assert_eq!(tree.children.len(), 3);
+        assert_eq!(tree.children[0].source, "Goblin Warband");
+        assert_eq!(tree.children[0].children[0].source, "Goblin Leader");
+        assert_eq!(tree.children[0].children[1].source, "2d4");
+        assert_eq!(tree.children[1].source, "1d4");
+        assert_eq!(
+            tree.to_string().lines().nth(1),
+            Some("  Goblin Warband (1): a goblin boss and 7 goblins")
+        );
+    }
+
+    #[test]
+    fn table_references_stop_at_cycles_and_unknown_names() {
+        let library = vec![
+            RollTable::uniform(["Ping then [[Pong]]"]).with_name("Ping"),
+            RollTable::uniform(["Pong then [[Ping]]"]).with_name("Pong"),
+        ];
+        let table = RollTable::uniform(["[[Ping]] and [[Nowhere]] with [[d6]]"]);
+
+        let mut rng = MockRng::new(vec![1, 1, 1, 5], vec![]);
+        let tree = roll_table("Encounters", &table, &library, &mut rng).unwrap();
+
+        assert_eq!(
+            tree.text,
+            "Ping then Pong then [[Ping]] and [[Nowhere]] with 5"
+        );
+        let cycle = &tree.children[0].children[0].children[0];
+        assert_eq!(cycle.error.as_deref(), Some("Cycle: Ping -> Pong -> Ping"));
+        assert_eq!(
+            tree.children[1].error.as_deref(),
+            Some("Unknown table \"Nowhere\"")
+        );
+
+        // A library table sharing the root's name is rolled, not taken for a cycle.
+        let library = vec![RollTable::uniform(["Kobolds"]).with_name("Encounters")];
+        let table = RollTable::uniform(["[[Encounters]] in the dark"]);
+        let tree = roll_table("Encounters", &table, &library, &mut rng).unwrap();
+        assert_eq!(tree.text, "Kobolds in the dark");
+    }
+
+    #[test]
+    fn table_references_stop_at_depth_limit() {
+        let library: Vec<RollTable> = (0..MAX_DEPTH + 2)
+            .map(|n| {
+                RollTable::uniform([format!("{n} [[Level {}]]", n + 1)])
+                    .with_name(format!("Level {n}"))
+            })
+            .collect();
+        let table = RollTable::uniform(["[[Level 0]]"]);
+
+        let mut rng = MockRng::new(vec![1], vec![]);
+        let tree = roll_table("Start", &table, &library, &mut rng).unwrap();
+
+        assert!(tree
+            .text
+            .ends_with(&format!("{} [[Level {}]]", MAX_DEPTH - 1, MAX_DEPTH)));
+    }
+
+    #[test]
+    fn bare_dice_only_roll_as_whole_words() {
+        let table = RollTable::uniform(["1d6+1 bats, not d6s or 3d6x, by a d20 and a d6"]);
+
+        let mut rng = MockRng::new(vec![1, 4], vec![]);
+        let tree = roll_table("Encounters", &table, &[], &mut rng).unwrap();
+
+        assert_eq!(tree.text, "5 bats, not d6s or 3d6x, by a d20 and a d6");
+        assert_eq!(tree.children.len(), 1);
+    }
+
+    #[test]
+    fn encounters_log_expanded_text_and_roll_tree() {
+        let mut data = ShadowtrackData {
+            encounter_table: RollTable::uniform(["2d6 [[Rats]]"]),
+            tables: vec![RollTable::uniform(["giant rats"]).with_name("Rats")],
+            ..Default::default()
+        };
+
+        let mut rng = MockRng::new(vec![1, 1, 3, 4, 1], vec![]);
+        roll_encounter(&mut data, &mut rng, false);
+
+        let entry = &data.event_log[0];
+        assert_eq!(
+            entry.texts(),
+            vec!["!ENCOUNTER! - 7 giant rats".to_string()]
+        );
+        let tree = entry.events[0]
+            .detail
+            .as_ref()
+            .unwrap()
+            .roll_tree
+            .as_ref()
+            .unwrap();
+        assert_eq!(tree.children.len(), 2);
+
+        // Plain results don't carry a tree.
+        roll_light_event(&mut data, &mut rng);
+        assert_eq!(data.event_log[0].events.len(), 2);
+        assert!(data.event_log[0].events[1].detail.is_none());
+    }
+
+    #[test]
+    fn danger_level_sets_check_cadence() {
+        let data = ShadowtrackData {
+            danger_level: DangerLevel::Unsafe,
+            ..Default::default()
+        };
+        let mut session = Session::new(data, MockRng::new(vec![4], vec![0]));
+        session.pass_turns(6);
+
+        let checked: Vec<u32> = session
+            .data
+            .event_log
+            .iter()
+            .filter(|entry| entry.texts().contains(&"No encounter"))
+            .map(|entry| entry.turn)
+            .collect();
+        assert_eq!(checked, vec![3, 6]);
+    }
+
+    #[test]
+    fn safe_level_never_rolls_encounters() {
+        let mut data = ShadowtrackData {
+            danger_level: DangerLevel::Safe,
+            ..Default::default()
+        };
+        assert!(!encounter_check_due(&data));
+
+        let mut rng = MockRng::new(vec![1], vec![0]);
+        roll_encounter(&mut data, &mut rng, false);
+        assert_eq!(data.event_log[0].texts(), vec!["No encounter".to_string()]);
+
+        // A forced encounter still happens.
+        roll_encounter(&mut data, &mut rng, true);
+        assert!(data.event_log[0].events[1].text.starts_with("!ENCOUNTER!"));
+    }
+
+    #[test]
+    fn custom_danger_level_uses_its_odds() {
+        let mut data = ShadowtrackData {
+            encounter_table: RollTable::uniform(["Cultists"]),
+            danger_level: DangerLevel::Custom(EncounterCheck {
+                every_turns: 1,
+                chance: 2,
+                die: 6,
+            }),
+            ..Default::default()
+        };
+
+        roll_encounter(&mut data, &mut MockRng::new(vec![2, 1], vec![0]), false);
+        roll_encounter(&mut data, &mut MockRng::new(vec![3], vec![0]), false);
+        assert_eq!(
+            data.event_log[0].texts(),
+            vec![
+                "!ENCOUNTER! - Cultists".to_string(),
+                "No encounter".to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn danger_level_changes_are_logged() {
+        let mut session = Session::new(ShadowtrackData::default(), MockRng::new(vec![4], vec![0]));
+        session.set_danger_level(DangerLevel::Risky);
+        session.set_danger_level(DangerLevel::Risky);
+
+        assert_eq!(
+            session.data.event_log[0].texts(),
+            vec!["Danger level: Risky (1 in 6 every 2 turns)".to_string()]
+        );
+    }
+
+    #[test]
+    fn encounter_hits_roll_follow_ups() {
+        let mut data = ShadowtrackData {
+            encounter_table: RollTable::uniform(["Kobolds"]),
+            ..Default::default()
+        };
+
+        // Encounter check, encounter table, reaction 2d6, distance, surprise twice, activity 2d6.
+        let mut rng = MockRng::new(vec![1, 1, 3, 4, 3, 2, 5, 6, 6], vec![]);
+        roll_encounter(&mut data, &mut rng, false);
+
+        let entry = &data.event_log[0];
+        assert_eq!(entry.texts(), vec!["!ENCOUNTER! - Kobolds".to_string()]);
+        let detail = entry.events[0].detail.as_ref().unwrap();
+        assert!(detail.roll_tree.is_none());
+        let encounter = detail.encounter.as_ref().unwrap();
+        assert_eq!(encounter.reaction.as_ref().unwrap().text, "Suspicious");
+        assert_eq!(encounter.distance.as_ref().unwrap().text, "Near");
+        assert_eq!(encounter.party_surprise.as_ref().unwrap().text, "Surprised");
+        assert_eq!(
+            encounter.monster_surprise.as_ref().unwrap().text,
+            "Not surprised"
+        );
+        assert_eq!(encounter.activity.as_ref().unwrap().text, "Sleeping");
+        assert_eq!(
+            encounter.to_string().lines().next(),
+            Some("Reaction: Suspicious (7)")
+        );
+    }
+
+    #[test]
+    fn encounter_follow_ups_can_be_skipped() {
+        let mut data = ShadowtrackData {
+            encounter_table: RollTable::uniform(["Kobolds"]),
+            ..Default::default()
+        };
+        data.encounter_follow_ups.distance.clear();
+        data.encounter_follow_ups.surprise.clear();
+
+        let mut rng = MockRng::new(vec![1], vec![]);
+        roll_encounter(&mut data, &mut rng, true);
+        let encounter = data.event_log[0].events[0]
+            .detail
+            .as_ref()
+            .unwrap()
+            .encounter
+            .as_ref()
+            .unwrap();
+        let labels: Vec<&str> = encounter.fields().iter().map(|(label, _)| *label).collect();
+        assert_eq!(labels, vec!["Reaction", "Activity"]);
+
+        data.encounter_follow_ups.enabled = false;
+        roll_encounter(&mut data, &mut rng, true);
+        assert!(data.event_log[0].events[1].detail.is_none());
+
+        // Nothing is logged for an encounter that missed.
+        roll_encounter(&mut data, &mut MockRng::new(vec![6], vec![]), false);
+        assert!(data.event_log[0].events[2].detail.is_none());
+    }
+
+    #[test]
+    fn encounters_roll_monsters_from_the_bestiary() {
+        let mut data = ShadowtrackData {
+            encounter_table: RollTable::uniform(["{{goblin}} scouts chasing a {{Wolf}}"]),
+            ..Default::default()
+        };
+        data.encounter_follow_ups.enabled = false;
+
+        // Encounter table, 2d4 goblins appearing, then 1d6 hit points for each of the three.
+        let mut rng = MockRng::new(vec![1, 1, 2, 4, 2, 5], vec![]);
+        roll_encounter(&mut data, &mut rng, true);
+
+        let entry = &data.event_log[0];
+        assert_eq!(
+            entry.texts(),
+            vec!["!ENCOUNTER! - goblin scouts chasing a Wolf".to_string()]
+        );
+        // Wolf isn't in the bestiary, so only the goblins are rolled.
+        let encounter = entry.events[0]
+            .detail
+            .as_ref()
+            .unwrap()
+            .encounter
+            .as_ref()
+            .unwrap();
+        assert!(encounter.fields().is_empty());
+        assert_eq!(encounter.monsters.len(), 1);
+        assert_eq!(encounter.monsters[0].hp, vec![4, 2, 5]);
+        assert_eq!(
+            encounter.to_string(),
+            "3 × Goblin (HP 4, 2, 5): AC 11, 1 club +0 (1d4), MOR 7\n"
+        );
+        assert_eq!(data.last_monsters, encounter.monsters);
+        assert_eq!(
+            data.last_encounter_foes(),
+            vec!["Goblin 1", "Goblin 2", "Goblin 3"]
+        );
+
+        // An encounter without references fights the encounter itself.
+        data.encounter_table = RollTable::uniform(["Kobolds"]);
+        roll_encounter(&mut data, &mut rng, true);
+        assert!(data.last_monsters.is_empty());
+        assert_eq!(data.last_encounter_foes(), vec!["Kobolds"]);
+    }
+
+    #[test]
+    fn bestiaries_round_trip_and_merge_by_name() {
+        use crate::bestiary::Monster;
+        use crate::save::{load_bestiary, write_bestiary};
+        use std::{env, fs};
+
+        let bestiary_file = env::temp_dir().join("test_bestiary.json");
+        let monsters = vec![
+            Monster {
+                ac: 12,
+                ..Monster::new("goblin")
+            },
+            Monster {
+                hp: DiceExpr::parse("3d8+3").unwrap(),
+                attacks: "1 bite +3 (1d8)".to_string(),
+                number_appearing: DiceExpr::dice(2, 6),
+                ..Monster::new("Wolf")
+            },
+        ];
+        write_bestiary(&bestiary_file, &monsters).unwrap();
+        let loaded = load_bestiary(&bestiary_file).unwrap();
+        fs::remove_file(bestiary_file).unwrap();
+        assert_eq!(loaded, monsters);
+
+        let mut data = ShadowtrackData::default();
+        let known = data.bestiary.len();
+        data.import_monsters(loaded);
+        assert_eq!(data.bestiary.len(), known + 1);
+        assert_eq!(data.monster("Goblin").unwrap().ac, 12);
+        assert_eq!(
+            data.monster("WOLF").unwrap().to_string(),
+            "AC 10, HP 3d8+3, 1 bite +3 (1d8), MOR 7"
+        );
+    }
+
+    fn light(light_type: LightSourceType, minutes_remaining: u32) -> LightSource {
+        LightSource {
+            label: light_type.to_string(),
+            light_type,
+            radius_feet: 30,
+            minutes_remaining,
+            id: 0,
+            owner: None,
+            last_roll: None,
+            state: LightState::Lit,
+            burn: None,
+        }
+    }
+
+    #[test]
+    fn lanterns_and_spells_never_double_burn() {
+        let mut data = ShadowtrackData::default();
+        data.light_sources.push(light(LightSourceType::Lantern, 60));
+        data.light_sources
+            .push(light(LightSourceType::Spell("Light".to_string()), 60));
+
+        let mut rng = MockRng::new(vec![1], vec![]);
+        process_light_burn(&mut data, &mut rng);
+
+        assert_eq!(data.light_sources[0].minutes_remaining, 50);
+        assert_eq!(data.light_sources[1].minutes_remaining, 50);
+        assert_eq!(data.light_sources[0].last_roll, None);
+        assert_eq!(rng.roll_index, 0);
+    }
+
+    #[test]
+    fn chance_burn_uses_configured_dice() {
+        let mut data = ShadowtrackData::default();
+        data.burn_models.torch = BurnModel::Chance {
+            minutes: 5,
+            dice: DiceExpr::parse("2d6").unwrap(),
+            chance: 4,
+            extra_minutes: 20,
+        };
+        data.light_sources.push(light(LightSourceType::Torch, 60));
+
+        process_light_burn(&mut data, &mut MockRng::new(vec![3, 2], vec![]));
+        assert_eq!(data.light_sources[0].minutes_remaining, 55);
+        assert_eq!(data.light_sources[0].last_roll, Some(5));
+
+        process_light_burn(&mut data, &mut MockRng::new(vec![1, 2], vec![]));
+        assert_eq!(data.light_sources[0].minutes_remaining, 30);
+    }
+
+    #[test]
+    fn real_time_lights_burn_with_the_clock() {
+        let mut data = ShadowtrackData::default();
+        data.burn_models.torch = BurnModel::RealTime;
+        data.burn_models.spell = BurnModel::Concentration;
+        data.burn_models.lantern = BurnModel::Never;
+        data.light_sources.push(light(LightSourceType::Torch, 60));
+        data.light_sources
+            .push(light(LightSourceType::Spell("Light".to_string()), 60));
+        data.light_sources.push(light(LightSourceType::Lantern, 60));
+        let mut session = Session::new(data, MockRng::new(vec![4], vec![0]));
+
+        session.pass_time(90);
+        assert_eq!(session.data.light_sources[0].minutes_remaining, 59);
+        session.pass_minutes(14);
+        assert_eq!(session.data.turn, 1);
+
+        let remaining: Vec<u32> = session
+            .data
+            .light_sources
+            .iter()
+            .map(|light| light.minutes_remaining)
+            .collect();
+        assert_eq!(remaining, vec![45, 60, 60]);
+    }
+
+    #[test]
+    fn lights_gutter_then_burn_out() {
+        let mut data = ShadowtrackData::default();
+        data.light_sources.push(LightSource {
+            label: "Ginny".to_string(),
+            ..light(LightSourceType::Lantern, 20)
+        });
+        let mut rng = MockRng::new(vec![6], vec![]);
+
+        process_light_burn(&mut data, &mut rng);
+        assert_eq!(data.light_sources[0].state, LightState::Guttering);
+        process_light_burn(&mut data, &mut rng);
+        assert_eq!(data.light_sources[0].state, LightState::BurntOut);
+        // Burnt out lights stay put until removed.
+        process_light_burn(&mut data, &mut rng);
+
+        assert_eq!(
+            data.event_log[0].texts(),
+            vec![
+                "Ginny's lantern is guttering, 10 min left".to_string(),
+                "Ginny's lantern gutters out".to_string(),
+                "The last light goes out. The party is in total darkness!".to_string()
+            ]
+        );
+        assert!(!relight_light(&mut data, 0));
+        assert_eq!(remove_spent_lights(&mut data), 1);
+        assert!(data.light_sources.is_empty());
+        assert_eq!(
+            data.event_log[0].events[3].text,
+            "Ginny's lantern is discarded"
+        );
+    }
+
+    #[test]
+    fn burnt_out_lights_offer_the_owners_spares() {
+        let mut data = ShadowtrackData::default();
+        data.burn_models.torch = BurnModel::PerTurn { minutes: 10 };
+        let ginny = data.add_character("Ginny");
+        data.characters[0].supplies.torches = 2;
+        data.light_sources.push(LightSource {
+            label: "Ginny".to_string(),
+            owner: Some(ginny),
+            ..light(LightSourceType::Torch, 10)
+        });
+        let mut rng = MockRng::new(vec![6], vec![]);
+
+        process_light_burn(&mut data, &mut rng);
+        assert_eq!(data.light_sources[0].state, LightState::BurntOut);
+        assert_eq!(
+            data.event_log[0].events[1].text,
+            "Ginny has 2 torches to replace it"
+        );
+
+        assert!(replace_from_supply(&mut data, 0));
+        assert_eq!(data.light_sources[0].state, LightState::Lit);
+        assert_eq!(data.light_sources[0].minutes_remaining, 60);
+        assert_eq!(data.characters[0].supplies.torches, 1);
+        assert!(!data.in_darkness);
+        // A torch still burning can't be swapped for a fresh one.
+        assert!(!replace_from_supply(&mut data, 0));
+    }
+
+    #[test]
+    fn spares_replace_burnt_out_lights_automatically() {
+        let mut data = ShadowtrackData::default();
+        data.supply_settings.auto_replace = true;
+        data.supply_settings.torch_minutes = 30;
+        data.burn_models.torch = BurnModel::PerTurn { minutes: 10 };
+        let ginny = data.add_character("Ginny");
+        data.characters[0].supplies.torches = 1;
+        data.light_sources.push(LightSource {
+            label: "Ginny".to_string(),
+            owner: Some(ginny),
+            ..light(LightSourceType::Torch, 10)
+        });
+        let mut rng = MockRng::new(vec![6], vec![]);
+
+        process_light_burn(&mut data, &mut rng);
+        assert_eq!(data.light_sources[0].state, LightState::Lit);
+        assert_eq!(data.light_sources[0].minutes_remaining, 30);
+        assert!(data.characters[0].supplies.is_empty());
+        assert_eq!(
+            data.event_log[0].texts(),
+            vec![
+                "Ginny's torch gutters out".to_string(),
+                "Ginny lights a fresh torch, 0 torches left".to_string()
+            ]
+        );
+
+        // A spare too short to outlast the warning starts out guttering.
+        data.supply_settings.torch_minutes = data.light_warning_minutes;
+        data.characters[0].supplies.torches = 1;
+        data.light_sources[0].minutes_remaining = 10;
+        process_light_burn(&mut data, &mut rng);
+        assert_eq!(data.light_sources[0].state, LightState::Guttering);
+        assert_eq!(
+            data.light_sources[0].minutes_remaining,
+            data.light_warning_minutes
+        );
+    }
+
+    #[test]
+    fn lanterns_refuel_from_oil_flasks() {
+        let mut data = ShadowtrackData::default();
+        let ginny = data.add_character("Ginny");
+        data.characters[0].supplies = Supplies {
+            oil_flasks: 1,
+            candles: 1,
+            ..Default::default()
+        };
+        data.light_sources.push(LightSource {
+            label: "Ginny".to_string(),
+            owner: Some(ginny),
+            ..light(LightSourceType::Lantern, 20)
+        });
+
+        assert!(replace_from_supply(&mut data, 0));
+        assert_eq!(data.light_sources[0].minutes_remaining, 80);
+        assert!(!replace_from_supply(&mut data, 0));
+        assert_eq!(
+            data.event_log[0].events[0].text,
+            "Ginny's lantern is refuelled (+60 min), 0 oil flasks left"
+        );
+
+        assert!(light_from_supply(&mut data, ginny, Supply::Candle));
+        let candle = &data.light_sources[1];
+        assert_eq!(candle.light_type, LightSourceType::Candle);
+        assert_eq!(candle.radius_feet, 5);
+        assert_eq!(candle.name(), "Ginny's candle");
+        assert_eq!(candle.owner, Some(ginny));
+        assert!(!light_from_supply(&mut data, ginny, Supply::Candle));
+        assert_eq!(data.characters[0].supplies.to_string(), "nothing");
+    }
+
+    #[test]
+    fn snuffed_lights_keep_their_time() {
+        let mut data = ShadowtrackData::default();
+        data.light_sources.push(LightSource {
+            label: "Ginny".to_string(),
+            ..light(LightSourceType::Torch, 40)
+        });
+
+        assert!(snuff_light(&mut data, 0));
+        assert!(!snuff_light(&mut data, 0));
+        process_light_burn(&mut data, &mut MockRng::new(vec![1], vec![]));
+        assert_eq!(data.light_sources[0].minutes_remaining, 40);
+        assert_eq!(data.light_sources[0].last_roll, None);
+
+        assert!(relight_light(&mut data, 0));
+        assert_eq!(data.light_sources[0].state, LightState::Lit);
+        assert_eq!(
+            data.event_log[0].texts(),
+            vec![
+                "Ginny's torch is snuffed out, 40 min left".to_string(),
+                "Ginny's torch is relit".to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn lights_without_a_duration_never_gutter() {
+        let mut data = ShadowtrackData::default();
+        data.burn_models.spell = BurnModel::Never;
+        data.light_sources
+            .push(light(LightSourceType::Spell("Light".to_string()), 0));
+
+        process_light_burn(&mut data, &mut MockRng::new(vec![1], vec![]));
+        assert_eq!(data.light_sources[0].state, LightState::Lit);
+        assert!(data.event_log.is_empty());
+    }
+
+    #[test]
+    fn illumination_follows_lit_lights() {
+        let mut data = ShadowtrackData::default();
+        assert_eq!(data.illumination(), Illumination::Darkness);
+
+        data.light_sources.push(LightSource {
+            radius_feet: 20,
+            ..light(LightSourceType::Torch, 60)
+        });
+        assert_eq!(data.illumination(), Illumination::Dim);
+
+        data.light_sources.push(light(LightSourceType::Lantern, 60));
+        assert_eq!(data.illumination(), Illumination::Bright);
+
+        data.light_sources[1].state = LightState::Guttering;
+        assert_eq!(data.illumination(), Illumination::Dim);
+
+        data.light_sources[0].state = LightState::Snuffed;
+        data.light_sources[1].state = LightState::BurntOut;
+        assert_eq!(data.illumination(), Illumination::Darkness);
+    }
+
+    #[test]
+    fn darkness_is_logged_and_raises_the_danger() {
+        let mut data = ShadowtrackData {
+            danger_level: DangerLevel::Unsafe,
+            ..Default::default()
+        };
+        assert_eq!(data.illumination.darkness_danger_level, None);
+        data.illumination.darkness_danger_level = Some(DangerLevel::Deadly);
+        let mut session = Session::new(data, MockRng::new(vec![4], vec![0]));
+        session.add_light(LightSource {
+            label: "Ginny".to_string(),
+            ..light(LightSourceType::Torch, 60)
+        });
+        assert!(!session.data.in_darkness);
+
+        session.snuff_light(0);
+        assert!(session.data.in_darkness);
+        assert_eq!(session.data.danger_level, DangerLevel::Deadly);
+
+        session.relight_light(0);
+        assert_eq!(session.data.danger_level, DangerLevel::Unsafe);
+        assert_eq!(
+            session.data.event_log[0].texts(),
+            vec![
+                "Ginny's torch is snuffed out, 60 min left".to_string(),
+                "The last light goes out. The party is in total darkness!".to_string(),
+                "Danger level: Deadly (1 in 6 every turn)".to_string(),
+                "Ginny's torch is relit".to_string(),
+                "Danger level: Unsafe (1 in 6 every 3 turns)".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn darkness_keeps_a_danger_level_picked_meanwhile() {
+        let mut data = ShadowtrackData {
+            danger_level: DangerLevel::Safe,
+            ..Default::default()
+        };
+        data.illumination.darkness_danger_level = Some(DangerLevel::Deadly);
+        let mut session = Session::new(data, MockRng::new(vec![4], vec![0]));
+        session.add_light(light(LightSourceType::Torch, 60));
+        session.extinguish_light(0);
+        session.set_danger_level(DangerLevel::Risky);
+        session.relight_light(0);
+        assert_eq!(session.data.danger_level, DangerLevel::Risky);
+
+        session.data.illumination.darkness_danger_level = None;
+        session.snuff_light(0);
+        assert!(session.data.in_darkness);
+        assert_eq!(session.data.danger_level, DangerLevel::Risky);
+    }
+
+    #[test]
+    fn loading_an_old_save_in_the_dark_logs_nothing() {
+        // Saves from before darkness was tracked load with `in_darkness` unset.
+        let data = ShadowtrackData {
+            in_darkness: false,
+            ..Default::default()
+        };
+        let mut session = Session::default();
+        session.load(data.clone());
+        assert!(session.data.in_darkness);
+        let mut session = Session::new(data, MockRng::new(vec![6], vec![0]));
+        assert!(session.data.in_darkness);
+
+        session.process_turn();
+        assert!(session
+            .data
+            .event_log
+            .iter()
+            .flat_map(|entry| &entry.events)
+            .all(|event| event.event != LogEvent::Darkness));
+    }
+
+    #[test]
+    fn new_lights_keep_the_chosen_duration_and_radius() {
+        let mut data = ShadowtrackData {
+            new_light_label: "Ginny".to_string(),
+            new_light_minutes: 90,
+            new_light_range: 15,
+            ..Default::default()
+        };
+        let light = data.take_new_light().unwrap();
+        assert_eq!(light.minutes_remaining, 90);
+        assert_eq!(light.radius_feet, 15);
+
+        // The owner is cleared, everything else is kept for the next light.
+        assert!(data.take_new_light().is_none());
+        assert_eq!(data.new_light_range, 15);
+        assert_eq!(data.new_light_minutes, 90);
+    }
+
+    #[test]
+    fn light_templates_carry_their_burn_model() {
+        let mut data = ShadowtrackData::default();
+        let index = data
+            .light_templates
+            .iter()
+            .position(|template| template.name == "Light spell 30ft/1h")
+            .unwrap();
+        data.apply_light_template(index);
+        data.new_light_label = "Aloona".to_string();
+        let light = data.take_new_light().unwrap();
+
+        assert_eq!(
+            light.light_type,
+            LightSourceType::Spell("Light".to_string())
+        );
+        assert_eq!(light.burn_model(&data.burn_models), &BurnModel::RealTime);
+        data.light_sources.push(light);
+
+        // Real-time lights ignore turn burn.
+        process_light_burn(&mut data, &mut MockRng::new(vec![1], vec![]));
+        assert_eq!(data.light_sources[0].minutes_remaining, 60);
+    }
+
+    #[test]
+    fn light_library_round_trips_and_merges_by_name() {
+        use crate::save::{load_light_library, write_light_library};
+        use crate::state::LightTemplate;
+        use std::{env, fs};
+
+        let library_file = env::temp_dir().join("test_light_library.json");
+        let templates = vec![
+            LightTemplate {
+                name: "torch 30ft/60m".to_string(),
+                radius_feet: 40,
+                ..ShadowtrackData::default().light_templates[0].clone()
+            },
+            LightTemplate {
+                name: "Candle 5ft/1h".to_string(),
+                light_type: LightSourceType::Torch,
+                radius_feet: 5,
+                minutes: 60,
+                burn: Some(BurnModel::PerTurn { minutes: 10 }),
+            },
+        ];
+        write_light_library(&library_file, &templates).unwrap();
+        let loaded = load_light_library(&library_file).unwrap();
+        fs::remove_file(library_file).unwrap();
+        assert_eq!(loaded, templates);
+
+        let mut data = ShadowtrackData::default();
+        data.import_light_templates(loaded);
+        assert_eq!(data.light_templates.len(), 4);
+        assert_eq!(
+            data.find_light_template("Torch 30ft/60m")
+                .unwrap()
+                .radius_feet,
+            40
+        );
+        assert!(data.find_light_template("candle 5ft/1h").is_some());
+    }
+
+    #[test]
+    fn lights_link_to_characters_on_the_roster() {
+        let mut data = ShadowtrackData::default();
+        let ginny = data.add_character("Ginny");
+        add_light(
+            &mut data,
+            LightSource {
+                owner: Some(ginny),
+                ..light(LightSourceType::Torch, 60)
+            },
+        );
+        add_light(
+            &mut data,
+            LightSource {
+                label: "Ginny".to_string(),
+                ..light(LightSourceType::Candle, 60)
+            },
+        );
+
+        // Lights take their owner's name, and a label alone doesn't put anyone on the roster.
+        assert_eq!(data.light_sources[0].owner, Some(ginny));
+        assert_eq!(data.light_sources[0].label, "Ginny");
+        assert_eq!(data.light_sources[1].owner, None);
+        data.new_light_label = "Hireling".to_string();
+        let new_light = data.take_new_light().unwrap();
+        add_light(&mut data, new_light);
+        assert_eq!(data.light_sources[2].owner, None);
+        assert_eq!(data.light_sources[2].label, "Hireling");
+        data.new_light_owner = Some(ginny);
+        let new_light = data.take_new_light().unwrap();
+        add_light(&mut data, new_light);
+        assert_eq!(data.light_sources[3].owner, Some(ginny));
+        assert_eq!(data.characters.len(), 1);
+
+        data.rename_character(ginny, "Ginny Hardbark");
+        assert_eq!(data.light_sources[0].name(), "Ginny Hardbark's torch");
+        assert_eq!(data.character_lights(ginny).count(), 2);
+
+        data.remove_character(ginny);
+        assert_eq!(data.light_sources[0].owner, None);
+        assert!(data.characters.is_empty());
+    }
+
+    #[test]
+    fn saves_without_a_roster_link_their_lights() {
+        let mut data = ShadowtrackData {
+            roster_linked: false,
+            ..Default::default()
+        };
+        data.light_sources.push(LightSource {
+            label: "Ginny".to_string(),
+            ..light(LightSourceType::Torch, 60)
+        });
+        data.light_sources.push(LightSource {
+            label: "Ginny".to_string(),
+            ..light(LightSourceType::Lantern, 60)
+        });
+
+        let mut session = Session::from_data(data);
+        assert_eq!(session.data.characters.len(), 1);
+        let ginny = session.data.characters[0].id;
+        assert_eq!(session.data.character_lights(ginny).count(), 2);
+
+        // Only once: a character taken off the roster stays off when the game is reloaded.
+        session.data.remove_character(ginny);
+        let saved = serde_json::to_string(&session.data).unwrap();
+        let session = Session::from_data(serde_json::from_str(&saved).unwrap());
+        assert!(session.data.characters.is_empty());
+        assert_eq!(session.data.light_sources[0].owner, None);
+    }
+
+    #[test]
+    fn characters_track_conditions() {
+        let mut character = Character::new(1, "Ginny");
+        character.max_hp = 8;
+        character.hp = 5;
+        character.luck_tokens = 1;
+        assert!(character.add_condition("Poisoned"));
+        assert!(!character.add_condition("poisoned"));
+        assert!(character.has_condition("POISONED"));
+        assert_eq!(
+            character.to_string(),
+            "Ginny (L1) HP 5/8 AC 10, 1 luck, Poisoned"
+        );
+        assert!(character.remove_condition("poisoned"));
+        assert!(!character.remove_condition("Poisoned"));
+        assert!(!character.is_down());
+
+        character.set_max_hp(4);
+        assert_eq!((character.hp, character.max_hp), (4, 4));
+        character.set_max_hp(10);
+        assert_eq!((character.hp, character.max_hp), (4, 10));
+        character.set_max_hp(0);
+        assert_eq!((character.hp, character.max_hp), (1, 1));
+    }
+
+    #[test]
+    fn effects_count_down_with_the_clock_and_turns() {
+        let mut data = ShadowtrackData::default();
+        let ginny = data.add_character("Ginny");
+        let mut session = Session::new(data, MockRng::new(vec![6], vec![0]));
+        session.add_effect(TimedEffect::new(
+            "Poisoned",
+            EffectTarget::Character(ginny),
+            EffectUnit::Turns,
+            2,
+        ));
+        let mut bless = TimedEffect::new("Bless", EffectTarget::Party, EffectUnit::Minutes, 15);
+        bless.expiry_message = "The blessing fades".to_string();
+        session.add_effect(bless);
+        assert!(session.data.characters[0].has_condition("Poisoned"));
+
+        session.pass_minutes(15);
+        assert_eq!(session.data.effects.len(), 1);
+        assert_eq!(session.data.effects[0].remaining, 1);
+        session.pass_minutes(10);
+        assert!(session.data.effects.is_empty());
+        assert!(!session.data.characters[0].has_condition("Poisoned"));
+
+        let events: Vec<&str> = session
+            .data
+            .event_log
+            .iter()
+            .flat_map(|entry| entry.texts())
+            .collect();
+        assert_eq!(events[0], "Poisoned on Ginny for 2 turns");
+        assert_eq!(events[1], "Bless on the party for 15 min");
+        assert!(events.contains(&"The blessing fades"));
+        assert!(events.contains(&"Poisoned on Ginny wears off"));
+    }
+
+    #[test]
+    fn effects_end_early_and_with_their_light() {
+        let mut data = ShadowtrackData::default();
+        add_light(
+            &mut data,
+            LightSource {
+                label: "Ginny".to_string(),
+                ..light(LightSourceType::Torch, 0)
+            },
+        );
+        let torch = data.light_sources[0].id;
+        assert_ne!(torch, 0);
+        add_effect(
+            &mut data,
+            TimedEffect::new("Hex", EffectTarget::Light(torch), EffectUnit::Rounds, 3),
+        );
+        add_effect(
+            &mut data,
+            TimedEffect::new("Stun", EffectTarget::Party, EffectUnit::Rounds, 1),
+        );
+        count_down_effects(&mut data, EffectUnit::Turns, 5);
+        assert_eq!(data.effects.len(), 2);
+
+        assert!(end_effect(&mut data, 1));
+        assert!(!end_effect(&mut data, 1));
+        assert_eq!(
+            data.event_log[0].events.last().unwrap().text,
+            "Stun on the party ends early"
+        );
+
+        data.light_sources[0].state = LightState::BurntOut;
+        remove_spent_lights(&mut data);
+        assert!(data.effects.is_empty());
+    }
+
+    #[test]
+    fn combat_rounds_pause_exploration_time() {
+        let mut data = ShadowtrackData::default();
+        data.burn_models.spell = BurnModel::RealTime;
+        let aloona = data.add_character("Aloona");
+        add_light(
+            &mut data,
+            LightSource {
+                owner: Some(aloona),
+                ..light(LightSourceType::Spell("Light".to_string()), 60)
+            },
+        );
+        data.clock_elapsed = 9 * 60 + 30;
+        data.next_process_minutes = Some(10);
+        let mut session = Session::new(data, MockRng::new(vec![6], vec![0]));
+        session.toggle_clock();
+
+        assert!(session.start_combat(vec!["Goblin".to_string(), " ".to_string()]));
+        assert!(!session.start_combat(vec![]));
+        let order = &session.data.combat.as_ref().unwrap().order;
+        assert_eq!(order.len(), 2);
+        assert!(!order[0].is_monster());
+        assert!(!session.tick(60));
+        assert!(!session.pass_time(60));
+        assert_eq!(session.pass_minutes(30), 0);
+        assert!(!session.pass_turns(1));
+        assert_eq!(session.data.clock_elapsed, 9 * 60 + 30);
+
+        session.add_effect(TimedEffect::new(
+            "Stun",
+            EffectTarget::Party,
+            EffectUnit::Rounds,
+            2,
+        ));
+        for _ in 0..9 {
+            assert!(session.next_round());
+        }
+        // Five rounds in, the fight crossed into minute 10.
+        assert_eq!(session.data.light_sources[0].minutes_remaining, 59);
+        assert!(session.data.effects.is_empty());
+        assert_eq!(session.data.turn, 0);
+
+        assert!(session.end_combat());
+        assert!(!session.end_combat());
+        assert!(session.data.combat.is_none());
+        assert_eq!(session.data.clock_elapsed, 9 * 60 + 30 + 60);
+        assert_eq!(session.data.turn, 1);
+        let events: Vec<&str> = session
+            .data
+            .event_log
+            .iter()
+            .flat_map(|entry| entry.texts())
+            .collect();
+        assert!(events.contains(&"Combat begins against Goblin"));
+        assert!(events.contains(&"Round 10"));
+        assert!(events.contains(&"Combat ends after 10 rounds, 1 min"));
+
+        // A fight that runs past more than one turn processes each of them when it ends.
+        assert!(session.start_combat(vec!["Goblin".to_string()]));
+        for _ in 1..250 {
+            session.next_round();
+        }
+        assert!(session.end_combat());
+        assert_eq!(session.data.clock_elapsed, 10 * 60 + 30 + 25 * 60);
+        assert_eq!(session.data.turn, 3);
+        assert_eq!(session.data.next_process_minutes, Some(40));
+    }
+
+    #[test]
+    fn manual_clock_adjustments_process_no_turns() {
+        let mut session = Session::new(ShadowtrackData::default(), MockRng::new(vec![4], vec![0]));
+        session.toggle_clock();
+        assert!(!session.tick(1));
+
+        session.advance_clock_secs(25 * 60);
+        assert_eq!(session.data.turn, 0);
+
+        // The turn the clock was moved past is processed once, and the next is a full turn on.
+        assert!(session.tick(1));
+        assert!(!session.tick(1));
+        assert_eq!(session.data.turn, 1);
+        assert_eq!(session.data.next_process_minutes, Some(35));
+    }
+
+    #[test]
+    fn initiative_orders_the_fight_by_mode() {
+        let names = |session: &Session<MockRng>| -> Vec<String> {
+            let combat = session.data.combat.as_ref().unwrap();
+            combat.order.iter().map(|c| c.name.clone()).collect()
+        };
+        let mut data = ShadowtrackData::default();
+        data.add_character("Ginny");
+        data.add_character("Aloona");
+        let monsters = vec!["Goblin".to_string(), "Orc".to_string()];
+
+        let mut session = Session::new(data.clone(), MockRng::new(vec![5, 15, 10, 20], vec![]));
+        session.start_combat(monsters.clone());
+        assert_eq!(names(&session), ["Orc", "Aloona", "Goblin", "Ginny"]);
+        assert_eq!(
+            session.data.event_log[0].events[1].text,
+            "Initiative (Individual): Orc 20, Aloona 15, Goblin 10, Ginny 5"
+        );
+
+        // Fallen characters are skipped, and the last turn rolls over into the next round.
+        session.data.characters[1].hp = 0;
+        assert!(session.next_turn());
+        assert_eq!(
+            session
+                .data
+                .combat
+                .as_ref()
+                .unwrap()
+                .current()
+                .unwrap()
+                .name,
+            "Goblin"
+        );
+        session.next_turn();
+        session.next_turn();
+        let combat = session.data.combat.as_ref().unwrap();
+        assert_eq!((combat.round, combat.current), (2, 0));
+
+        data.combat_settings.initiative = InitiativeMode::Clockwise;
+        let mut session = Session::new(data.clone(), MockRng::new(vec![5, 15, 10, 20], vec![]));
+        session.start_combat(monsters.clone());
+        assert_eq!(names(&session), ["Orc", "Ginny", "Aloona", "Goblin"]);
+
+        data.combat_settings.initiative = InitiativeMode::Group;
+        let mut session = Session::new(data, MockRng::new(vec![8], vec![]));
+        session.start_combat(monsters);
+        assert_eq!(names(&session), ["Ginny", "Aloona", "Goblin", "Orc"]);
+        let combat = session.data.combat.as_ref().unwrap();
+        assert!(combat.order.iter().all(|c| c.initiative == Some(8)));
+    }
+
+    #[test]
+    fn monster_damage_calls_for_morale_checks() {
+        use crate::bestiary::{default_bestiary, MonsterGroup};
+        use crate::state::MoraleRule;
+        use crate::systems::{check_morale, damage_monster, next_turn, start_combat};
+
+        let goblin = default_bestiary().remove(0);
+        let mut data = ShadowtrackData {
+            last_encounter: Some("Goblin scouts".to_string()),
+            last_monsters: vec![MonsterGroup {
+                leader: Some(0),
+                ..MonsterGroup::new(goblin, vec![4, 4, 4, 4])
+            }],
+            ..Default::default()
+        };
+        let mut rng = MockRng::new(vec![3], vec![]);
+        fn events(data: &ShadowtrackData) -> Vec<&str> {
+            data.event_log[0].texts()
+        }
+
+        assert!(damage_monster(&mut data, &mut rng, 0, 1, 2));
+        assert!(damage_monster(&mut data, &mut rng, 0, 1, 5));
+        assert!(!damage_monster(&mut data, &mut rng, 0, 4, 1));
+        // The leader falling also brings half the group down, for one check.
+        assert!(damage_monster(&mut data, &mut rng, 0, 0, 4));
+        assert!(damage_monster(&mut data, &mut rng, 0, 1, -3));
+        assert!(damage_monster(&mut data, &mut rng, 0, 1, 3));
+        assert_eq!(
+            events(&data),
+            [
+                "Goblin 2 takes 2 damage, 2/4 HP",
+                "Goblin 2 takes 5 damage and falls",
+                "Goblin 1 takes 4 damage and falls",
+                "Morale check for Goblin (their leader has fallen): 6 on 2d6 against 7, they hold",
+                "Goblin 2 heals 3, 3/4 HP",
+                "Goblin 2 takes 3 damage and falls",
+            ]
+        );
+        let group = &data.last_monsters[0];
+        assert_eq!(group.fallen(), 2);
+        assert_eq!(
+            group.to_string(),
+            "4 × Goblin (HP dead, dead, 4, 4): AC 11, 1 club +0 (1d4), MOR 7"
+        );
+
+        // Monsters that flee drop out of the fight.
+        let foes = data.last_encounter_foes();
+        assert!(start_combat(&mut data, foes));
+        let combat = data.combat.as_ref().unwrap();
+        assert_eq!(combat.order[2].creature, Some((0, 2)));
+        assert!(data.is_out_of_fight(&combat.order[0]));
+        next_turn(&mut data);
+        assert_eq!(data.combat.as_ref().unwrap().current, 2);
+
+        data.morale_settings.rule = MoraleRule::D20;
+        let held = check_morale(
+            &mut data,
+            &mut MockRng::new(vec![15], vec![]),
+            0,
+            "called for",
+        );
+        assert_eq!(held, Some(false));
+        assert!(data.last_monsters[0].fled);
+        let combat = data.combat.as_ref().unwrap();
+        assert!(data.is_out_of_fight(&combat.order[3]));
+        assert_eq!(
+            *events(&data).last().unwrap(),
+            "Morale check for Goblin (called for): 15 on d20 against 7, they flee"
+        );
+    }
+
+    #[test]
+    fn old_log_events_load_as_typed_events() {
+        let data: ShadowtrackData =
+            serde_json::from_str(include_str!("../../../example_save.json")).unwrap();
+        let event = &data.event_log[0].events[0];
+        assert_eq!(event.text, "No encounter");
+        assert_eq!(event.event, LogEvent::NoEncounter);
+        assert_eq!(event.game_time_label(), "--:--");
+        assert!(event.wall_clock.is_none());
+
+        let entry: TurnEntry = serde_json::from_str(
+            r#"{"turn": 4, "events": ["Torch gutters", "!ENCOUNTER! - Kobolds"]}"#,
+        )
+        .unwrap();
+        assert_eq!(entry.events[0].event, LogEvent::Message);
+        assert!(entry.events[0].detail.is_none());
+        assert_eq!(
+            entry.events[1].event,
+            LogEvent::Encounter {
+                result: "Kobolds".to_string()
+            }
+        );
+
+        let json = serde_json::to_string(&entry).unwrap();
+        assert_eq!(serde_json::from_str::<TurnEntry>(&json).unwrap(), entry);
+    }
+
+    #[test]
+    fn logged_events_record_game_and_wall_clock_time() {
+        let mut session = Session::default();
+        // Adjustments one after another are logged as one move.
+        session.advance_clock_secs(60);
+        session.advance_clock_secs(65);
+        session.set_danger_level(DangerLevel::Risky);
+
+        let events = &session.data.event_log[0].events;
+        assert_eq!(events[0].text, "Clock moved from 00:00 to 02:05");
+        assert_eq!(
+            events[0].event,
+            LogEvent::ClockAdjust {
+                from_secs: 0,
+                to_secs: 125
+            }
+        );
+        assert_eq!(events[1].game_time_label(), "02:05");
+        assert_eq!(
+            events[1].event,
+            LogEvent::DangerChange {
+                level: DangerLevel::Risky
+            }
+        );
+        assert!(events[1].wall_clock.is_some());
+        assert_eq!(events.len(), 2);
+
+        session.advance_clock_secs(60);
+        let events = &session.data.event_log[0].events;
+        assert_eq!(events[2].text, "Clock moved from 02:05 to 03:05");
+
+        assert_eq!(format_utc(0), "1970-01-01 00:00 UTC");
+        assert_eq!(format_utc(951_827_400), "2000-02-29 12:30 UTC");
+        assert_eq!(format_utc(1_740_857_400), "2025-03-01 19:30 UTC");
+        assert_eq!(format_clock(0), "00:00");
+        assert_eq!(format_clock(605), "10:05");
+        assert_eq!(format_clock(7200), "120:00");
+    }
+
+    #[test]
+    fn log_events_are_tagged_and_filtered() {
+        let mut session = Session::default();
+        session.data.turn = 1;
+        session.set_danger_level(DangerLevel::Risky);
+        session.data.turn = 2;
+        session.roll_dice("d6").unwrap();
+        session.data.turn = 3;
+        session.data.encounter_table = RollTable::uniform(["{{Mimic}} chest"]);
+        session.roll_encounter(true);
+
+        let event = &session.data.event_log[2].events[0];
+        assert_eq!(event.tags, ["encounter"]);
+        assert!(session.tag_event(3, 0, " Loot"));
+        assert!(!session.tag_event(3, 0, "loot"));
+        assert!(!session.tag_event(3, 5, "loot"));
+        assert!(session.tag_event(1, 0, "mystery"));
+        assert!(session.untag_event(1, 0, "danger"));
+        assert_eq!(session.data.event_log[0].events[0].tags, ["mystery"]);
+        assert!(session.data.log_tags().contains(&"dice".to_string()));
+
+        let log = &session.data.event_log;
+        let turns = |filter: &LogFilter| -> Vec<u32> {
+            filter
+                .apply(log)
+                .iter()
+                .map(|(entry, _)| entry.turn)
+                .collect()
+        };
+        let mut filter = LogFilter::default();
+        assert_eq!(turns(&filter), [1, 2, 3]);
+        filter.toggle_tag("LOOT");
+        filter.toggle_tag("mystery");
+        assert_eq!(turns(&filter), [1, 3]);
+        filter.from_turn = Some(2);
+        assert_eq!(turns(&filter), [3]);
+
+        // Searches ignore case and look through encounter follow-ups too.
+        let filter = LogFilter {
+            text: "MIMIC".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(turns(&filter), [3]);
+        let filter = LogFilter {
+            text: "AC 12".to_string(),
+            to_turn: Some(3),
+            ..Default::default()
+        };
+        assert_eq!(turns(&filter), [3]);
+    }
+
+    #[test]
+    fn log_events_are_edited_struck_out_moved_and_noted() {
+        let mut session = Session::default();
+        session.data.turn = 1;
+        session.roll_dice("d6").unwrap();
+        session.set_danger_level(DangerLevel::Risky);
+        session.advance_clock_secs(60);
+        session.data.turn = 3;
+
+        assert!(session.edit_event(1, 0, "Rolled a 4 to find the key"));
+        assert!(!session.edit_event(1, 0, "Rolled a 4 to find the key"));
+        assert!(session.edit_event(1, 0, "Found the key"));
+        let event = session.data.logged_event(1, 0).unwrap();
+        assert_eq!(event.text, "Found the key");
+        assert!(event.original_text().starts_with("Rolled d6: "));
+        assert_eq!(event.edits.len(), 2);
+
+        assert!(session.strike_event(1, 1, true));
+        assert!(!session.strike_event(1, 1, true));
+        assert!(session.data.logged_event(1, 1).unwrap().deleted);
+
+        assert!(session.move_event(1, 2, 0));
+        assert!(!session.move_event(1, 0, 3));
+        assert!(session.data.event_log[0].texts()[0].starts_with("Clock moved"));
+
+        session.add_note(1, Some(1), "The ogre dropped a brass key");
+        session.add_note(3, None, "Rested in the crypt");
+        session.add_note(2, None, "Searched the corridor");
+        let entry = &session.data.event_log[0];
+        assert_eq!(entry.events[1].event, LogEvent::Note);
+        assert_eq!(entry.events[1].text, "The ogre dropped a brass key");
+        assert_eq!(entry.events[1].game_secs, Some(60));
+        let turns: Vec<u32> = session.data.event_log.iter().map(|e| e.turn).collect();
+        assert_eq!(turns, [1, 2, 3]);
+        assert_eq!(session.data.event_log[1].events[0].game_secs, None);
+        assert_eq!(session.data.event_log[2].events[0].game_secs, Some(60));
+
+        let json = serde_json::to_string(&session.data.event_log).unwrap();
+        let log: Vec<TurnEntry> = serde_json::from_str(&json).unwrap();
+        assert_eq!(log, session.data.event_log);
+    }
+
+    #[test]
+    fn recaps_summarise_the_log() {
+        let burnout = |kind| LogEvent::LightBurnout {
+            light: 1,
+            owner: "Ginny".to_string(),
+            kind,
+        };
+        let encounter = LogEvent::Encounter {
+            result: "Goblins".to_string(),
+        };
+        let mut struck = LoggedEvent::new(encounter.clone(), "!ENCOUNTER! - Rats", 600);
+        struck.deleted = true;
+        let data = ShadowtrackData {
+            turn: 2,
+            clock_elapsed: 1200,
+            event_log: vec![
+                TurnEntry {
+                    turn: 1,
+                    events: vec![
+                        LoggedEvent::new(encounter, "!ENCOUNTER! - Goblins & <wolves>", 600),
+                        struck,
+                        LoggedEvent::new(LogEvent::Note, "The goblins fled", 600),
+                    ],
+                },
+                TurnEntry {
+                    turn: 2,
+                    events: vec![
+                        LoggedEvent::new(burnout(LightSourceType::Torch), "Torch out", 1200),
+                        LoggedEvent::new(burnout(LightSourceType::Candle), "Candle out", 1200),
+                    ],
+                },
+            ],
+            ..Default::default()
+        };
+
+        let recap = Recap::new(&data);
+        assert_eq!(recap.turns_elapsed, 2);
+        assert_eq!(recap.torches_consumed, 1);
+        assert_eq!(recap.encounters.len(), 1);
+        assert_eq!(recap.encounters[0].text, "Goblins & <wolves>");
+        assert_eq!(recap.burnouts.len(), 2);
+        assert_eq!(recap.notes[0].text, "The goblins fled");
+        assert_eq!(recap.turns[0].1.len(), 2);
+
+        let markdown = recap.render(RecapFormat::Markdown);
+        assert!(markdown.contains("- **Torches consumed:** 1"));
+        assert!(markdown.contains("- Turn 1 `10:00` Goblins & <wolves>"));
+        assert!(markdown.contains("- `10:00` *The goblins fled*"));
+        assert!(!markdown.contains("Rats"));
+        let html = recap.render(RecapFormat::Html);
+        assert!(html.contains("<time>10:00</time>Goblins &amp; &lt;wolves&gt;"));
+        assert!(html.contains("<style>"));
+        let text = recap.render(RecapFormat::Text);
+        assert!(text.contains("Turn 2 [20:00] Candle out"));
+        assert!(text.contains("  [10:00] Note: The goblins fled"));
+    }
+
+    #[test]
+    fn autosaves_rotate_backups_and_recover() {
+        use std::{env, fs};
+
+        let dir = env::temp_dir().join(format!("shadowtrack_autosave_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let mut autosaver = Autosaver::new(&dir);
+        let mut data = ShadowtrackData::default();
+        autosaver.settings.interval_secs = 60;
+        autosaver.settings.backups = 2;
+
+        assert!(!autosaver.unclean_shutdown());
+        autosaver.start(&data).unwrap();
+        assert!(autosaver.unclean_shutdown());
+
+        // Saves on each processed turn and once the interval passes, not in between.
+        assert!(!autosaver.tick(&data, 30).unwrap());
+        data.turn = 1;
+        assert!(autosaver.tick(&data, 1).unwrap());
+        assert!(!autosaver.tick(&data, 59).unwrap());
+        data.clock_elapsed = 60;
+        assert!(autosaver.tick(&data, 1).unwrap());
+        assert_eq!(autosaver.load_latest().unwrap(), data);
+        assert!(!dir.join("autosave.json.tmp").exists());
+
+        // Only the newest backups are kept, newest first.
+        data.turn = 2;
+        autosaver.save(&data).unwrap();
+        data.turn = 3;
+        autosaver.save(&data).unwrap();
+        let backups = autosaver.backups();
+        assert_eq!(backups.len(), 2);
+        let turns: Vec<u32> = backups
+            .iter()
+            .map(|path| crate::save::load_save(path).unwrap().turn)
+            .collect();
+        assert_eq!(turns, vec![2, 1]);
+
+        // An unchanged game isn't saved again, so its backups aren't rotated away.
+        assert!(!autosaver.tick(&data, 600).unwrap());
+        let turns: Vec<u32> = autosaver
+            .backups()
+            .iter()
+            .map(|path| crate::save::load_save(path).unwrap().turn)
+            .collect();
+        assert_eq!(turns, vec![2, 1]);
+
+        autosaver.settings.enabled = false;
+        data.turn = 4;
+        assert!(!autosaver.tick(&data, 600).unwrap());
+        autosaver.finish(&data).unwrap();
+        assert!(!autosaver.unclean_shutdown());
+        assert_eq!(autosaver.load_latest().unwrap().turn, 3);
+
+        // A damaged autosave falls back to the newest backup that still loads.
+        fs::write(autosaver.latest_path(), "{").unwrap();
+        fs::write(autosaver.backup_path(1), "").unwrap();
+        assert!(autosaver.load_latest().is_err());
+        assert_eq!(autosaver.load_newest().unwrap().turn, 1);
+
+        // Settings are kept with the autosaves, not in the game.
+        autosaver.save_settings().unwrap();
+        assert_eq!(Autosaver::new(&dir).settings, autosaver.settings);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
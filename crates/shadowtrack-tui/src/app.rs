@@ -0,0 +1,204 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind};
+use shadowtrack_core::save::write_save;
+use shadowtrack_core::session::Session;
+use shadowtrack_core::state::{DangerLevel, LightSourceType, ShadowtrackData};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Mode {
+    Normal,
+    AddLight(AddLightField),
+}
+
+/// Text field that receives typing in the add light popup.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AddLightField {
+    /// Label for a light nobody in the party carries.
+    Label,
+    Spell,
+}
+
+pub struct TuiApp {
+    pub session: Session,
+    pub last_tick: Instant,
+    pub mode: Mode,
+    pub save_path: Option<PathBuf>,
+    pub status: Option<String>,
+    pub should_quit: bool,
+    /// Light template last picked in the add light popup.
+    pub template: Option<usize>,
+}
+
+impl TuiApp {
+    pub fn new(session: Session, save_path: Option<PathBuf>) -> Self {
+        Self {
+            session,
+            last_tick: Instant::now(),
+            mode: Mode::Normal,
+            save_path,
+            status: None,
+            should_quit: false,
+            template: None,
+        }
+    }
+
+    #[inline]
+    pub(crate) fn should_tick(&self, now: Instant) -> Option<u64> {
+        let elapsed = now.duration_since(self.last_tick);
+        if elapsed >= Duration::from_secs(1) {
+            Some(elapsed.as_secs())
+        } else {
+            None
+        }
+    }
+
+    pub(crate) fn handle_clock_tick(&mut self) {
+        // Tick every second
+        if let Some(secs) = self.should_tick(Instant::now()) {
+            self.last_tick += Duration::from_secs(secs);
+            self.session.tick(secs);
+        }
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) {
+        if key.kind != KeyEventKind::Press {
+            return;
+        }
+        match self.mode {
+            Mode::Normal => self.handle_normal_key(key.code),
+            Mode::AddLight(field) => self.handle_add_light_key(key.code, field),
+        }
+    }
+
+    fn handle_normal_key(&mut self, code: KeyCode) {
+        match code {
+            KeyCode::Char('q') | KeyCode::Esc => self.should_quit = true,
+            KeyCode::Char(' ') => self.session.toggle_clock(),
+            KeyCode::Char('r') => self.session.reset_clock(),
+            KeyCode::Char('1') => self.session.advance_clock_secs(60),
+            KeyCode::Char('5') => self.session.advance_clock_secs(300),
+            KeyCode::Char('0') => self.session.advance_clock_secs(600),
+            KeyCode::Char('a') => self.mode = Mode::AddLight(AddLightField::Label),
+            KeyCode::Char('e') => self.session.roll_encounter(true),
+            KeyCode::Char('t') => self.session.roll_light_event(),
+            KeyCode::Char('d') => self.cycle_danger_level(),
+            KeyCode::Char('x') => _ = self.session.remove_spent_lights(),
+            KeyCode::Char('c') => self.toggle_combat(),
+            KeyCode::Char('n') => _ = self.session.next_turn(),
+            KeyCode::Char('i') => _ = self.session.roll_initiative(),
+            KeyCode::Char('s') => self.save(),
+            _ => {}
+        }
+    }
+
+    fn handle_add_light_key(&mut self, code: KeyCode, field: AddLightField) {
+        let data = &mut self.session.data;
+        match code {
+            KeyCode::Esc => self.mode = Mode::Normal,
+            KeyCode::Enter => self.add_light(),
+            KeyCode::Tab => {
+                if let LightSourceType::Spell(_) = data.new_light_type {
+                    self.mode = match field {
+                        AddLightField::Label => Mode::AddLight(AddLightField::Spell),
+                        AddLightField::Spell => Mode::AddLight(AddLightField::Label),
+                    };
+                }
+            }
+            KeyCode::Up | KeyCode::Down => {
+                data.new_light_type = match (&data.new_light_type, code) {
+                    (LightSourceType::Torch, KeyCode::Down) => LightSourceType::Lantern,
+                    (LightSourceType::Lantern, KeyCode::Down) => LightSourceType::Candle,
+                    (LightSourceType::Candle, KeyCode::Down) => {
+                        LightSourceType::Spell(String::new())
+                    }
+                    (LightSourceType::Spell(_), KeyCode::Down) => LightSourceType::Torch,
+                    (LightSourceType::Torch, _) => LightSourceType::Spell(String::new()),
+                    (LightSourceType::Lantern, _) => LightSourceType::Torch,
+                    (LightSourceType::Candle, _) => LightSourceType::Lantern,
+                    (LightSourceType::Spell(_), _) => LightSourceType::Candle,
+                };
+                // A template's burn model belongs with its type.
+                data.new_light_burn = None;
+                self.mode = Mode::AddLight(AddLightField::Label);
+            }
+            KeyCode::Left => data.new_light_range = data.new_light_range.saturating_sub(5).max(5),
+            KeyCode::Right => data.new_light_range = (data.new_light_range + 5).min(100),
+            KeyCode::PageDown => {
+                data.new_light_minutes = data.new_light_minutes.saturating_sub(10).max(10)
+            }
+            KeyCode::PageUp => data.new_light_minutes += 10,
+            KeyCode::F(3) => data.new_light_owner = next_owner(data),
+            KeyCode::F(2) if !data.light_templates.is_empty() => {
+                let next = self
+                    .template
+                    .map_or(0, |index| (index + 1) % data.light_templates.len());
+                data.apply_light_template(next);
+                self.template = Some(next);
+                self.mode = Mode::AddLight(AddLightField::Label);
+            }
+            KeyCode::Backspace => {
+                self.focused_text(field).pop();
+            }
+            KeyCode::Char(c) => self.focused_text(field).push(c),
+            _ => {}
+        }
+    }
+
+    fn focused_text(&mut self, field: AddLightField) -> &mut String {
+        let data = &mut self.session.data;
+        match (field, &mut data.new_light_type) {
+            (AddLightField::Spell, LightSourceType::Spell(name)) => name,
+            _ => &mut data.new_light_label,
+        }
+    }
+
+    fn add_light(&mut self) {
+        let Some(light) = self.session.data.take_new_light() else {
+            return;
+        };
+        self.session.add_light(light);
+        self.mode = Mode::Normal;
+    }
+
+    /// Starts a fight against the last encounter, or ends the one in progress.
+    fn toggle_combat(&mut self) {
+        if !self.session.end_combat() {
+            let monsters = self.session.data.last_encounter_foes();
+            self.session.start_combat(monsters);
+        }
+    }
+
+    /// Steps through the preset danger levels. A custom level steps back to the first preset.
+    fn cycle_danger_level(&mut self) {
+        let presets = DangerLevel::PRESETS;
+        let next = presets
+            .iter()
+            .position(|level| *level == self.session.data.danger_level)
+            .map_or(presets[0], |index| presets[(index + 1) % presets.len()]);
+        self.session.set_danger_level(next);
+    }
+
+    fn save(&mut self) {
+        self.status = Some(match &self.save_path {
+            Some(path) => match write_save(path, &self.session.data) {
+                Ok(()) => format!("Saved to {}", path.display()),
+                Err(e) => format!("Failed to save the game: {}", e),
+            },
+            None => "No save file given on the command line".to_string(),
+        });
+    }
+}
+
+/// The party member after the new light's current owner, going back to nobody after the last.
+fn next_owner(data: &ShadowtrackData) -> Option<u32> {
+    let next = match data.new_light_owner {
+        Some(id) => data
+            .characters
+            .iter()
+            .position(|character| character.id == id)
+            .map_or(0, |index| index + 1),
+        None => 0,
+    };
+    data.characters.get(next).map(|character| character.id)
+}
@@ -0,0 +1,48 @@
+use crate::app::TuiApp;
+use crossterm::event::{self, Event};
+use shadowtrack_core::save::load_save;
+use shadowtrack_core::session::Session;
+use shadowtrack_core::state::ShadowtrackData;
+use std::io;
+use std::path::PathBuf;
+use std::time::Duration;
+
+mod app;
+mod ui;
+
+fn main() -> io::Result<()> {
+    // Optional save file: loaded if it exists, and written back with [s].
+    let save_path = std::env::args().nth(1).map(PathBuf::from);
+    let data = match &save_path {
+        Some(path) if path.exists() => match load_save(path) {
+            Ok(data) => data,
+            Err(e) => {
+                eprintln!("Failed to load game: {}", e);
+                return Ok(());
+            }
+        },
+        _ => ShadowtrackData::default(),
+    };
+    let mut app = TuiApp::new(Session::from_data(data), save_path);
+
+    let mut terminal = ratatui::init();
+    let result = run(&mut terminal, &mut app);
+    ratatui::restore();
+    result
+}
+
+fn run(terminal: &mut ratatui::DefaultTerminal, app: &mut TuiApp) -> io::Result<()> {
+    while !app.should_quit {
+        app.handle_clock_tick();
+        terminal.draw(|frame| ui::draw(frame, app))?;
+        if event::poll(Duration::from_millis(250))? {
+            if let Event::Key(key) = event::read()? {
+                app.handle_key(key);
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,158 @@
+#[cfg(test)]
+mod tui {
+    use crate::app::{AddLightField, Mode, TuiApp};
+    use crossterm::event::{KeyCode, KeyEvent};
+    use shadowtrack_core::session::Session;
+    use shadowtrack_core::state::{DangerLevel, LightSourceType};
+
+    fn press(app: &mut TuiApp, code: KeyCode) {
+        app.handle_key(KeyEvent::from(code));
+    }
+
+    fn type_text(app: &mut TuiApp, text: &str) {
+        for c in text.chars() {
+            press(app, KeyCode::Char(c));
+        }
+    }
+
+    #[test]
+    fn clock_keys_drive_the_session() {
+        let mut app = TuiApp::new(Session::default(), None);
+
+        press(&mut app, KeyCode::Char(' '));
+        assert!(app.session.clock_running);
+
+        press(&mut app, KeyCode::Char('5'));
+        press(&mut app, KeyCode::Char('0'));
+        assert_eq!(app.session.data.clock_elapsed, 900);
+
+        press(&mut app, KeyCode::Char('r'));
+        assert!(!app.session.clock_running);
+        assert_eq!(app.session.data.clock_elapsed, 0);
+    }
+
+    #[test]
+    fn forced_rolls_write_to_the_log() {
+        let mut app = TuiApp::new(Session::default(), None);
+
+        press(&mut app, KeyCode::Char('e'));
+        press(&mut app, KeyCode::Char('t'));
+
+        let events = &app.session.data.event_log[0].events;
+        assert_eq!(events.len(), 2);
+        assert!(events[0].text.starts_with("!ENCOUNTER! - "));
+    }
+
+    #[test]
+    fn danger_key_cycles_presets() {
+        let mut app = TuiApp::new(Session::default(), None);
+        assert_eq!(app.session.data.danger_level, DangerLevel::Deadly);
+
+        press(&mut app, KeyCode::Char('d'));
+        assert_eq!(app.session.data.danger_level, DangerLevel::Safe);
+        press(&mut app, KeyCode::Char('d'));
+        assert_eq!(app.session.data.danger_level, DangerLevel::Unsafe);
+    }
+
+    #[test]
+    fn add_light_popup_adds_a_spell() {
+        let mut app = TuiApp::new(Session::default(), None);
+
+        press(&mut app, KeyCode::Char('a'));
+        assert_eq!(app.mode, Mode::AddLight(AddLightField::Label));
+
+        // Typing in the popup must not trigger normal-mode keys.
+        type_text(&mut app, "Aloona ");
+        press(&mut app, KeyCode::Up);
+        press(&mut app, KeyCode::Tab);
+        type_text(&mut app, "Light");
+        press(&mut app, KeyCode::Right);
+        press(&mut app, KeyCode::PageUp);
+        press(&mut app, KeyCode::Enter);
+
+        assert_eq!(app.mode, Mode::Normal);
+        assert!(!app.session.clock_running);
+        let light = &app.session.data.light_sources[0];
+        assert_eq!(light.label, "Aloona ");
+        assert_eq!(
+            light.light_type,
+            LightSourceType::Spell("Light".to_string())
+        );
+        assert_eq!(light.radius_feet, 35);
+        assert_eq!(light.minutes_remaining, 70);
+    }
+
+    #[test]
+    fn add_light_popup_picks_an_owner_from_the_party() {
+        let mut app = TuiApp::new(Session::default(), None);
+        let ginny = app.session.data.add_character("Ginny");
+
+        press(&mut app, KeyCode::Char('a'));
+        press(&mut app, KeyCode::F(3));
+        press(&mut app, KeyCode::F(3));
+        assert_eq!(app.session.data.new_light_owner, None);
+        press(&mut app, KeyCode::F(3));
+        assert_eq!(app.session.data.new_light_owner, Some(ginny));
+        press(&mut app, KeyCode::Enter);
+
+        let light = &app.session.data.light_sources[0];
+        assert_eq!(light.owner, Some(ginny));
+        assert_eq!(light.label, "Ginny");
+        assert_eq!(app.session.data.characters.len(), 1);
+    }
+
+    #[test]
+    fn add_light_popup_cycles_templates() {
+        let mut app = TuiApp::new(Session::default(), None);
+
+        press(&mut app, KeyCode::Char('a'));
+        press(&mut app, KeyCode::F(2));
+        press(&mut app, KeyCode::F(2));
+        type_text(&mut app, "Ginny");
+        press(&mut app, KeyCode::Enter);
+
+        let light = &app.session.data.light_sources[0];
+        assert_eq!(app.template, Some(1));
+        assert_eq!(light.light_type, LightSourceType::Lantern);
+        assert_eq!(light.minutes_remaining, 60);
+    }
+
+    #[test]
+    fn add_light_requires_an_owner() {
+        let mut app = TuiApp::new(Session::default(), None);
+
+        press(&mut app, KeyCode::Char('a'));
+        press(&mut app, KeyCode::Enter);
+        assert!(app.session.data.light_sources.is_empty());
+
+        press(&mut app, KeyCode::Esc);
+        assert_eq!(app.mode, Mode::Normal);
+        assert!(!app.should_quit);
+    }
+
+    #[test]
+    fn draws_all_panels() {
+        use ratatui::backend::TestBackend;
+        use ratatui::Terminal;
+
+        let mut app = TuiApp::new(Session::default(), None);
+        press(&mut app, KeyCode::Char('t'));
+        press(&mut app, KeyCode::Char('a'));
+
+        let mut terminal = Terminal::new(TestBackend::new(120, 30)).unwrap();
+        terminal.draw(|frame| crate::ui::draw(frame, &app)).unwrap();
+        let screen: String = terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|cell| cell.symbol())
+            .collect();
+
+        assert!(screen.contains("Game Time: 00:00"));
+        assert!(screen.contains("Turn: 0"));
+        assert!(screen.contains("No active light sources"));
+        assert!(screen.contains("Encounter Table"));
+        assert!(screen.contains("Add Light Source"));
+    }
+}
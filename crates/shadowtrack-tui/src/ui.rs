@@ -0,0 +1,324 @@
+use crate::app::{AddLightField, Mode, TuiApp};
+use num_integer::Integer;
+use ratatui::layout::{Constraint, Flex, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Clear, List, ListItem, Paragraph, Wrap};
+use ratatui::Frame;
+use shadowtrack_core::state::{Illumination, LightSourceType, LightState, Supply};
+use shadowtrack_core::tables::RollTable;
+
+pub fn draw(frame: &mut Frame, app: &TuiApp) {
+    let [top, body, help] = Layout::vertical([
+        Constraint::Length(3),
+        Constraint::Min(0),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+    let [left, center] =
+        Layout::horizontal([Constraint::Percentage(35), Constraint::Percentage(65)]).areas(body);
+    // The party block only shows once there's someone in it or an effect running.
+    let data = &app.session.data;
+    let party_rows = (data.characters.len() + data.effects.len() + data.last_monsters.len()) as u16;
+    let party_height = if party_rows == 0 { 0 } else { party_rows + 2 };
+    let [lights, party, encounter] = Layout::vertical([
+        Constraint::Min(0),
+        Constraint::Length(party_height),
+        Constraint::Length(10),
+    ])
+    .areas(center);
+
+    draw_clock(frame, app, top);
+    draw_log(frame, app, left);
+    draw_lights(frame, app, lights);
+    if party_rows > 0 {
+        draw_party(frame, app, party);
+    }
+    draw_encounter(frame, app, encounter);
+    draw_help(frame, app, help);
+
+    if let Mode::AddLight(field) = app.mode {
+        draw_add_light(frame, app, field);
+    }
+}
+
+fn draw_clock(frame: &mut Frame, app: &TuiApp, area: Rect) {
+    let data = &app.session.data;
+    let (minutes, seconds) = data.clock_elapsed.div_rem(&60);
+    let state = if app.session.clock_running {
+        Span::styled("running", Style::new().fg(Color::Green))
+    } else {
+        Span::styled("stopped", Style::new().fg(Color::DarkGray))
+    };
+    let mut line = Line::from(vec![
+        Span::styled("Game Time: ", Style::new().fg(Color::Red).bold()),
+        Span::styled(format!("{minutes:02}:{seconds:02}"), Style::new().bold()),
+        Span::raw("  ("),
+        state,
+        Span::raw(format!(")  Turn {}  ", data.turn)),
+        Span::styled(
+            format!("Danger: {}", data.danger_level),
+            Style::new().fg(Color::LightRed),
+        ),
+    ]);
+    if let Some(combat) = &data.combat {
+        let turn = combat.current().map_or(String::new(), |combatant| {
+            format!(", {}'s turn", combatant.name)
+        });
+        line.push_span(Span::styled(
+            format!("  COMBAT round {}{}", combat.round, turn),
+            Style::new().fg(Color::Red).bold(),
+        ));
+    }
+    frame.render_widget(Paragraph::new(line).block(Block::bordered()), area);
+}
+
+fn draw_log(frame: &mut Frame, app: &TuiApp, area: Rect) {
+    let log = &app.session.data.event_log;
+    let mut lines = Vec::new();
+    for entry in log.iter().rev() {
+        lines.push(Line::styled(
+            format!("Turn: {}", entry.turn),
+            Style::new().fg(Color::LightYellow).bold(),
+        ));
+        for event in entry.events.iter().rev() {
+            let text = format!("   [{}] {}", event.game_time_label(), event);
+            lines.push(if event.deleted {
+                Line::styled(text, Style::new().add_modifier(Modifier::CROSSED_OUT))
+            } else {
+                Line::raw(text)
+            });
+            if let Some(encounter) = event.detail.as_ref().and_then(|d| d.encounter.as_ref()) {
+                for follow_up in encounter.to_string().lines() {
+                    lines.push(Line::styled(
+                        format!("      {}", follow_up),
+                        Style::new().add_modifier(Modifier::DIM),
+                    ));
+                }
+            }
+        }
+    }
+    if log.is_empty() {
+        lines.push(Line::raw("No events logged"));
+    }
+    frame.render_widget(
+        Paragraph::new(lines)
+            .wrap(Wrap { trim: false })
+            .block(Block::bordered().title(" Log ")),
+        area,
+    );
+}
+
+fn draw_lights(frame: &mut Frame, app: &TuiApp, area: Rect) {
+    let data = &app.session.data;
+    let illumination = data.illumination();
+    let mut block = Block::bordered().title(format!(" Active Light Sources: {} ", illumination));
+    if illumination == Illumination::Darkness {
+        block = block
+            .border_style(Style::new().fg(Color::Red))
+            .title_bottom(
+                Line::styled(" TOTAL DARKNESS ", Style::new().fg(Color::Red).bold()).centered(),
+            );
+    }
+    if data.light_sources.is_empty() {
+        frame.render_widget(
+            Paragraph::new("No active light sources")
+                .style(Style::new().fg(Color::Yellow))
+                .block(block),
+            area,
+        );
+        return;
+    }
+    let items: Vec<ListItem> = data
+        .light_sources
+        .iter()
+        .map(|light| {
+            let state_style = match light.state {
+                LightState::Lit => Style::new(),
+                LightState::Guttering => Style::new().fg(Color::LightRed).bold(),
+                LightState::Extinguished | LightState::Snuffed => Style::new().fg(Color::Cyan),
+                LightState::BurntOut => Style::new().fg(Color::DarkGray),
+            };
+            let mut lines = vec![Line::from(vec![
+                Span::styled(light.label.clone(), Style::new().bold()),
+                Span::raw(format!(
+                    "  {} ({}ft)  Time left: {}  ",
+                    light.light_type,
+                    light.radius_feet,
+                    light
+                        .burn_model(&data.burn_models)
+                        .time_left_label(light.minutes_remaining)
+                )),
+                Span::styled(light.state.to_string(), state_style),
+            ])];
+            if let Some(supply) = Supply::for_light(&light.light_type) {
+                let spare = data.supplies_of(light).count(supply);
+                if spare > 0 {
+                    lines[0].push_span(Span::styled(
+                        format!("  ({} spare)", supply.count_label(spare)),
+                        Style::new().fg(Color::DarkGray),
+                    ));
+                }
+            }
+            if let Some(roll) = light.last_roll {
+                lines.push(Line::styled(
+                    format!("   Last Burn Roll: {}", roll),
+                    Style::new().fg(Color::DarkGray),
+                ));
+            }
+            ListItem::new(lines)
+        })
+        .collect();
+    frame.render_widget(List::new(items).block(block), area);
+}
+
+fn draw_party(frame: &mut Frame, app: &TuiApp, area: Rect) {
+    let data = &app.session.data;
+    let mut items: Vec<ListItem> = data
+        .characters
+        .iter()
+        .map(|character| {
+            let style = if character.is_down() {
+                Style::new().fg(Color::Red)
+            } else {
+                Style::new()
+            };
+            let mut line = Line::styled(character.to_string(), style);
+            if !character.supplies.is_empty() {
+                line.push_span(Span::styled(
+                    format!("  Spares: {}", character.supplies),
+                    Style::new().fg(Color::DarkGray),
+                ));
+            }
+            ListItem::new(line)
+        })
+        .collect();
+    items.extend(data.effects.iter().map(|effect| {
+        ListItem::new(Line::from(vec![
+            Span::styled(effect.name.clone(), Style::new().fg(Color::LightGreen)),
+            Span::raw(format!(
+                " on {}, {} left",
+                data.effect_target_name(effect.target),
+                effect.time_left_label()
+            )),
+        ]))
+    }));
+    // The last encounter's monsters, with the HP they have left.
+    items.extend(data.last_monsters.iter().map(|group| {
+        let color = if group.fled {
+            Color::DarkGray
+        } else {
+            Color::LightRed
+        };
+        ListItem::new(Line::styled(group.to_string(), Style::new().fg(color)))
+    }));
+    frame.render_widget(
+        List::new(items).block(Block::bordered().title(" Party ")),
+        area,
+    );
+}
+
+fn draw_encounter(frame: &mut Frame, app: &TuiApp, area: Rect) {
+    let data = &app.session.data;
+    let [encounters, torch_events] =
+        Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)]).areas(area);
+    let table = |table: &RollTable| -> Vec<ListItem> {
+        table
+            .entries
+            .iter()
+            .map(|entry| ListItem::new(format!("{:>5}  {}", entry.range_label(), entry.text)))
+            .collect()
+    };
+    frame.render_widget(
+        List::new(table(&data.encounter_table)).block(Block::bordered().title(format!(
+            " Encounter Table ({}) [e] roll ",
+            data.encounter_table.dice
+        ))),
+        encounters,
+    );
+    frame.render_widget(
+        List::new(table(&data.torch_event_table)).block(Block::bordered().title(format!(
+            " Torch Event Table ({}) [t] roll ",
+            data.torch_event_table.dice
+        ))),
+        torch_events,
+    );
+}
+
+fn draw_help(frame: &mut Frame, app: &TuiApp, area: Rect) {
+    let text = match (&app.mode, &app.status) {
+        (Mode::Normal, Some(status)) => status.clone(),
+        (Mode::Normal, None) => {
+            "[space] start/stop  [r] reset  [1/5/0] +1/+5/+10 min  [a] add light  [e] encounter  [t] torch event  [d] danger  [x] remove spent  [c] combat  [n] next turn  [i] initiative  [s] save  [q] quit"
+                .to_string()
+        }
+        (Mode::AddLight(_), _) => {
+            "[f2] template  [f3] owner  [up/down] type  [left/right] radius  [pgup/pgdn] minutes  [tab] spell name  [enter] add  [esc] cancel"
+                .to_string()
+        }
+    };
+    frame.render_widget(
+        Paragraph::new(text).style(Style::new().add_modifier(Modifier::DIM)),
+        area,
+    );
+}
+
+fn draw_add_light(frame: &mut Frame, app: &TuiApp, field: AddLightField) {
+    let data = &app.session.data;
+    let [area] = Layout::horizontal([Constraint::Length(50)])
+        .flex(Flex::Center)
+        .areas(frame.area());
+    let [area] = Layout::vertical([Constraint::Length(8)])
+        .flex(Flex::Center)
+        .areas(area);
+
+    let focused = |f: AddLightField| {
+        if f == field {
+            Style::new().fg(Color::Yellow).bold()
+        } else {
+            Style::new()
+        }
+    };
+    let template = app
+        .template
+        .and_then(|index| data.light_templates.get(index))
+        .map_or("none", |template| template.name.as_str());
+    let burn = data
+        .new_light_burn
+        .as_ref()
+        .unwrap_or_else(|| data.burn_models.for_type(&data.new_light_type));
+    let mut lines = vec![
+        Line::styled(
+            format!("Template: {}", template),
+            Style::new().add_modifier(Modifier::DIM),
+        ),
+        match data.new_light_owner.and_then(|id| data.character(id)) {
+            Some(character) => Line::raw(format!("Owner:  {}", character.name)),
+            None => Line::from(vec![
+                Span::raw("Label:  "),
+                Span::styled(
+                    format!("{}_", data.new_light_label),
+                    focused(AddLightField::Label),
+                ),
+            ]),
+        },
+        Line::raw(format!("Type:   {}", data.new_light_type)),
+        Line::raw(format!("Radius: {}ft", data.new_light_range)),
+        Line::raw(format!("Time:   {} min ({})", data.new_light_minutes, burn)),
+    ];
+    if let LightSourceType::Spell(name) = &data.new_light_type {
+        lines.insert(
+            3,
+            Line::from(vec![
+                Span::raw("Spell:  "),
+                Span::styled(format!("{}_", name), focused(AddLightField::Spell)),
+            ]),
+        );
+    }
+
+    frame.render_widget(Clear, area);
+    frame.render_widget(
+        Paragraph::new(lines).block(Block::bordered().title(" Add Light Source ")),
+        area,
+    );
+}
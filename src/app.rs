@@ -1,37 +1,69 @@
-use crate::app::rng::DefaultRandomSource;
-use crate::app::save::{load_from_file, save_to_file};
-use crate::app::state::ShadowtrackData;
-use crate::app::systems::{process_light_burn, roll_encounter, roll_light_event};
+use crate::app::save::{export_recap, load_from_file, save_to_file};
+use crate::app::ui::autosave::{draw_autosave_menu, draw_restore_window};
 use crate::app::ui::clock::{draw_clock, draw_clock_controls};
-use crate::app::ui::log::draw_log_ui;
-use crate::app::ui::{encounter::draw_encounter_ui, lights::draw_torch_ui};
+use crate::app::ui::combat::draw_combat_ui;
+use crate::app::ui::effects::draw_effects_ui;
+use crate::app::ui::encounter::{draw_danger_level_switcher, draw_encounter_ui};
+use crate::app::ui::lights::{draw_darkness_banner, draw_torch_ui};
+use crate::app::ui::log::{draw_log_ui, LogEditor};
+use crate::app::ui::roster::draw_roster_ui;
+use crate::app::ui::table::draw_named_tables_ui;
+use crate::app::ui::templates::draw_light_templates_ui;
 use eframe::{egui, App};
-use log::debug;
-use num_integer::Integer;
+use shadowtrack_core::event_log::LogFilter;
+use shadowtrack_core::recap::{Recap, RecapFormat};
+use shadowtrack_core::save::autosave::{app_data_dir, Autosaver};
+use shadowtrack_core::session::Session;
+use shadowtrack_core::state::{EffectTarget, EffectUnit, ShadowtrackData, TimedEffect};
+use std::collections::HashMap;
 use std::time::{Duration, Instant};
 
-pub mod rng;
 pub mod save;
-pub mod state;
-pub mod systems;
 pub mod ui;
 
 pub struct ShadowtrackApp {
     pub show_add_light_modal: bool,
-    pub data: ShadowtrackData,
-    pub clock_running: bool,
+    pub dice_expression: String,
+    pub last_dice_error: Option<String>,
+    pub new_character_name: String,
+    /// Comma separated monsters for the next fight.
+    pub combat_monsters: String,
+    /// How much the monster stat cards' Hit and Heal buttons change HP by.
+    pub monster_damage: i64,
+    /// Condition being typed on each character's card, by character id.
+    pub new_conditions: HashMap<u32, String>,
+    /// Effect being set up in the timed effects form.
+    pub new_effect: TimedEffect,
+    /// Which events the log panel shows.
+    pub log_filter: LogFilter,
+    /// Notes, edits and tags being written in the log panel.
+    pub log_editor: LogEditor,
+    pub session: Session,
     pub last_tick: Instant,
-    pub rng: DefaultRandomSource,
+    /// Autosaves to the app data directory, if there is one.
+    pub autosaver: Option<Autosaver>,
+    /// The autosave of a session that didn't shut down cleanly, held until the GM restores or
+    /// discards it.
+    pub recovered: Option<ShadowtrackData>,
 }
 
 impl Default for ShadowtrackApp {
     fn default() -> Self {
         Self {
             show_add_light_modal: false,
-            data: ShadowtrackData::default(),
-            clock_running: false,
+            dice_expression: "2d6".to_string(),
+            last_dice_error: None,
+            new_character_name: String::new(),
+            combat_monsters: String::new(),
+            monster_damage: 1,
+            new_conditions: HashMap::new(),
+            new_effect: TimedEffect::new("", EffectTarget::Party, EffectUnit::Turns, 6),
+            log_filter: LogFilter::default(),
+            log_editor: LogEditor::default(),
+            session: Session::default(),
             last_tick: Instant::now(),
-            rng: DefaultRandomSource,
+            autosaver: None,
+            recovered: None,
         }
     }
 }
@@ -39,12 +71,13 @@ impl Default for ShadowtrackApp {
 impl App for ShadowtrackApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         self.handle_clock_tick();
+        draw_restore_window(self, ctx);
         egui::TopBottomPanel::top("top_menu").show(ctx, |ui| {
             egui::menu::bar(ui, |ui| {
                 ui.menu_button("Game State", |ui| {
                     if ui.button("Save").clicked() {
                         ui.close_menu();
-                        if let Err(e) = save_to_file(&self.data) {
+                        if let Err(e) = save_to_file(&self.session.data) {
                             eprintln!("Failed to save the game: {}", e);
                         }
                     }
@@ -52,11 +85,32 @@ impl App for ShadowtrackApp {
                     if ui.button("Load").clicked() {
                         ui.close_menu();
                         match load_from_file() {
-                            Ok(data) => self.data = data,
+                            Ok(data) => self.session.load(data),
                             Err(e) => eprintln!("Failed to load game: {}", e),
                         }
                     }
 
+                    ui.menu_button("Export Recap", |ui| {
+                        for format in RecapFormat::ALL {
+                            if ui.button(format.to_string()).clicked() {
+                                ui.close_menu();
+                                if let Err(e) = export_recap(&self.session.data, format) {
+                                    eprintln!("Failed to export the recap: {}", e);
+                                }
+                            }
+                        }
+                        ui.separator();
+                        if ui.button("Copy as Markdown").clicked() {
+                            ui.close_menu();
+                            let recap = Recap::new(&self.session.data);
+                            ui.ctx().copy_text(recap.render(RecapFormat::Markdown));
+                        }
+                    });
+
+                    ui.menu_button("Autosave", |ui| {
+                        draw_autosave_menu(self, ui);
+                    });
+
                     if ui.button("Reset").clicked() {
                         ui.close_menu();
                         self.reset();
@@ -64,6 +118,8 @@ impl App for ShadowtrackApp {
                 });
                 ui.separator();
                 draw_clock_controls(self, ui);
+                ui.separator();
+                draw_danger_level_switcher(self, ui);
             });
         });
 
@@ -71,7 +127,7 @@ impl App for ShadowtrackApp {
             .resizable(true)
             .default_width(250.0)
             .width_range(180.0..=500.0)
-            .show(&ctx, |ui| {
+            .show(ctx, |ui| {
                 ctx.request_repaint();
                 draw_clock(self, ui);
                 ui.separator();
@@ -79,28 +135,64 @@ impl App for ShadowtrackApp {
                     draw_log_ui(self, ui);
                 });
             });
-        egui::CentralPanel::default().show(&ctx, |ui| {
+        egui::SidePanel::right("roster_panel")
+            .resizable(true)
+            .default_width(250.0)
+            .width_range(180.0..=500.0)
+            .show(ctx, |ui| {
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    draw_roster_ui(self, ui);
+                });
+            });
+        egui::CentralPanel::default().show(ctx, |ui| {
+            draw_darkness_banner(self, ui);
+            draw_combat_ui(self, ui);
             draw_torch_ui(self, ui);
+            draw_effects_ui(self, ui);
+            draw_light_templates_ui(self, ui);
             draw_encounter_ui(self, ui);
+            draw_named_tables_ui(self, ui);
         });
     }
-}
 
-impl ShadowtrackApp {
-    fn reset(&mut self) {
-        *self = Self::default();
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        // Leave an unanswered recovery marked as unclean so it's offered again next time.
+        if self.recovered.is_some() {
+            return;
+        }
+        if let Some(autosaver) = &mut self.autosaver {
+            if let Err(e) = autosaver.finish(&self.session.data) {
+                eprintln!("Failed to autosave: {}", e);
+            }
+        }
     }
+}
 
-    pub fn toggle_clock(&mut self) {
-        self.clock_running = !self.clock_running;
+impl ShadowtrackApp {
+    /// A new app autosaving to the app data directory, holding on to the newest autosave there
+    /// that loads if that session didn't shut down cleanly.
+    pub fn with_autosave() -> Self {
+        let mut app = Self::default();
+        let Some(mut autosaver) = app_data_dir().map(Autosaver::new) else {
+            return app;
+        };
+        if autosaver.unclean_shutdown() {
+            match autosaver.load_newest() {
+                Ok(data) => app.recovered = Some(data),
+                Err(e) => eprintln!("Failed to load the autosave: {}", e),
+            }
+        }
+        if let Err(e) = autosaver.start(&app.session.data) {
+            eprintln!("Failed to start autosaving: {}", e);
+        }
+        app.autosaver = Some(autosaver);
+        app
     }
 
-    pub fn advance_clock_secs(&mut self, secs: u64) {
-        self.data.clock_elapsed += secs;
-        debug!(
-            "Advanced Clock: {secs} Seconds\nNew Value: {}",
-            self.data.clock_elapsed
-        );
+    fn reset(&mut self) {
+        let autosaver = self.autosaver.take();
+        *self = Self::default();
+        self.autosaver = autosaver;
     }
 
     #[inline]
@@ -117,28 +209,14 @@ impl ShadowtrackApp {
         // Tick every second
         if let Some(secs) = self.should_tick(Instant::now()) {
             self.last_tick += Duration::from_secs(secs);
-
-            if self.clock_running {
-                // Advance game clock
-                self.data.clock_elapsed += secs;
-                let (elapsed_minutes, _seconds) = self.data.clock_elapsed.div_rem(&60);
-                match self.data.next_process_minutes {
-                    Some(next_minutes) if elapsed_minutes >= next_minutes => {
-                        self.data.turn += 1;
-
-                        process_light_burn(&mut self.data, &mut self.rng);
-                        roll_light_event(&mut self.data, &mut self.rng);
-                        roll_encounter(&mut self.data, &mut self.rng, false);
-
-                        self.data.next_process_minutes =
-                            Some(elapsed_minutes + self.data.process_interval_minutes);
-                    }
-                    None => {
-                        // First activation of process timer, we need to set a value to check against.
-                        // TODO: Implement user-configurable value for process interval.
-                        self.data.next_process_minutes = Some(self.data.process_interval_minutes);
-                    }
-                    _ => { /* do nothing */ }
+            self.session.tick(secs);
+            // Don't overwrite the autosave of a crashed session before it's been offered back.
+            if self.recovered.is_some() {
+                return;
+            }
+            if let Some(autosaver) = &mut self.autosaver {
+                if let Err(e) = autosaver.tick(&self.session.data, secs) {
+                    eprintln!("Failed to autosave: {}", e);
                 }
             }
         }
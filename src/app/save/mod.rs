@@ -1,69 +1,94 @@
-use crate::app::state::ShadowtrackData;
 use rfd::FileDialog;
-use serde_json;
-use std::fs::{read_to_string, write};
-use std::path::PathBuf;
-use std::{fmt, io};
+use shadowtrack_core::bestiary::Monster;
+use shadowtrack_core::recap::RecapFormat;
+use shadowtrack_core::save::{
+    load_bestiary, load_light_library, load_save, write_bestiary, write_light_library, write_recap,
+    write_save, SaveError,
+};
+use shadowtrack_core::state::{LightTemplate, ShadowtrackData};
+use std::io;
 
 const DEFAULT_SAVE_FILE: &str = "save.json";
+const DEFAULT_LIGHT_LIBRARY_FILE: &str = "light_templates.json";
+const DEFAULT_BESTIARY_FILE: &str = "bestiary.json";
+const DEFAULT_RECAP_FILE: &str = "recap";
 
-#[derive(Debug)]
-pub enum SaveError {
-    Serialization(serde_json::Error),
-    Io(io::Error),
-}
-
-impl fmt::Display for SaveError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            SaveError::Serialization(e) => write!(f, "Serialization error: {}", e),
-            SaveError::Io(e) => write!(f, "I/O error: {}", e),
-        }
+pub fn save_to_file(game_data: &ShadowtrackData) -> Result<(), SaveError> {
+    if let Some(save_file) = FileDialog::new()
+        .set_title("Save game data to?")
+        .set_file_name(DEFAULT_SAVE_FILE)
+        .set_directory("./")
+        .save_file()
+    {
+        write_save(&save_file, game_data)?;
     }
+    Ok(())
 }
 
-impl From<serde_json::Error> for SaveError {
-    fn from(err: serde_json::Error) -> Self {
-        SaveError::Serialization(err)
+pub fn export_recap(game_data: &ShadowtrackData, format: RecapFormat) -> Result<(), SaveError> {
+    let extension = format.extension();
+    if let Some(recap_file) = FileDialog::new()
+        .set_title("Save session recap to?")
+        .add_filter(format.to_string(), &[extension])
+        .set_file_name(format!("{}.{}", DEFAULT_RECAP_FILE, extension))
+        .set_directory("./")
+        .save_file()
+    {
+        write_recap(&recap_file, game_data, format)?;
     }
+    Ok(())
 }
 
-impl From<io::Error> for SaveError {
-    fn from(err: io::Error) -> Self {
-        SaveError::Io(err)
-    }
+pub fn load_from_file() -> Result<ShadowtrackData, SaveError> {
+    let save_file = FileDialog::new()
+        .add_filter("text", &["txt", "json"])
+        .set_directory("./")
+        .pick_file()
+        .ok_or_else(|| SaveError::Io(io::Error::other("No file selected")))?;
+
+    load_save(&save_file)
 }
 
-pub fn write_save(save_file: &PathBuf, game_data: &ShadowtrackData) -> Result<(), SaveError> {
-    let save_data = serde_json::to_string_pretty(game_data)?;
-    write(save_file, save_data)?;
+pub fn export_light_library(templates: &[LightTemplate]) -> Result<(), SaveError> {
+    if let Some(library_file) = FileDialog::new()
+        .set_title("Save light templates to?")
+        .set_file_name(DEFAULT_LIGHT_LIBRARY_FILE)
+        .set_directory("./")
+        .save_file()
+    {
+        write_light_library(&library_file, templates)?;
+    }
     Ok(())
 }
 
-pub fn load_save(save_file: &PathBuf) -> Result<ShadowtrackData, SaveError> {
-    let content = read_to_string(save_file)?;
-    let data = serde_json::from_str(&content)?;
-    Ok(data)
+pub fn import_light_library() -> Result<Vec<LightTemplate>, SaveError> {
+    let library_file = FileDialog::new()
+        .add_filter("text", &["txt", "json"])
+        .set_directory("./")
+        .pick_file()
+        .ok_or_else(|| SaveError::Io(io::Error::other("No file selected")))?;
+
+    load_light_library(&library_file)
 }
 
-pub fn save_to_file(game_data: &ShadowtrackData) -> Result<(), SaveError> {
-    if let Some(save_file) = FileDialog::new()
-        .set_title("Save game data to?")
-        .set_file_name(DEFAULT_SAVE_FILE)
+pub fn export_bestiary(monsters: &[Monster]) -> Result<(), SaveError> {
+    if let Some(bestiary_file) = FileDialog::new()
+        .set_title("Save bestiary to?")
+        .set_file_name(DEFAULT_BESTIARY_FILE)
         .set_directory("./")
         .save_file()
     {
-        write_save(&save_file, game_data)?;
+        write_bestiary(&bestiary_file, monsters)?;
     }
     Ok(())
 }
 
-pub fn load_from_file() -> Result<ShadowtrackData, SaveError> {
-    let save_file = FileDialog::new()
+pub fn import_bestiary() -> Result<Vec<Monster>, SaveError> {
+    let bestiary_file = FileDialog::new()
         .add_filter("text", &["txt", "json"])
         .set_directory("./")
         .pick_file()
-        .ok_or_else(|| SaveError::Io(io::Error::new(io::ErrorKind::Other, "No file selected")))?;
+        .ok_or_else(|| SaveError::Io(io::Error::other("No file selected")))?;
 
-    load_save(&save_file)
+    load_bestiary(&bestiary_file)
 }
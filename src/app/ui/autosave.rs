@@ -0,0 +1,80 @@
+use crate::app::ShadowtrackApp;
+use egui::{DragValue, Ui};
+use shadowtrack_core::event_log::format_clock;
+
+/// Autosave settings, shown in the Game State menu. Changes are kept for the next time the app
+/// starts.
+pub fn draw_autosave_menu(app: &mut ShadowtrackApp, ui: &mut Ui) {
+    let Some(autosaver) = &mut app.autosaver else {
+        ui.label("No app data directory to autosave to");
+        return;
+    };
+    let settings = &mut autosaver.settings;
+    let mut changed = ui.checkbox(&mut settings.enabled, "Autosave").changed();
+    ui.add_enabled_ui(settings.enabled, |ui| {
+        ui.horizontal(|ui| {
+            ui.label("Every");
+            let mut minutes = settings.interval_secs / 60;
+            if ui
+                .add(DragValue::new(&mut minutes).range(1..=60).suffix(" min"))
+                .changed()
+            {
+                settings.interval_secs = minutes * 60;
+                changed = true;
+            }
+        });
+        changed |= ui
+            .checkbox(&mut settings.every_turn, "And every processed turn")
+            .changed();
+        ui.horizontal(|ui| {
+            ui.label("Keep");
+            changed |= ui
+                .add(DragValue::new(&mut settings.backups).range(0..=20))
+                .changed();
+            ui.label("backups");
+        });
+    });
+    ui.label(format!("Saving to {}", autosaver.dir().display()));
+    if changed {
+        if let Err(e) = autosaver.save_settings() {
+            eprintln!("Failed to save the autosave settings: {}", e);
+        }
+    }
+}
+
+/// Offers the autosave left by a session that didn't shut down cleanly.
+pub fn draw_restore_window(app: &mut ShadowtrackApp, ctx: &egui::Context) {
+    let Some(recovered) = &app.recovered else {
+        return;
+    };
+    let mut restore = None;
+    egui::Window::new("Restore Session?")
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+        .show(ctx, |ui| {
+            ui.label("Shadowtrack didn't shut down cleanly last time.");
+            ui.label(format!(
+                "Restore the autosave from turn {}, game time {}?",
+                recovered.turn,
+                format_clock(recovered.clock_elapsed)
+            ));
+            ui.horizontal(|ui| {
+                if ui.button("Restore").clicked() {
+                    restore = Some(true);
+                }
+                if ui.button("Start Fresh").clicked() {
+                    restore = Some(false);
+                }
+            });
+        });
+    match restore {
+        Some(true) => {
+            if let Some(data) = app.recovered.take() {
+                app.session.load(data);
+            }
+        }
+        Some(false) => app.recovered = None,
+        None => {}
+    }
+}
@@ -0,0 +1,139 @@
+use crate::app::save::{export_bestiary, import_bestiary};
+use crate::app::ui::table::draw_dice_field;
+use crate::app::ShadowtrackApp;
+use egui::{Color32, DragValue, RichText, Ui};
+use shadowtrack_core::bestiary::{Monster, MonsterGroup};
+
+/// Editor for the monsters encounter tables can reference as `{{Name}}`.
+pub fn draw_bestiary_ui(app: &mut ShadowtrackApp, ui: &mut Ui) {
+    ui.collapsing("📖 Bestiary", |ui| {
+        ui.label("Reference a monster from an encounter entry as {{Name}}.");
+        let data = &mut app.session.data;
+        let mut remove = None;
+        for (index, monster) in data.bestiary.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                ui.label("Name:");
+                ui.text_edit_singleline(&mut monster.name);
+                if ui.small_button("🗑").clicked() {
+                    remove = Some(index);
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.label("Appearing");
+                draw_dice_field(
+                    ui,
+                    ("monster_appearing", index),
+                    &mut monster.number_appearing,
+                );
+                ui.label("HP");
+                draw_dice_field(ui, ("monster_hp", index), &mut monster.hp);
+                ui.label("AC");
+                ui.add(DragValue::new(&mut monster.ac).range(0..=30));
+                ui.label("Morale");
+                ui.add(DragValue::new(&mut monster.morale).range(2..=20));
+            });
+            ui.horizontal(|ui| {
+                ui.label("Attacks:");
+                ui.add(
+                    egui::TextEdit::singleline(&mut monster.attacks).hint_text("1 club +0 (1d4)"),
+                );
+            });
+            ui.horizontal(|ui| {
+                ui.label("Notes:");
+                ui.text_edit_singleline(&mut monster.notes);
+            });
+            ui.separator();
+        }
+        if let Some(index) = remove {
+            data.bestiary.remove(index);
+        }
+        let mut import = false;
+        ui.horizontal(|ui| {
+            if ui.button("Add Monster").clicked() {
+                data.bestiary.push(Monster::new("New Monster"));
+            }
+            if ui.button("Export Bestiary…").clicked() {
+                if let Err(e) = export_bestiary(&data.bestiary) {
+                    eprintln!("Failed to export bestiary: {}", e);
+                }
+            }
+            import = ui.button("Import Bestiary…").clicked();
+        });
+        if import {
+            match import_bestiary() {
+                Ok(imported) => data.import_monsters(imported),
+                Err(e) => eprintln!("Failed to import bestiary: {}", e),
+            }
+        }
+    });
+}
+
+/// Something done to the last encounter's monsters from their stat cards, applied once the
+/// cards have been drawn.
+pub enum MonsterAction {
+    Damage(usize, usize, i64),
+    Leader(usize, usize),
+    Morale(usize),
+}
+
+/// A compact stat card for creatures an encounter turned up, with their HP to hit or heal by
+/// `amount`.
+pub fn draw_stat_card(
+    ui: &mut Ui,
+    group_index: usize,
+    group: &MonsterGroup,
+    amount: i64,
+    action: &mut Option<MonsterAction>,
+) {
+    let monster = &group.monster;
+    egui::Frame::group(ui.style()).show(ui, |ui| {
+        ui.vertical(|ui| {
+            ui.horizontal(|ui| {
+                ui.label(
+                    RichText::new(format!("{} × {}", group.hp.len(), monster.name))
+                        .color(Color32::LIGHT_RED)
+                        .strong(),
+                );
+                ui.label(format!("AC {}", monster.ac));
+                ui.label(format!("MOR {}", monster.morale));
+                if group.fled {
+                    ui.label(RichText::new("Fled").color(Color32::YELLOW));
+                }
+            });
+            if !monster.attacks.is_empty() {
+                ui.label(format!("ATK {}", monster.attacks));
+            }
+            if !monster.notes.is_empty() {
+                ui.label(RichText::new(&monster.notes).italics());
+            }
+            egui::Grid::new(("creatures", group_index)).show(ui, |ui| {
+                for index in 0..group.hp.len() {
+                    let leader = group.leader == Some(index);
+                    let mut name = RichText::new(group.creature_name(index));
+                    if group.is_out(index) {
+                        name = name.strikethrough().color(Color32::GRAY);
+                    }
+                    ui.label(name);
+                    ui.label(group.hp_label(index));
+                    if ui.small_button("Hit").clicked() {
+                        *action = Some(MonsterAction::Damage(group_index, index, amount));
+                    }
+                    if ui.small_button("Heal").clicked() {
+                        *action = Some(MonsterAction::Damage(group_index, index, -amount));
+                    }
+                    if ui
+                        .selectable_label(leader, "★")
+                        .on_hover_text("Leader: morale is checked when they die")
+                        .clicked()
+                    {
+                        *action = Some(MonsterAction::Leader(group_index, index));
+                    }
+                    ui.end_row();
+                }
+            });
+            if ui.button("Check Morale").clicked() {
+                *action = Some(MonsterAction::Morale(group_index));
+            }
+        });
+    });
+}
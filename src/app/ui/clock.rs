@@ -5,31 +5,29 @@ use num_integer::Integer;
 
 pub fn draw_clock_controls(app: &mut ShadowtrackApp, ui: &mut Ui) {
     ui.horizontal(|ui| {
-        let button_label = if app.clock_running {
+        let button_label = if app.session.clock_running {
             "Stop Clock"
         } else {
             "Start Clock"
         };
 
         if ui.button(button_label).clicked() {
-            app.toggle_clock()
+            app.session.toggle_clock()
         }
 
         if ui.button("Reset Clock").clicked() {
-            app.data.clock_elapsed = 0;
-            app.clock_running = false;
-            app.data.next_process_minutes = None;
+            app.session.reset_clock();
         }
 
         ui.separator();
         if ui.button("+1 min").clicked() {
-            app.advance_clock_secs(60);
+            app.session.advance_clock_secs(60);
         }
         if ui.button("+5 min").clicked() {
-            app.advance_clock_secs(300);
+            app.session.advance_clock_secs(300);
         }
         if ui.button("+10 min").clicked() {
-            app.advance_clock_secs(600);
+            app.session.advance_clock_secs(600);
         }
     });
 }
@@ -37,7 +35,7 @@ pub fn draw_clock_controls(app: &mut ShadowtrackApp, ui: &mut Ui) {
 pub fn draw_clock(app: &mut ShadowtrackApp, ui: &mut Ui) {
     let style = Style::default();
     let mut layout = LayoutJob::default();
-    let (minutes, seconds) = app.data.clock_elapsed.div_rem(&60);
+    let (minutes, seconds) = app.session.data.clock_elapsed.div_rem(&60);
 
     // Timer Label
     RichText::from("Game Time: ")
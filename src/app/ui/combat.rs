@@ -0,0 +1,156 @@
+use crate::app::ui::table::draw_dice_field;
+use crate::app::ShadowtrackApp;
+use egui::{Color32, DragValue, RichText, Ui};
+use shadowtrack_core::state::InitiativeMode;
+
+/// Something done to the fight, applied once it has been drawn.
+enum CombatAction {
+    NextTurn,
+    NextRound,
+    RollInitiative,
+    End,
+}
+
+/// Starts a fight, or runs the one in progress turn by turn.
+pub fn draw_combat_ui(app: &mut ShadowtrackApp, ui: &mut Ui) {
+    let data = &mut app.session.data;
+    // Who can't act, and the HP of monsters rolled from the bestiary.
+    let status: Vec<(bool, String)> = data
+        .combat
+        .iter()
+        .flat_map(|combat| &combat.order)
+        .map(|combatant| {
+            let hp = combatant
+                .creature
+                .and_then(|(group, index)| {
+                    let group = data.last_monsters.get(group)?;
+                    (index < group.hp.len()).then(|| format!("HP {}", group.hp_label(index)))
+                })
+                .unwrap_or_default();
+            (data.is_out_of_fight(combatant), hp)
+        })
+        .collect();
+    let Some(combat) = &mut data.combat else {
+        draw_start_combat(app, ui);
+        return;
+    };
+    let settings = &mut data.combat_settings;
+
+    let mut action = None;
+    egui::Frame::new()
+        .stroke(egui::Stroke::new(2.0, Color32::DARK_RED))
+        .inner_margin(8.0)
+        .show(ui, |ui| {
+            ui.horizontal(|ui| {
+                ui.label(
+                    RichText::new(format!("⚔ Round {}", combat.round))
+                        .color(Color32::RED)
+                        .size(22_f32)
+                        .strong(),
+                );
+                ui.label(
+                    RichText::new("Exploration time is paused")
+                        .italics()
+                        .color(Color32::GRAY),
+                );
+            });
+            egui::Grid::new("initiative_order").show(ui, |ui| {
+                for (index, combatant) in combat.order.iter_mut().enumerate() {
+                    let current = index == combat.current;
+                    ui.label(if current { "▶" } else { "" });
+                    let color = if combatant.is_monster() {
+                        Color32::LIGHT_RED
+                    } else {
+                        Color32::LIGHT_BLUE
+                    };
+                    let (out, hp) = &status[index];
+                    let mut name = RichText::new(&combatant.name).color(color);
+                    if current {
+                        name = name.strong();
+                    }
+                    if *out {
+                        name = name.strikethrough();
+                    }
+                    ui.label(name);
+                    ui.label(hp);
+                    ui.label(
+                        combatant
+                            .initiative
+                            .map_or("-".to_string(), |roll| roll.to_string()),
+                    );
+                    draw_dice_field(ui, ("initiative_dice", index), &mut combatant.dice);
+                    ui.end_row();
+                }
+            });
+            ui.horizontal(|ui| {
+                if ui.button("Next Turn").clicked() {
+                    action = Some(CombatAction::NextTurn);
+                }
+                if ui.button("Next Round").clicked() {
+                    action = Some(CombatAction::NextRound);
+                }
+                if ui.button("🎲 Roll Initiative").clicked() {
+                    action = Some(CombatAction::RollInitiative);
+                }
+                if ui.button("End Combat").clicked() {
+                    action = Some(CombatAction::End);
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.label("Initiative:");
+                egui::ComboBox::from_id_salt("initiative_mode")
+                    .selected_text(settings.initiative.to_string())
+                    .show_ui(ui, |ui| {
+                        for mode in InitiativeMode::ALL {
+                            ui.selectable_value(&mut settings.initiative, mode, mode.to_string());
+                        }
+                    });
+                ui.separator();
+                ui.label("Each round takes");
+                ui.add(
+                    DragValue::new(&mut settings.round_seconds)
+                        .range(1..=600)
+                        .suffix(" sec"),
+                );
+            });
+        });
+    ui.add_space(4.0);
+
+    match action {
+        Some(CombatAction::NextTurn) => _ = app.session.next_turn(),
+        Some(CombatAction::NextRound) => _ = app.session.next_round(),
+        Some(CombatAction::RollInitiative) => _ = app.session.roll_initiative(),
+        Some(CombatAction::End) => _ = app.session.end_combat(),
+        None => {}
+    }
+}
+
+/// One click to fight the last encounter, or a list of monsters to fight.
+fn draw_start_combat(app: &mut ShadowtrackApp, ui: &mut Ui) {
+    let mut monsters = None;
+    ui.horizontal(|ui| {
+        if let Some(encounter) = &app.session.data.last_encounter {
+            if ui
+                .button(format!("⚔ Fight {}", encounter))
+                .on_hover_text("Start combat against the last encounter and roll initiative")
+                .clicked()
+            {
+                monsters = Some(app.session.data.last_encounter_foes());
+            }
+            ui.separator();
+        }
+        ui.label("Monsters:");
+        ui.add(
+            egui::TextEdit::singleline(&mut app.combat_monsters)
+                .hint_text("Goblin, Goblin, Hobgoblin"),
+        );
+        if ui.button("⚔ Start Combat").clicked() {
+            monsters = Some(app.combat_monsters.split(',').map(str::to_string).collect());
+            app.combat_monsters.clear();
+        }
+    });
+    if let Some(monsters) = monsters {
+        app.session.start_combat(monsters);
+    }
+    ui.separator();
+}
@@ -0,0 +1,88 @@
+use crate::app::ShadowtrackApp;
+use egui::{Color32, DragValue, RichText, Ui};
+use shadowtrack_core::state::{EffectTarget, EffectUnit};
+
+/// Running spells, poisons, blessings and the like, with a form to start a new one.
+pub fn draw_effects_ui(app: &mut ShadowtrackApp, ui: &mut Ui) {
+    ui.collapsing("⏳ Timed Effects", |ui| {
+        let data = &app.session.data;
+        let mut end = None;
+        if data.effects.is_empty() {
+            ui.label(RichText::new("No effects running").italics());
+        }
+        for (index, effect) in data.effects.iter().enumerate() {
+            ui.horizontal(|ui| {
+                ui.label(RichText::new(&effect.name).color(Color32::LIGHT_GREEN));
+                ui.label(format!("on {}", data.effect_target_name(effect.target)));
+                ui.label(format!("{} left", effect.time_left_label()));
+                if ui.small_button("End").clicked() {
+                    end = Some(index);
+                }
+            });
+        }
+        if let Some(index) = end {
+            app.session.end_effect(index);
+        }
+
+        ui.separator();
+        let data = &app.session.data;
+        let effect = &mut app.new_effect;
+        ui.horizontal(|ui| {
+            ui.label("Effect:");
+            ui.text_edit_singleline(&mut effect.name);
+        });
+        ui.horizontal(|ui| {
+            ui.label("On:");
+            egui::ComboBox::from_id_salt("effect_target")
+                .selected_text(data.effect_target_name(effect.target))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut effect.target, EffectTarget::Party, "the party");
+                    for character in &data.characters {
+                        ui.selectable_value(
+                            &mut effect.target,
+                            EffectTarget::Character(character.id),
+                            &character.name,
+                        );
+                    }
+                    for light in &data.light_sources {
+                        ui.selectable_value(
+                            &mut effect.target,
+                            EffectTarget::Light(light.id),
+                            light.name(),
+                        );
+                    }
+                });
+        });
+        ui.horizontal(|ui| {
+            ui.label("Lasts:");
+            ui.add(DragValue::new(&mut effect.remaining).range(1..=999));
+            egui::ComboBox::from_id_salt("effect_unit")
+                .selected_text(effect.unit.to_string())
+                .show_ui(ui, |ui| {
+                    for unit in EffectUnit::ALL {
+                        ui.selectable_value(&mut effect.unit, unit, unit.to_string());
+                    }
+                });
+        });
+        ui.horizontal(|ui| {
+            ui.label("When it wears off:");
+            ui.add(
+                egui::TextEdit::singleline(&mut effect.expiry_message)
+                    .hint_text("\"<effect> on <target> wears off\""),
+            );
+        });
+        if ui
+            .add_enabled(
+                !effect.name.trim().is_empty(),
+                egui::Button::new("➕ Start"),
+            )
+            .clicked()
+        {
+            let mut started = effect.clone();
+            started.name = started.name.trim().to_string();
+            effect.name.clear();
+            effect.expiry_message.clear();
+            app.session.add_effect(started);
+        }
+    });
+}
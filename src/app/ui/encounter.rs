@@ -1,20 +1,156 @@
-use crate::app::rng::DefaultRandomSource;
-use crate::app::systems::roll_encounter;
+use crate::app::ui::bestiary::{draw_bestiary_ui, draw_stat_card, MonsterAction};
+use crate::app::ui::table::draw_roll_table_editor;
 use crate::app::ShadowtrackApp;
-use egui::Ui;
+use egui::{Color32, DragValue, RichText, Ui};
+use shadowtrack_core::state::{DangerLevel, EncounterCheck, MoraleRule};
 
 pub fn draw_encounter_ui(app: &mut ShadowtrackApp, ui: &mut Ui) {
     ui.horizontal(|ui| {
         ui.collapsing("🎲 Edit Encounter Table", |ui| {
-            for entry in &mut app.data.encounter_table {
-                ui.text_edit_singleline(entry);
-            }
+            draw_roll_table_editor(ui, "encounter_table", &mut app.session.data.encounter_table);
             if ui.button("Add Encounter").clicked() {
-                app.data.encounter_table.push("New Encounter".into());
+                app.session.data.encounter_table.push("New Encounter");
+            }
+        });
+        ui.collapsing("🎲 Edit Encounter Follow-ups", |ui| {
+            let follow_ups = &mut app.session.data.encounter_follow_ups;
+            ui.checkbox(
+                &mut follow_ups.enabled,
+                "Roll follow-ups when an encounter hits",
+            );
+            ui.label("An empty table skips that follow-up. Surprise is rolled once for each side.");
+            for (label, salt, table) in [
+                ("Reaction", "reaction_table", &mut follow_ups.reaction),
+                ("Distance", "distance_table", &mut follow_ups.distance),
+                ("Surprise", "surprise_table", &mut follow_ups.surprise),
+                ("Activity", "activity_table", &mut follow_ups.activity),
+            ] {
+                ui.collapsing(label, |ui| {
+                    draw_roll_table_editor(ui, salt, table);
+                    if ui.button("Add Entry").clicked() {
+                        table.push("New Entry");
+                    }
+                });
             }
         });
+        draw_bestiary_ui(app, ui);
         if ui.button("Roll Random Encounter").clicked() {
-            roll_encounter(&mut app.data, &mut DefaultRandomSource, true)
+            app.session.roll_encounter(true)
+        }
+    });
+    draw_last_encounter(app, ui);
+    ui.horizontal(|ui| {
+        ui.label("Dice:");
+        ui.text_edit_singleline(&mut app.dice_expression);
+        if ui.button("Roll").clicked() {
+            app.last_dice_error = app
+                .session
+                .roll_dice(&app.dice_expression)
+                .err()
+                .map(|e| e.to_string());
         }
+        if let Some(error) = &app.last_dice_error {
+            ui.label(RichText::new(error).color(Color32::RED));
+        }
+    });
+}
+
+/// Stat cards for the monsters the last encounter turned up, tracking their HP and morale.
+fn draw_last_encounter(app: &mut ShadowtrackApp, ui: &mut Ui) {
+    let data = &mut app.session.data;
+    let Some(encounter) = &data.last_encounter else {
+        return;
+    };
+    ui.label(RichText::new(format!("Last encounter: {}", encounter)).strong());
+    if data.last_monsters.is_empty() {
+        return;
+    }
+    ui.horizontal(|ui| {
+        ui.label("Hit or heal by");
+        ui.add(DragValue::new(&mut app.monster_damage).range(1..=999));
+        ui.separator();
+        ui.label("Morale:");
+        let settings = &mut data.morale_settings;
+        egui::ComboBox::from_id_salt("morale_rule")
+            .selected_text(settings.rule.to_string())
+            .show_ui(ui, |ui| {
+                for rule in MoraleRule::ALL {
+                    ui.selectable_value(&mut settings.rule, rule, rule.to_string());
+                }
+            });
+        ui.checkbox(
+            &mut settings.automatic,
+            "Check when half fall or the leader dies",
+        );
     });
+    let mut action = None;
+    ui.horizontal_wrapped(|ui| {
+        for (index, group) in data.last_monsters.iter().enumerate() {
+            draw_stat_card(ui, index, group, app.monster_damage, &mut action);
+        }
+    });
+
+    match action {
+        Some(MonsterAction::Damage(group, index, amount)) => {
+            _ = app.session.damage_monster(group, index, amount)
+        }
+        Some(MonsterAction::Leader(group, index)) => {
+            if let Some(group) = app.session.data.last_monsters.get_mut(group) {
+                group.leader = (group.leader != Some(index)).then_some(index);
+            }
+        }
+        Some(MonsterAction::Morale(group)) => _ = app.session.check_morale(group, "called for"),
+        None => {}
+    }
+}
+
+/// Quick switcher for the danger level, with the custom check's odds when selected.
+pub fn draw_danger_level_switcher(app: &mut ShadowtrackApp, ui: &mut Ui) {
+    let current = app.session.data.danger_level;
+    let mut selected = current;
+    egui::ComboBox::from_id_salt("danger_level")
+        .selected_text(format!("Danger: {}", current.name()))
+        .show_ui(ui, |ui| {
+            for level in DangerLevel::PRESETS {
+                ui.selectable_value(&mut selected, level, level.to_string());
+            }
+            // Start a custom check from the current odds.
+            let custom = match current {
+                DangerLevel::Custom(_) => current,
+                _ => DangerLevel::Custom(current.check().unwrap_or(EncounterCheck {
+                    every_turns: 1,
+                    chance: 1,
+                    die: 6,
+                })),
+            };
+            ui.selectable_value(&mut selected, custom, "Custom");
+        });
+    if selected != current {
+        app.session.set_danger_level(selected);
+    }
+
+    if let DangerLevel::Custom(check) = app.session.data.danger_level {
+        // Edits are held until a drag or text entry ends, so only the final odds are logged.
+        let id = ui.make_persistent_id("custom_danger_level");
+        let mut pending = ui
+            .data_mut(|d| d.get_temp::<EncounterCheck>(id))
+            .unwrap_or(check);
+        let responses = [
+            ui.add(DragValue::new(&mut pending.chance).range(1..=pending.die)),
+            ui.label("in"),
+            ui.add(DragValue::new(&mut pending.die).range(2..=100).prefix("d")),
+            ui.label("every"),
+            ui.add(
+                DragValue::new(&mut pending.every_turns)
+                    .range(1..=12)
+                    .suffix(" turns"),
+            ),
+        ];
+        if responses.iter().any(|r| r.drag_stopped() || r.lost_focus()) {
+            ui.data_mut(|d| d.remove::<EncounterCheck>(id));
+            app.session.set_danger_level(DangerLevel::Custom(pending));
+        } else {
+            ui.data_mut(|d| d.insert_temp(id, pending));
+        }
+    }
 }
@@ -1,136 +1,478 @@
-use crate::app::rng::DefaultRandomSource;
-use crate::app::state::ShadowtrackData;
-use crate::app::systems::roll_light_event;
-use crate::app::{
-    state::{LightSource, LightSourceType},
-    ShadowtrackApp,
+use crate::app::ui::table::draw_roll_table_editor;
+use crate::app::ShadowtrackApp;
+use egui::{Color32, DragValue, RichText, Ui};
+use shadowtrack_core::dice::DiceExpr;
+use shadowtrack_core::state::{
+    BurnModel, DangerLevel, Illumination, LightSourceType, LightState, ShadowtrackData, Supply,
 };
-use egui::{Color32, RichText, Ui};
 
-fn new_light_type_label(app: &mut ShadowtrackData) -> String {
+fn new_light_type_label(app: &ShadowtrackData) -> String {
     match &app.new_light_type {
         LightSourceType::Torch => "Torch".to_string(),
         LightSourceType::Lantern => "Lantern".to_string(),
+        LightSourceType::Candle => "Candle".to_string(),
         LightSourceType::Spell(name) => format!("Spell ({})", name),
     }
 }
 
+/// Editor for one light type's burn model and its settings.
+pub fn draw_burn_model_editor(ui: &mut Ui, id_salt: &str, model: &mut BurnModel) {
+    let chance_default = || BurnModel::Chance {
+        minutes: 10,
+        dice: DiceExpr::dice(1, 6),
+        chance: 2,
+        extra_minutes: 10,
+    };
+    ui.horizontal(|ui| {
+        let name = match model {
+            BurnModel::RealTime => "Real time",
+            BurnModel::PerTurn { .. } => "Per turn",
+            BurnModel::Chance { .. } => "Chance",
+            BurnModel::Never => "Never expires",
+            BurnModel::Concentration => "Concentration",
+        };
+        egui::ComboBox::from_id_salt(("burn_model", id_salt))
+            .selected_text(name)
+            .show_ui(ui, |ui| {
+                for (label, option) in [
+                    ("Real time", BurnModel::RealTime),
+                    ("Per turn", BurnModel::PerTurn { minutes: 10 }),
+                    ("Chance", chance_default()),
+                    ("Never expires", BurnModel::Never),
+                    ("Concentration", BurnModel::Concentration),
+                ] {
+                    let selected = std::mem::discriminant(model) == std::mem::discriminant(&option);
+                    if ui.selectable_label(selected, label).clicked() && !selected {
+                        *model = option;
+                    }
+                }
+            });
+
+        match model {
+            BurnModel::PerTurn { minutes } => {
+                ui.add(
+                    DragValue::new(minutes)
+                        .range(0..=600)
+                        .suffix(" min per turn"),
+                );
+            }
+            BurnModel::Chance {
+                minutes,
+                dice,
+                chance,
+                extra_minutes,
+            } => {
+                ui.add(
+                    DragValue::new(minutes)
+                        .range(0..=600)
+                        .suffix(" min per turn"),
+                );
+                ui.label("plus");
+                ui.add(
+                    DragValue::new(extra_minutes)
+                        .range(0..=600)
+                        .suffix(" min when"),
+                );
+                // Keep the typed dice text between frames, like the table editor does.
+                let id = ui.make_persistent_id(("burn_dice", id_salt));
+                let mut text = ui
+                    .data_mut(|d| d.get_temp::<String>(id))
+                    .filter(|text| DiceExpr::parse(text).map_or(true, |parsed| parsed == *dice))
+                    .unwrap_or_else(|| dice.to_string());
+                let edit = ui.add(egui::TextEdit::singleline(&mut text).desired_width(60.0));
+                if edit.changed() {
+                    if let Ok(parsed) = DiceExpr::parse(&text) {
+                        *dice = parsed;
+                    }
+                }
+                ui.label("rolls");
+                ui.add(DragValue::new(chance).prefix("≤ "));
+                if DiceExpr::parse(&text).is_err() {
+                    ui.label(RichText::new("Invalid dice").color(Color32::RED));
+                }
+                ui.data_mut(|d| d.insert_temp(id, text));
+            }
+            BurnModel::RealTime | BurnModel::Never | BurnModel::Concentration => {}
+        }
+    });
+}
+
+/// Something done to a light from its row, applied once the list has been drawn.
+enum LightAction {
+    Snuff(usize),
+    Extinguish(usize),
+    Relight(usize),
+    Replace(usize),
+}
+
+fn light_state_color(state: LightState) -> Color32 {
+    match state {
+        LightState::Lit => Color32::WHITE,
+        LightState::Guttering => Color32::ORANGE,
+        LightState::Extinguished | LightState::Snuffed => Color32::LIGHT_BLUE,
+        LightState::BurntOut => Color32::DARK_GRAY,
+    }
+}
+
+/// Banner across the central panel while the party has no light.
+pub fn draw_darkness_banner(app: &ShadowtrackApp, ui: &mut Ui) {
+    if app.session.data.illumination() != Illumination::Darkness {
+        return;
+    }
+    egui::Frame::new()
+        .fill(Color32::from_rgb(60, 0, 0))
+        .stroke(egui::Stroke::new(2.0, Color32::RED))
+        .inner_margin(8.0)
+        .show(ui, |ui| {
+            ui.vertical_centered(|ui| {
+                ui.label(
+                    RichText::new("🌑 TOTAL DARKNESS 🌑")
+                        .color(Color32::RED)
+                        .size(28_f32)
+                        .strong(),
+                );
+                let mut note = "No light sources are lit.".to_string();
+                if let Some(level) = app.session.data.illumination.darkness_danger_level {
+                    note.push_str(&format!(" Danger: {}", level.name()));
+                }
+                ui.label(RichText::new(note).color(Color32::WHITE));
+            });
+        });
+    ui.add_space(4.0);
+}
+
 pub fn draw_torch_ui(app: &mut ShadowtrackApp, ui: &mut Ui) {
     ui.horizontal(|ui| {
         ui.label("Active Light Sources");
         ui.separator();
+        let illumination = app.session.data.illumination();
+        let color = match illumination {
+            Illumination::Bright => Color32::LIGHT_YELLOW,
+            Illumination::Dim => Color32::ORANGE,
+            Illumination::Darkness => Color32::RED,
+        };
+        ui.label(RichText::new(format!("Illumination: {}", illumination)).color(color));
+        ui.separator();
         if ui.button("➕ Add").clicked() {
             app.show_add_light_modal = true;
         }
+        let any_spent = app
+            .session
+            .data
+            .light_sources
+            .iter()
+            .any(|light| light.state == LightState::BurntOut);
+        if ui
+            .add_enabled(any_spent, egui::Button::new("🗑 Remove Spent"))
+            .clicked()
+        {
+            app.session.remove_spent_lights();
+        }
     });
     ui.separator();
-    if app.data.light_sources.is_empty() {
+    if app.session.data.light_sources.is_empty() {
         ui.label(
             RichText::new("No active light sources")
                 .color(Color32::YELLOW)
                 .size(18_f32),
         );
     } else {
-        for light in &mut app.data.light_sources {
+        let data = &app.session.data;
+        let mut action = None;
+        for (index, light) in data.light_sources.iter().enumerate() {
+            let color = light_state_color(light.state);
             ui.horizontal(|ui| {
+                ui.label(RichText::new(&light.label).color(color).size(18_f32));
+                ui.label(light.light_type.to_string());
                 ui.label(
-                    RichText::new(&light.label)
+                    RichText::new(format!("({}ft)", light.radius_feet))
                         .color(Color32::WHITE)
-                        .size(18_f32),
+                        .size(12_f32),
                 );
-                ui.label(&light.light_type.to_string());
+                let burn = light.burn_model(&data.burn_models);
                 ui.label(
-                    RichText::new(format!("({}ft)", light.radius_feet.to_string()))
-                        .color(Color32::WHITE)
-                        .size(12_f32),
+                    RichText::new(format!(
+                        "Time left: {}",
+                        burn.time_left_label(light.minutes_remaining)
+                    ))
+                    .color(color),
+                )
+                .on_hover_text(burn.to_string());
+                ui.label(
+                    RichText::new(light.state.to_string())
+                        .color(color)
+                        .italics(),
                 );
-                ui.label(format!("Time left: {} min", light.minutes_remaining));
+                if light.state.is_lit() {
+                    if ui.small_button("Snuff").clicked() {
+                        action = Some(LightAction::Snuff(index));
+                    }
+                    if ui
+                        .small_button("💨")
+                        .on_hover_text("Blown out or doused")
+                        .clicked()
+                    {
+                        action = Some(LightAction::Extinguish(index));
+                    }
+                } else if light.state.can_relight() && ui.small_button("Relight").clicked() {
+                    action = Some(LightAction::Relight(index));
+                }
+                if let Some(supply) = Supply::for_light(&light.light_type) {
+                    let spare = data.supplies_of(light).count(supply);
+                    let button = match supply {
+                        Supply::OilFlask => Some("🛢 Refuel"),
+                        _ if light.state == LightState::BurntOut => Some("🔥 Light Next"),
+                        _ => None,
+                    };
+                    if let Some(button) = button.filter(|_| spare > 0) {
+                        if ui
+                            .small_button(button)
+                            .on_hover_text(format!("{} left", supply.count_label(spare)))
+                            .clicked()
+                        {
+                            action = Some(LightAction::Replace(index));
+                        }
+                    }
+                }
             });
             if let Some(roll) = light.last_roll {
                 ui.label(format!("Last Burn Roll: {}", roll));
             }
         }
+        match action {
+            Some(LightAction::Snuff(index)) => _ = app.session.snuff_light(index),
+            Some(LightAction::Extinguish(index)) => _ = app.session.extinguish_light(index),
+            Some(LightAction::Relight(index)) => _ = app.session.relight_light(index),
+            Some(LightAction::Replace(index)) => _ = app.session.replace_from_supply(index),
+            None => {}
+        }
     }
     ui.separator();
+    draw_supplies_ui(app, ui);
+    ui.collapsing("🔥 Edit Burn Rules", |ui| {
+        ui.horizontal(|ui| {
+            ui.label("Warn when a light has");
+            ui.add(
+                DragValue::new(&mut app.session.data.light_warning_minutes)
+                    .range(0..=120)
+                    .suffix(" min"),
+            );
+            ui.label("or less left");
+        });
+        let settings = &mut app.session.data.illumination;
+        ui.horizontal(|ui| {
+            ui.label("Bright light from a");
+            ui.add(
+                DragValue::new(&mut settings.bright_radius_feet)
+                    .range(0..=200)
+                    .suffix("ft"),
+            );
+            ui.label("radius or more");
+        });
+        ui.horizontal(|ui| {
+            ui.label("In total darkness, switch danger to");
+            let selected = settings
+                .darkness_danger_level
+                .map_or("Don't switch".to_string(), |level| level.name().to_string());
+            egui::ComboBox::from_id_salt("darkness_danger_level")
+                .selected_text(selected)
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut settings.darkness_danger_level, None, "Don't switch");
+                    for level in DangerLevel::PRESETS {
+                        ui.selectable_value(
+                            &mut settings.darkness_danger_level,
+                            Some(level),
+                            level.to_string(),
+                        );
+                    }
+                });
+        });
+        let models = &mut app.session.data.burn_models;
+        egui::Grid::new("burn_models").show(ui, |ui| {
+            for (label, model) in [
+                ("Torch", &mut models.torch),
+                ("Lantern", &mut models.lantern),
+                ("Candle", &mut models.candle),
+                ("Spell", &mut models.spell),
+            ] {
+                ui.label(label);
+                draw_burn_model_editor(ui, label, model);
+                ui.end_row();
+            }
+        });
+    });
     ui.horizontal(|ui| {
         ui.collapsing("🎲 Edit Torch Event Table", |ui| {
-            for entry in &mut app.data.torch_event_table {
-                ui.text_edit_singleline(entry);
-            }
+            draw_roll_table_editor(
+                ui,
+                "torch_event_table",
+                &mut app.session.data.torch_event_table,
+            );
             if ui.button("Add Torch Event").clicked() {
-                app.data.torch_event_table.push("New Torch Event".into());
+                app.session.data.torch_event_table.push("New Torch Event");
             }
         });
         if ui.button("Roll Torch Event").clicked() {
-            roll_light_event(&mut app.data, &mut DefaultRandomSource)
+            app.session.roll_light_event()
         };
     });
 
     if app.show_add_light_modal {
-        egui::Window::new("Add Light Source")
-            .collapsible(false)
-            .resizable(false)
-            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
-            .show(ui.ctx(), |ui| {
-                ui.horizontal(|ui| {
-                    ui.label("Owner:");
-                    ui.text_edit_singleline(&mut app.data.new_light_label);
-                });
-                ui.horizontal(|ui| {
-                    ui.label("Radius:");
-                    ui.add(egui::DragValue::new(&mut app.data.new_light_range).range(5..=100));
-                });
+        draw_add_light_modal(app, ui);
+    }
+}
 
-                ui.horizontal(|ui| {
-                    ui.label("Type:");
-                    egui::ComboBox::from_id_salt("light_type")
-                        .selected_text(new_light_type_label(&mut app.data))
-                        .show_ui(ui, |ui| {
-                            ui.selectable_value(
-                                &mut app.data.new_light_type,
-                                LightSourceType::Torch,
-                                "Torch",
-                            );
-                            ui.selectable_value(
-                                &mut app.data.new_light_type,
-                                LightSourceType::Lantern,
-                                "Lantern",
-                            );
+/// How spare torches, oil flasks and candles are used.
+fn draw_supplies_ui(app: &mut ShadowtrackApp, ui: &mut Ui) {
+    ui.collapsing("🎒 Supplies", |ui| {
+        let settings = &mut app.session.data.supply_settings;
+        ui.checkbox(
+            &mut settings.auto_replace,
+            "Light the next one as soon as a light burns out",
+        );
+        ui.horizontal(|ui| {
+            ui.label("Burns for:");
+            for (label, minutes) in [
+                ("Torch", &mut settings.torch_minutes),
+                ("Oil flask", &mut settings.oil_flask_minutes),
+                ("Candle", &mut settings.candle_minutes),
+            ] {
+                ui.label(label);
+                ui.add(DragValue::new(minutes).range(1..=480).suffix(" min"));
+            }
+        });
+        ui.label("Each character's spares are kept on the party roster.");
+    });
+}
+
+fn draw_add_light_modal(app: &mut ShadowtrackApp, ui: &mut Ui) {
+    egui::Window::new("Add Light Source")
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+        .show(ui.ctx(), |ui| {
+            let data = &mut app.session.data;
+            ui.horizontal(|ui| {
+                ui.label("Template:");
+                let mut picked = None;
+                egui::ComboBox::from_id_salt("light_template")
+                    .selected_text("Pick a template…")
+                    .show_ui(ui, |ui| {
+                        for (index, template) in data.light_templates.iter().enumerate() {
+                            if ui.selectable_label(false, &template.name).clicked() {
+                                picked = Some(index);
+                            }
+                        }
+                    });
+                if let Some(index) = picked {
+                    data.apply_light_template(index);
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.label("Owner:");
+                let owner = data
+                    .new_light_owner
+                    .and_then(|id| data.character(id))
+                    .map_or("Nobody in the party", |character| &character.name);
+                egui::ComboBox::from_id_salt("light_owner")
+                    .selected_text(owner)
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut data.new_light_owner, None, "Nobody in the party");
+                        for character in &data.characters {
                             ui.selectable_value(
-                                &mut app.data.new_light_type,
-                                LightSourceType::Spell(String::new()),
-                                "Spell",
+                                &mut data.new_light_owner,
+                                Some(character.id),
+                                &character.name,
                             );
-                        });
+                        }
+                    });
+            });
+            if data.new_light_owner.is_none() {
+                ui.horizontal(|ui| {
+                    ui.label("Label:");
+                    ui.text_edit_singleline(&mut data.new_light_label);
                 });
+            }
+            ui.horizontal(|ui| {
+                ui.label("Radius:");
+                ui.add(
+                    DragValue::new(&mut data.new_light_range)
+                        .range(5..=100)
+                        .suffix("ft"),
+                );
+                ui.label("Duration:");
+                ui.add(
+                    DragValue::new(&mut data.new_light_minutes)
+                        .range(1..=1440)
+                        .suffix(" min"),
+                );
+            });
 
-                if let LightSourceType::Spell(name) = &mut app.data.new_light_type {
-                    ui.horizontal(|ui| {
-                        ui.label("Spell:");
-                        ui.text_edit_singleline(name);
+            ui.horizontal(|ui| {
+                ui.label("Type:");
+                let type_before = data.new_light_type.clone();
+                egui::ComboBox::from_id_salt("light_type")
+                    .selected_text(new_light_type_label(data))
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(
+                            &mut data.new_light_type,
+                            LightSourceType::Torch,
+                            "Torch",
+                        );
+                        ui.selectable_value(
+                            &mut data.new_light_type,
+                            LightSourceType::Lantern,
+                            "Lantern",
+                        );
+                        ui.selectable_value(
+                            &mut data.new_light_type,
+                            LightSourceType::Candle,
+                            "Candle",
+                        );
+                        ui.selectable_value(
+                            &mut data.new_light_type,
+                            LightSourceType::Spell(String::new()),
+                            "Spell",
+                        );
                     });
+                // A template's burn model belongs with its type.
+                if data.new_light_type != type_before {
+                    data.new_light_burn = None;
                 }
-                ui.separator();
+            });
+
+            if let LightSourceType::Spell(name) = &mut data.new_light_type {
                 ui.horizontal(|ui| {
-                    if ui.button("Add").clicked() {
-                        if !app.data.new_light_label.is_empty() {
-                            app.data.light_sources.push(LightSource {
-                                label: app.data.new_light_label.clone(),
-                                radius_feet: app.data.new_light_range,
-                                light_type: app.data.new_light_type.clone(),
-                                minutes_remaining: 60,
-                                last_roll: None,
-                            });
-                            app.data.new_light_label.clear();
-                            app.data.new_light_range = 20;
-                            app.data.new_light_type = LightSourceType::Torch;
-                            app.show_add_light_modal = false;
-                        }
-                    }
-                    if ui.button("Cancel").clicked() {
+                    ui.label("Spell:");
+                    ui.text_edit_singleline(name);
+                });
+            }
+            ui.horizontal(|ui| {
+                let burn = data
+                    .new_light_burn
+                    .as_ref()
+                    .unwrap_or_else(|| data.burn_models.for_type(&data.new_light_type));
+                ui.label(format!("Burn: {}", burn));
+                if data.new_light_burn.is_some()
+                    && ui
+                        .small_button("✖")
+                        .on_hover_text("Use the type's burn rule")
+                        .clicked()
+                {
+                    data.new_light_burn = None;
+                }
+            });
+            ui.separator();
+            ui.horizontal(|ui| {
+                if ui.button("Add").clicked() {
+                    if let Some(light) = app.session.data.take_new_light() {
+                        app.session.add_light(light);
                         app.show_add_light_modal = false;
                     }
-                });
+                }
+                if ui.button("Cancel").clicked() {
+                    app.show_add_light_modal = false;
+                }
             });
-    }
+        });
 }
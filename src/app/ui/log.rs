@@ -1,23 +1,325 @@
 use crate::app::ShadowtrackApp;
 use eframe::epaint::Color32;
-use egui::Ui;
+use egui::{DragValue, RichText, Ui};
+use shadowtrack_core::event_log::{LogEvent, LogFilter, LoggedEvent};
 
-pub fn draw_log_ui(app: &ShadowtrackApp, ui: &mut Ui) {
+/// Text being written into the log: a GM note, or a new version of a logged event.
+pub struct LogDraft {
+    pub turn: u32,
+    /// The event being edited, or where the note goes in its turn (the end if `None`).
+    pub index: Option<usize>,
+    pub text: String,
+    pub note: bool,
+}
+
+/// What the log panel is in the middle of writing.
+#[derive(Default)]
+pub struct LogEditor {
+    /// Tag being typed in a log event's right-click menu.
+    pub new_tag: String,
+    pub draft: Option<LogDraft>,
+}
+
+/// A change made to a logged event from its right-click menu, or a draft being saved, applied
+/// once the log has been drawn.
+enum LogAction {
+    Tag(u32, usize, String),
+    Untag(u32, usize, String),
+    Edit(u32, usize, String),
+    Strike(u32, usize, bool),
+    Move(u32, usize, usize),
+    Note(u32, Option<usize>, String),
+}
+
+pub fn draw_log_ui(app: &mut ShadowtrackApp, ui: &mut Ui) {
+    let tags = app.session.data.log_tags();
+    draw_filter_bar(&mut app.log_filter, &tags, ui);
+    let mut actions = Vec::new();
+    draw_note_draft(
+        &mut app.log_editor.draft,
+        app.session.data.turn,
+        ui,
+        &mut actions,
+    );
+    ui.separator();
+
+    let shown = app.log_filter.apply(&app.session.data.event_log);
     egui::ScrollArea::vertical().show(ui, |ui| {
-        for entry in app.data.event_log.iter().rev() {
+        for (entry, indices) in shown.iter().rev() {
             // Turn Label
             ui.label(
-                egui::RichText::from(format!("📜 Turn: {}", entry.turn))
+                RichText::from(format!("📜 Turn: {}", entry.turn))
                     .size(18_f32)
                     .color(Color32::LIGHT_YELLOW)
                     .strong(),
             );
-            for event in entry.events.iter().rev() {
-                ui.label(format!("   {}", event));
+            for &index in indices.iter().rev() {
+                let at = (entry.turn, index, entry.events.len());
+                draw_event(
+                    ui,
+                    at,
+                    &entry.events[index],
+                    &tags,
+                    &mut app.log_editor,
+                    &mut actions,
+                );
             }
         }
-        if app.data.event_log.is_empty() {
+        if app.session.data.event_log.is_empty() {
             ui.label("No events logged");
+        } else if shown.is_empty() {
+            ui.label("No events match the filter");
+        }
+    });
+
+    for action in actions {
+        match action {
+            LogAction::Tag(turn, index, tag) => _ = app.session.tag_event(turn, index, &tag),
+            LogAction::Untag(turn, index, tag) => _ = app.session.untag_event(turn, index, &tag),
+            LogAction::Edit(turn, index, text) => _ = app.session.edit_event(turn, index, &text),
+            LogAction::Strike(turn, index, deleted) => {
+                _ = app.session.strike_event(turn, index, deleted)
+            }
+            LogAction::Move(turn, from, to) => _ = app.session.move_event(turn, from, to),
+            LogAction::Note(turn, index, text) => app.session.add_note(turn, index, &text),
+        }
+    }
+}
+
+/// Text search, tag toggles and a turn range for narrowing down the log.
+fn draw_filter_bar(filter: &mut LogFilter, tags: &[String], ui: &mut Ui) {
+    ui.horizontal(|ui| {
+        ui.add(
+            egui::TextEdit::singleline(&mut filter.text)
+                .hint_text("🔎 Search the log")
+                .desired_width(140.0),
+        );
+        if ui
+            .add_enabled(!filter.is_empty(), egui::Button::new("Clear"))
+            .clicked()
+        {
+            *filter = LogFilter::default();
+        }
+    });
+    ui.horizontal_wrapped(|ui| {
+        for tag in tags {
+            if ui
+                .selectable_label(filter.tags.contains(tag), tag)
+                .clicked()
+            {
+                filter.toggle_tag(tag);
+            }
+        }
+    });
+    ui.horizontal(|ui| {
+        draw_turn_bound(ui, "From turn", &mut filter.from_turn);
+        draw_turn_bound(ui, "to", &mut filter.to_turn);
+    });
+}
+
+fn draw_turn_bound(ui: &mut Ui, label: &str, bound: &mut Option<u32>) {
+    let mut enabled = bound.is_some();
+    if ui.checkbox(&mut enabled, label).changed() {
+        *bound = enabled.then_some(0);
+    }
+    if let Some(turn) = bound {
+        ui.add(DragValue::new(turn));
+    }
+}
+
+/// The GM note being written, or a button to start one on the current turn.
+fn draw_note_draft(
+    draft: &mut Option<LogDraft>,
+    current_turn: u32,
+    ui: &mut Ui,
+    actions: &mut Vec<LogAction>,
+) {
+    let Some(note) = draft.as_mut().filter(|draft| draft.note) else {
+        if ui.button("📝 Add Note").clicked() {
+            *draft = Some(LogDraft {
+                turn: current_turn,
+                index: None,
+                text: String::new(),
+                note: true,
+            });
+        }
+        return;
+    };
+    ui.horizontal(|ui| {
+        ui.label("Note on turn");
+        ui.add(DragValue::new(&mut note.turn));
+        if let Some(index) = note.index {
+            ui.label(format!("after event {}", index));
         }
     });
+    ui.add(
+        egui::TextEdit::multiline(&mut note.text)
+            .hint_text("What actually happened")
+            .desired_rows(2),
+    );
+    let mut done = false;
+    ui.horizontal(|ui| {
+        if ui
+            .add_enabled(!note.text.trim().is_empty(), egui::Button::new("Save Note"))
+            .clicked()
+        {
+            let text = note.text.trim().to_string();
+            actions.push(LogAction::Note(note.turn, note.index, text));
+            done = true;
+        }
+        done |= ui.button("Cancel").clicked();
+    });
+    if done {
+        *draft = None;
+    }
+}
+
+/// One logged event with its tags and encounter follow-ups, or a field for editing it. `at` is
+/// its turn, its index in the turn and how many events the turn has. Right-clicking it offers
+/// tags, editing, striking out, moving and noting after it.
+fn draw_event(
+    ui: &mut Ui,
+    at: (u32, usize, usize),
+    event: &LoggedEvent,
+    tags: &[String],
+    editor: &mut LogEditor,
+    actions: &mut Vec<LogAction>,
+) {
+    let (turn, index, count) = at;
+    if let Some(draft) = editor
+        .draft
+        .as_mut()
+        .filter(|draft| !draft.note && draft.turn == turn && draft.index == Some(index))
+    {
+        let mut done = false;
+        ui.horizontal(|ui| {
+            ui.text_edit_singleline(&mut draft.text);
+            if ui.small_button("Save").clicked() {
+                actions.push(LogAction::Edit(turn, index, draft.text.clone()));
+                done = true;
+            }
+            done |= ui.small_button("Cancel").clicked();
+        });
+        if done {
+            editor.draft = None;
+        }
+        return;
+    }
+
+    let label = ui
+        .horizontal_wrapped(|ui| {
+            let mut text = RichText::from(format!("   [{}] {}", event.game_time_label(), event));
+            if event.event == LogEvent::Note {
+                text = text.italics().color(Color32::LIGHT_GREEN);
+            }
+            if !event.edits.is_empty() {
+                text = text.underline();
+            }
+            if event.deleted {
+                text = text.strikethrough().weak();
+            }
+            let label = ui.add(egui::Label::new(text).sense(egui::Sense::click()));
+            for tag in &event.tags {
+                ui.label(
+                    RichText::from(format!("#{}", tag))
+                        .color(Color32::LIGHT_BLUE)
+                        .small(),
+                );
+            }
+            label
+        })
+        .inner;
+    let detail = event.detail.as_ref();
+    let hover: Vec<String> = event
+        .wall_clock_label()
+        .into_iter()
+        .chain(
+            (!event.edits.is_empty())
+                .then(|| format!("Edited, first logged as: {}", event.original_text())),
+        )
+        .chain(
+            detail
+                .and_then(|d| d.roll_tree.as_ref())
+                .map(|t| t.to_string()),
+        )
+        .collect();
+    let label = if hover.is_empty() {
+        label
+    } else {
+        label.on_hover_text(hover.join("\n\n"))
+    };
+    label.context_menu(|ui| {
+        if ui.button("✏ Edit").clicked() {
+            editor.draft = Some(LogDraft {
+                turn,
+                index: Some(index),
+                text: event.text.clone(),
+                note: false,
+            });
+            ui.close_menu();
+        }
+        let strike = if event.deleted {
+            "Restore"
+        } else {
+            "Strike Out"
+        };
+        if ui.button(strike).clicked() {
+            actions.push(LogAction::Strike(turn, index, !event.deleted));
+            ui.close_menu();
+        }
+        if ui
+            .add_enabled(index > 0, egui::Button::new("Move Earlier"))
+            .clicked()
+        {
+            actions.push(LogAction::Move(turn, index, index - 1));
+        }
+        if ui
+            .add_enabled(index + 1 < count, egui::Button::new("Move Later"))
+            .clicked()
+        {
+            actions.push(LogAction::Move(turn, index, index + 1));
+        }
+        if ui.button("📝 Add Note After").clicked() {
+            editor.draft = Some(LogDraft {
+                turn,
+                index: Some(index + 1),
+                text: String::new(),
+                note: true,
+            });
+            ui.close_menu();
+        }
+        ui.separator();
+        ui.label("Tags");
+        for tag in tags {
+            let mut on = event.has_tag(tag);
+            if ui.checkbox(&mut on, tag).changed() {
+                actions.push(if on {
+                    LogAction::Tag(turn, index, tag.clone())
+                } else {
+                    LogAction::Untag(turn, index, tag.clone())
+                });
+            }
+        }
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::TextEdit::singleline(&mut editor.new_tag)
+                    .hint_text("New tag")
+                    .desired_width(80.0),
+            );
+            if ui.button("Add").clicked() && !editor.new_tag.trim().is_empty() {
+                let tag = std::mem::take(&mut editor.new_tag);
+                actions.push(LogAction::Tag(turn, index, tag));
+                ui.close_menu();
+            }
+        });
+    });
+    if let Some(encounter) = detail.and_then(|d| d.encounter.as_ref()) {
+        for follow_up in encounter.to_string().lines() {
+            ui.label(
+                RichText::from(format!("      {}", follow_up))
+                    .color(Color32::LIGHT_GRAY)
+                    .small(),
+            );
+        }
+    }
 }
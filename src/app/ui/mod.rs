@@ -1,4 +1,11 @@
+pub mod autosave;
+pub mod bestiary;
 pub mod clock;
+pub mod combat;
+pub mod effects;
 pub mod encounter;
 pub mod lights;
 pub mod log;
+pub mod roster;
+pub mod table;
+pub mod templates;
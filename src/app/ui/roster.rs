@@ -0,0 +1,153 @@
+use crate::app::ShadowtrackApp;
+use egui::{Color32, DragValue, RichText, Ui};
+use shadowtrack_core::state::Supply;
+
+/// Something done to the roster from a character's card, applied once the roster has been
+/// drawn.
+enum RosterAction {
+    Rename(u32, String),
+    Remove(u32),
+    Light(u32, Supply),
+}
+
+/// The party: each character's stats, conditions, supplies and the lights they carry.
+pub fn draw_roster_ui(app: &mut ShadowtrackApp, ui: &mut Ui) {
+    ui.heading("Party");
+    ui.horizontal(|ui| {
+        ui.text_edit_singleline(&mut app.new_character_name);
+        let name = app.new_character_name.trim();
+        if ui
+            .add_enabled(
+                !name.is_empty() && app.session.data.find_character(name).is_none(),
+                egui::Button::new("➕ Add"),
+            )
+            .clicked()
+        {
+            app.session.data.add_character(name);
+            app.new_character_name.clear();
+        }
+    });
+    ui.separator();
+    if app.session.data.characters.is_empty() {
+        ui.label(RichText::new("No characters yet").color(Color32::YELLOW));
+        return;
+    }
+
+    let mut action = None;
+    let data = &mut app.session.data;
+    let (characters, lights) = (&mut data.characters, &data.light_sources);
+    for character in characters.iter_mut() {
+        let id = character.id;
+        let color = if character.is_down() {
+            Color32::RED
+        } else {
+            Color32::WHITE
+        };
+        egui::CollapsingHeader::new(RichText::new(character.to_string()).color(color))
+            .id_salt(("character", id))
+            .show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    let mut name = character.name.clone();
+                    if ui.text_edit_singleline(&mut name).changed() && !name.trim().is_empty() {
+                        action = Some(RosterAction::Rename(id, name));
+                    }
+                    if ui.small_button("🗑").on_hover_text("Remove").clicked() {
+                        action = Some(RosterAction::Remove(id));
+                    }
+                });
+                egui::Grid::new(("character_stats", id)).show(ui, |ui| {
+                    ui.label("HP");
+                    ui.horizontal(|ui| {
+                        ui.add(DragValue::new(&mut character.hp).range(-99..=character.max_hp));
+                        ui.label("/");
+                        let mut max_hp = character.max_hp;
+                        if ui.add(DragValue::new(&mut max_hp).range(1..=999)).changed() {
+                            character.set_max_hp(max_hp);
+                        }
+                    });
+                    ui.end_row();
+                    ui.label("AC");
+                    ui.add(DragValue::new(&mut character.ac).range(0..=30));
+                    ui.end_row();
+                    ui.label("Level");
+                    ui.add(DragValue::new(&mut character.level).range(0..=20));
+                    ui.end_row();
+                    ui.label("Luck");
+                    ui.add(DragValue::new(&mut character.luck_tokens).range(0..=9));
+                    ui.end_row();
+                });
+
+                ui.horizontal_wrapped(|ui| {
+                    ui.label("Conditions:");
+                    let mut remove = None;
+                    for condition in &character.conditions {
+                        if ui
+                            .small_button(format!("{} ✖", condition))
+                            .on_hover_text("Remove")
+                            .clicked()
+                        {
+                            remove = Some(condition.clone());
+                        }
+                    }
+                    if let Some(condition) = remove {
+                        character.remove_condition(&condition);
+                    }
+                });
+                ui.horizontal(|ui| {
+                    let text = app.new_conditions.entry(id).or_default();
+                    ui.add(egui::TextEdit::singleline(text).desired_width(120.0));
+                    if ui.small_button("Add condition").clicked() && character.add_condition(text) {
+                        text.clear();
+                    }
+                });
+
+                ui.horizontal(|ui| {
+                    ui.label("Supplies:");
+                    for supply in Supply::ALL {
+                        ui.add(
+                            DragValue::new(character.supplies.count_mut(supply))
+                                .range(0..=99)
+                                .custom_formatter(move |count, _| supply.count_label(count as u32)),
+                        );
+                    }
+                });
+                ui.horizontal(|ui| {
+                    for (label, supply) in [
+                        ("Light torch", Supply::Torch),
+                        ("Light candle", Supply::Candle),
+                    ] {
+                        if ui
+                            .add_enabled(
+                                character.supplies.count(supply) > 0,
+                                egui::Button::new(label),
+                            )
+                            .clicked()
+                        {
+                            action = Some(RosterAction::Light(id, supply));
+                        }
+                    }
+                });
+
+                let carried: Vec<String> = lights
+                    .iter()
+                    .filter(|light| light.owner == Some(id))
+                    .map(|light| format!("{} ({})", light.light_type, light.state))
+                    .collect();
+                if carried.is_empty() {
+                    ui.label(RichText::new("Carries no lights").italics());
+                } else {
+                    ui.label(format!("Carries: {}", carried.join(", ")));
+                }
+            });
+    }
+
+    match action {
+        Some(RosterAction::Rename(id, name)) => app.session.data.rename_character(id, &name),
+        Some(RosterAction::Remove(id)) => {
+            app.session.data.remove_character(id);
+            app.new_conditions.remove(&id);
+        }
+        Some(RosterAction::Light(id, supply)) => _ = app.session.light_from_supply(id, supply),
+        None => {}
+    }
+}
@@ -0,0 +1,121 @@
+use crate::app::ShadowtrackApp;
+use egui::{Color32, DragValue, RichText, Ui};
+use shadowtrack_core::dice::DiceExpr;
+use shadowtrack_core::tables::RollTable;
+
+/// Small text field for a dice expression, keeping half-typed text between frames and only
+/// changing `dice` once the text parses.
+pub fn draw_dice_field(ui: &mut Ui, id_salt: impl std::hash::Hash, dice: &mut DiceExpr) {
+    let id = ui.make_persistent_id(id_salt);
+    let mut text = ui
+        .data_mut(|d| d.get_temp::<String>(id))
+        .filter(|text| DiceExpr::parse(text).map_or(true, |parsed| parsed == *dice))
+        .unwrap_or_else(|| dice.to_string());
+    let edit = ui.add(egui::TextEdit::singleline(&mut text).desired_width(60.0));
+    if edit.changed() {
+        if let Ok(parsed) = DiceExpr::parse(&text) {
+            *dice = parsed;
+        }
+    }
+    if DiceExpr::parse(&text).is_err() {
+        edit.on_hover_text(RichText::new("Invalid dice").color(Color32::RED));
+    }
+    ui.data_mut(|d| d.insert_temp(id, text));
+}
+
+/// Editor for a roll table's dice, weights or ranges, and entry text.
+pub fn draw_roll_table_editor(ui: &mut Ui, id_salt: &str, table: &mut RollTable) {
+    let id = ui.make_persistent_id(id_salt);
+    // Keep the typed dice text between frames, so half-typed expressions aren't thrown away.
+    let mut dice_text = ui
+        .data_mut(|d| d.get_temp::<String>(id))
+        .filter(|text| match DiceExpr::parse(text) {
+            Ok(dice) => dice == table.dice,
+            Err(_) => !table.weighted,
+        })
+        .unwrap_or_else(|| table.dice.to_string());
+
+    ui.horizontal(|ui| {
+        ui.label("Dice:");
+        ui.add_enabled_ui(!table.weighted, |ui| {
+            if ui.text_edit_singleline(&mut dice_text).changed() {
+                if let Ok(dice) = DiceExpr::parse(&dice_text) {
+                    table.dice = dice;
+                }
+            }
+        });
+        if ui.checkbox(&mut table.weighted, "Weighted").changed() && table.weighted {
+            table.repack();
+        }
+    });
+    if table.weighted {
+        dice_text = table.dice.to_string();
+    } else if let Err(e) = DiceExpr::parse(&dice_text) {
+        ui.label(RichText::new(e.to_string()).color(Color32::RED));
+    }
+    ui.data_mut(|d| d.insert_temp(id, dice_text));
+
+    let mut remove = None;
+    let mut reweighed = false;
+    egui::Grid::new(id.with("entries")).show(ui, |ui| {
+        for (index, entry) in table.entries.iter_mut().enumerate() {
+            if table.weighted {
+                let mut weight = entry.weight();
+                if ui
+                    .add(DragValue::new(&mut weight).range(0..=100).prefix("x"))
+                    .changed()
+                {
+                    entry.high = entry.low + weight as i64 - 1;
+                    reweighed = true;
+                }
+                ui.label(entry.range_label());
+            } else {
+                ui.add(DragValue::new(&mut entry.low));
+                ui.add(DragValue::new(&mut entry.high));
+            }
+            ui.text_edit_singleline(&mut entry.text);
+            if ui.small_button("🗑").clicked() {
+                remove = Some(index);
+            }
+            ui.end_row();
+        }
+    });
+    if reweighed {
+        table.repack();
+    }
+    if let Some(index) = remove {
+        table.remove(index);
+    }
+
+    for problem in table.problems() {
+        ui.label(RichText::new(problem).color(Color32::YELLOW));
+    }
+}
+
+/// Editor for the named tables that other tables reference as `[[Name]]`.
+pub fn draw_named_tables_ui(app: &mut ShadowtrackApp, ui: &mut Ui) {
+    ui.collapsing("📚 Edit Sub-tables", |ui| {
+        ui.label("Reference a sub-table from any table entry as [[Name]]. Dice like 1d4 are rolled in place.");
+        let mut remove = None;
+        for (index, table) in app.session.data.tables.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                ui.label("Name:");
+                ui.text_edit_singleline(&mut table.name);
+                if ui.small_button("🗑").clicked() {
+                    remove = Some(index);
+                }
+            });
+            draw_roll_table_editor(ui, &format!("named_table_{index}"), table);
+            ui.separator();
+        }
+        if let Some(index) = remove {
+            app.session.data.tables.remove(index);
+        }
+        if ui.button("Add Sub-table").clicked() {
+            app.session
+                .data
+                .tables
+                .push(RollTable::uniform(["New Entry"]).with_name("New Table"));
+        }
+    });
+}
@@ -0,0 +1,106 @@
+use crate::app::save::{export_light_library, import_light_library};
+use crate::app::ui::lights::draw_burn_model_editor;
+use crate::app::ShadowtrackApp;
+use egui::{DragValue, Ui};
+use shadowtrack_core::state::{LightSourceType, LightTemplate};
+
+/// Editor for the light templates offered when adding a light.
+pub fn draw_light_templates_ui(app: &mut ShadowtrackApp, ui: &mut Ui) {
+    ui.collapsing("📋 Edit Light Templates", |ui| {
+        let data = &mut app.session.data;
+        let (templates, burn_models) = (&mut data.light_templates, &data.burn_models);
+        let mut remove = None;
+        for (index, template) in templates.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                ui.label("Name:");
+                ui.text_edit_singleline(&mut template.name);
+                if ui.small_button("🗑").clicked() {
+                    remove = Some(index);
+                }
+            });
+            ui.horizontal(|ui| {
+                egui::ComboBox::from_id_salt(("template_type", index))
+                    .selected_text(match &template.light_type {
+                        LightSourceType::Torch => "Torch",
+                        LightSourceType::Lantern => "Lantern",
+                        LightSourceType::Candle => "Candle",
+                        LightSourceType::Spell(_) => "Spell",
+                    })
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(
+                            &mut template.light_type,
+                            LightSourceType::Torch,
+                            "Torch",
+                        );
+                        ui.selectable_value(
+                            &mut template.light_type,
+                            LightSourceType::Lantern,
+                            "Lantern",
+                        );
+                        ui.selectable_value(
+                            &mut template.light_type,
+                            LightSourceType::Candle,
+                            "Candle",
+                        );
+                        if !matches!(template.light_type, LightSourceType::Spell(_))
+                            && ui.selectable_label(false, "Spell").clicked()
+                        {
+                            template.light_type = LightSourceType::Spell(String::new());
+                        }
+                    });
+                if let LightSourceType::Spell(name) = &mut template.light_type {
+                    ui.add(egui::TextEdit::singleline(name).desired_width(80.0));
+                }
+                ui.add(
+                    DragValue::new(&mut template.radius_feet)
+                        .range(5..=100)
+                        .suffix("ft"),
+                );
+                ui.add(
+                    DragValue::new(&mut template.minutes)
+                        .range(1..=1440)
+                        .suffix(" min"),
+                );
+            });
+            ui.horizontal(|ui| {
+                let mut own_burn = template.burn.is_some();
+                if ui.checkbox(&mut own_burn, "Own burn rule").changed() {
+                    template.burn = own_burn
+                        .then(|| burn_models.for_type(&template.light_type))
+                        .cloned();
+                }
+                if let Some(burn) = &mut template.burn {
+                    draw_burn_model_editor(ui, &format!("template_{index}"), burn);
+                }
+            });
+            ui.separator();
+        }
+        if let Some(index) = remove {
+            templates.remove(index);
+        }
+        let mut import = false;
+        ui.horizontal(|ui| {
+            if ui.button("Add Template").clicked() {
+                templates.push(LightTemplate {
+                    name: "New Light".to_string(),
+                    light_type: LightSourceType::Torch,
+                    radius_feet: 30,
+                    minutes: 60,
+                    burn: None,
+                });
+            }
+            if ui.button("Export Library…").clicked() {
+                if let Err(e) = export_light_library(templates) {
+                    eprintln!("Failed to export light templates: {}", e);
+                }
+            }
+            import = ui.button("Import Library…").clicked();
+        });
+        if import {
+            match import_light_library() {
+                Ok(imported) => data.import_light_templates(imported),
+                Err(e) => eprintln!("Failed to import light templates: {}", e),
+            }
+        }
+    });
+}
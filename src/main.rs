@@ -5,7 +5,7 @@ fn main() {
     eframe::run_native(
         "Shadowtrack",
         options,
-        Box::new(|_cc| Ok(Box::new(app::ShadowtrackApp::default()))),
+        Box::new(|_cc| Ok(Box::new(app::ShadowtrackApp::with_autosave()))),
     )
     .expect("Failed to start application");
 }